@@ -0,0 +1,72 @@
+#![feature(allocator_api)]
+
+use {
+    criterion::{black_box, criterion_group, criterion_main, Criterion},
+    std::alloc::Global,
+    storage_api::{alloc_box, inline_box, AllocStorage, Box, RawVec, Vec},
+};
+
+fn box_new(c: &mut Criterion) {
+    let mut group = c.benchmark_group("box_new");
+    group.bench_function("std", |b| {
+        b.iter(|| drop(black_box(std::boxed::Box::new(black_box(7u64)))))
+    });
+    group.bench_function("AllocStorage", |b| {
+        b.iter(|| {
+            drop(black_box(Box::new_in(
+                black_box(7u64),
+                AllocStorage::new(Global),
+            )))
+        })
+    });
+    group.finish();
+}
+
+fn vec_push(c: &mut Criterion) {
+    const LEN: u32 = 1000;
+
+    let mut group = c.benchmark_group("vec_push");
+    group.bench_function("std", |b| {
+        b.iter(|| {
+            let mut vec = std::vec::Vec::new();
+            for i in 0..LEN {
+                vec.push(black_box(i));
+            }
+            vec
+        })
+    });
+    group.bench_function("AllocStorage", |b| {
+        b.iter(|| {
+            let mut vec = Vec::new_in(AllocStorage::new(Global));
+            for i in 0..LEN {
+                vec.push(black_box(i));
+            }
+            vec
+        })
+    });
+    group.bench_function("RawVec", |b| {
+        b.iter(|| {
+            let mut raw = RawVec::new_in(AllocStorage::new(Global));
+            for i in 0..LEN {
+                raw.grow_amortized(i as usize + 1).unwrap();
+                raw.as_mut()[i as usize].write(black_box(i));
+            }
+            raw
+        })
+    });
+    group.finish();
+}
+
+fn small_box(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_box");
+    group.bench_function("inline", |b| {
+        b.iter(|| drop(black_box(inline_box(black_box([7u64; 4])))))
+    });
+    group.bench_function("alloc", |b| {
+        b.iter(|| drop(black_box(alloc_box(black_box([7u64; 4])))))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, box_new, vec_push, small_box);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+macro_rules! align_types {
+    ($($name:ident = $align:literal),* $(,)?) => {$(
+        #[doc = concat!(
+            "A data store of (at least) `N` bytes, aligned to ", stringify!($align), ".\n",
+            "\n",
+            "For use as the `DataStore` parameter of e.g. [`InlineStorage`](crate::InlineStorage).\n",
+            "The size is `N` rounded up to a multiple of the alignment.",
+        )]
+        #[repr(C, align($align))]
+        #[derive(Clone, Copy)]
+        pub struct $name<const N: usize>(pub [u8; N]);
+
+        impl SupportedAlignment for Alignment<$align> {
+            type DataStore<const N: usize> = $name<N>;
+        }
+    )*};
+}
+
+align_types! {
+    Align1 = 1,
+    Align2 = 2,
+    Align4 = 4,
+    Align8 = 8,
+    Align16 = 16,
+    Align32 = 32,
+    Align64 = 64,
+    Align128 = 128,
+    Align256 = 256,
+    Align512 = 512,
+    Align1024 = 1024,
+    Align2048 = 2048,
+    Align4096 = 4096,
+}
+
+/// An alignment, as a type.
+///
+/// Only the alignments with an [`Align1`]-style data store are
+/// [supported](SupportedAlignment).
+pub struct Alignment<const ALIGN: usize>;
+
+/// An [`Alignment`] which has a data store type.
+pub trait SupportedAlignment {
+    /// A data store of (at least) `N` bytes with this alignment.
+    type DataStore<const N: usize>: Copy;
+}
+
+/// A data store of (at least) `SIZE` bytes, aligned to `ALIGN`.
+///
+/// This picks the [`Align1`] through [`Align4096`] type for the alignment, so
+/// `ALIGN` must be a power of two no greater than 4096. As with those, the size
+/// is `SIZE` rounded up to a multiple of the alignment.
+pub type LayoutDataStore<const SIZE: usize, const ALIGN: usize> =
+    <Alignment<ALIGN> as SupportedAlignment>::DataStore<SIZE>;
@@ -0,0 +1,121 @@
+use {
+    crate::{InfallibleEmptyStorage, Memory, PinningStorage, SharedMutabilityStorage, Storage},
+    core::{
+        alloc::{AllocError, Layout},
+        cmp,
+    },
+};
+
+/// A storage which aligns all memory of another storage to (at least) `ALIGN`.
+///
+/// Every layout is passed on to the inner storage with its alignment raised
+/// to `ALIGN`, e.g. to cache-line align everything a collection allocates.
+///
+/// `ALIGN` must be a power of two, which is checked at compile time:
+///
+/// ```compile_fail
+/// # use storage_api::{AlignedStorage, InlineStorage};
+/// let storage = AlignedStorage::<_, 3>::new(InlineStorage::<u64>::new());
+/// ```
+pub struct AlignedStorage<S, const ALIGN: usize> {
+    storage: S,
+}
+
+impl<S, const ALIGN: usize> AlignedStorage<S, ALIGN> {
+    const ALIGN_IS_POWER_OF_TWO: () = assert!(
+        ALIGN.is_power_of_two(),
+        "AlignedStorage alignment must be a power of two"
+    );
+
+    pub const fn new(storage: S) -> Self {
+        let _ = Self::ALIGN_IS_POWER_OF_TWO;
+        Self { storage }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    /// The layout used with the inner storage, failing if raising the
+    /// alignment overflows the size.
+    fn try_align(layout: Layout) -> Result<Layout, AllocError> {
+        layout.align_to(ALIGN).map_err(|_| AllocError)
+    }
+
+    /// The layout used with the inner storage.
+    ///
+    /// # Safety
+    ///
+    /// - The layout must have been used to allocate a handle.
+    unsafe fn align(layout: Layout) -> Layout {
+        Self::try_align(layout).unwrap_unchecked()
+    }
+}
+
+unsafe impl<S: Storage, const ALIGN: usize> Storage for AlignedStorage<S, ALIGN> {
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = S::NEEDS_LAYOUT_FOR_DEALLOC;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        self.storage.allocate(Self::try_align(layout)?)
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        self.storage.allocate_empty(cmp::max(align, ALIGN))
+    }
+
+    fn current_handle(&self) -> Option<Self::Handle> {
+        self.storage.current_handle()
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.storage.deallocate(handle, Self::align(layout))
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.storage.resolve(handle, Self::align(layout))
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_mut(handle, Self::align(layout))
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        let new_layout = Self::try_align(new_layout)?;
+        self.storage
+            .grow(handle, Self::align(old_layout), new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        let new_layout = Self::try_align(new_layout)?;
+        self.storage
+            .shrink(handle, Self::align(old_layout), new_layout)
+    }
+}
+
+unsafe impl<S: SharedMutabilityStorage, const ALIGN: usize> SharedMutabilityStorage
+    for AlignedStorage<S, ALIGN>
+{
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_raw(handle, Self::align(layout))
+    }
+}
+
+unsafe impl<S: PinningStorage, const ALIGN: usize> PinningStorage for AlignedStorage<S, ALIGN> {}
+
+unsafe impl<S: InfallibleEmptyStorage, const ALIGN: usize> InfallibleEmptyStorage
+    for AlignedStorage<S, ALIGN>
+{
+}
@@ -1,5 +1,9 @@
 use {
-    crate::{Memory, MultipleStorage, PinningStorage, SharedMutabilityStorage, Storage},
+    crate::{
+        polyfill::{dangling, debug_assert_distinct, poison},
+        CloneStorage, InfallibleEmptyStorage, Memory, MultipleStorage, PinningStorage,
+        SharedMutabilityStorage, Storage,
+    },
     core::{
         alloc::{AllocError, Allocator, Layout},
         mem::MaybeUninit,
@@ -8,6 +12,13 @@ use {
 };
 
 /// A storage that stores objects via an [`Allocator`].
+///
+/// Zero-sized memory handles are dangling, and never touch the allocator.
+///
+/// This is a transparent wrapper around the allocator, so it is a ZST exactly
+/// when the allocator is.
+#[repr(transparent)]
+#[derive(Clone, Default)]
 pub struct AllocStorage<A: Allocator> {
     alloc: A,
 }
@@ -22,19 +33,47 @@ unsafe impl<A: Allocator> Storage for AllocStorage<A> {
     type Handle = AllocHandle;
 
     fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if layout.size() == 0 {
+            return Ok(AllocHandle::new(dangling(layout)));
+        }
+
         let (ptr, _meta) = self.alloc.allocate(layout)?.to_raw_parts();
-        Ok(AllocHandle::new(ptr))
+        Ok(AllocHandle::tracked(ptr))
+    }
+
+    fn allocate_with_excess(
+        &mut self,
+        layout: Layout,
+    ) -> Result<(Self::Handle, Layout), AllocError> {
+        if layout.size() == 0 {
+            return Ok((AllocHandle::new(dangling(layout)), layout));
+        }
+
+        let (ptr, size) = self.alloc.allocate(layout)?.to_raw_parts();
+        // The allocator fits the returned size with `layout`'s alignment.
+        let excess = unsafe { Layout::from_size_align_unchecked(size, layout.align()) };
+        Ok((AllocHandle::tracked(ptr), excess))
     }
 
     unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+
+        poison(self.resolve_mut(handle, layout));
+        handle.untrack();
         self.alloc.deallocate(handle.pointer.cast(), layout)
     }
 
+    #[inline(always)]
     unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        handle.check();
         NonNull::from_raw_parts(handle.pointer, layout.size()).as_ref()
     }
 
+    #[inline(always)]
     unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        handle.check();
         NonNull::from_raw_parts(handle.pointer, layout.size()).as_mut()
     }
 
@@ -44,11 +83,23 @@ unsafe impl<A: Allocator> Storage for AllocStorage<A> {
         old_layout: Layout,
         new_layout: Layout,
     ) -> Result<Self::Handle, AllocError> {
+        if new_layout == old_layout {
+            return Ok(handle);
+        }
+
+        if old_layout.size() == 0 {
+            return self.allocate(new_layout);
+        }
+
+        // Unlike `realloc`, `Allocator::grow` and `shrink` allow the alignment
+        // to change as well, as `Storage` does; no need to copy by hand.
+        handle.check();
         let (ptr, _meta) = self
             .alloc
             .grow(handle.pointer.cast(), old_layout, new_layout)?
             .to_raw_parts();
-        Ok(AllocHandle::new(ptr))
+        handle.untrack();
+        Ok(AllocHandle::tracked(ptr))
     }
 
     unsafe fn shrink(
@@ -57,11 +108,22 @@ unsafe impl<A: Allocator> Storage for AllocStorage<A> {
         old_layout: Layout,
         new_layout: Layout,
     ) -> Result<Self::Handle, AllocError> {
+        if new_layout == old_layout {
+            return Ok(handle);
+        }
+
+        if new_layout.size() == 0 {
+            self.deallocate(handle, old_layout);
+            return Ok(AllocHandle::new(dangling(new_layout)));
+        }
+
+        handle.check();
         let (ptr, _meta) = self
             .alloc
             .shrink(handle.pointer.cast(), old_layout, new_layout)?
             .to_raw_parts();
-        Ok(AllocHandle::new(ptr))
+        handle.untrack();
+        Ok(AllocHandle::tracked(ptr))
     }
 }
 
@@ -70,6 +132,7 @@ unsafe impl<A: Allocator> MultipleStorage for AllocStorage<A> {
         &mut self,
         handles: [(Self::Handle, Layout); N],
     ) -> [&mut Memory; N] {
+        debug_assert_distinct(&handles);
         let mut ptrs: [MaybeUninit<&mut Memory>; N] = MaybeUninit::uninit().assume_init();
         for (ptr, (handle, layout)) in ptrs.iter_mut().zip(handles) {
             ptr.write(self.resolve_raw(handle, layout));
@@ -79,25 +142,302 @@ unsafe impl<A: Allocator> MultipleStorage for AllocStorage<A> {
 }
 
 unsafe impl<A: Allocator> SharedMutabilityStorage for AllocStorage<A> {
+    #[inline(always)]
     unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        handle.check();
         NonNull::from_raw_parts(handle.pointer, layout.size()).as_mut()
     }
 }
 
 unsafe impl<A: Allocator> PinningStorage for AllocStorage<A> {}
 
+unsafe impl<A: Allocator> InfallibleEmptyStorage for AllocStorage<A> {}
+
+unsafe impl<A: Allocator + Clone> CloneStorage for AllocStorage<A> {}
+
+/// A shared reference to an alloc storage is a full storage, allocating
+/// through a shared reference to the allocator.
+///
+/// This allows multiple boxes to share one storage by reference. (A blanket
+/// impl for references to any [`SharedMutabilityStorage`] would overlap with
+/// the blanket Storage impl for MultipleStorage, as downstream crates could
+/// implement `MultipleStorage` for references to their own types.)
+unsafe impl<A: Allocator> Storage for &'_ AllocStorage<A> {
+    type Handle = AllocHandle;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        AllocStorage::new(&self.alloc).allocate(layout)
+    }
+
+    fn allocate_with_excess(
+        &mut self,
+        layout: Layout,
+    ) -> Result<(Self::Handle, Layout), AllocError> {
+        AllocStorage::new(&self.alloc).allocate_with_excess(layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        AllocStorage::new(&self.alloc).deallocate(handle, layout)
+    }
+
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        (**self).resolve_raw(handle, layout)
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        (**self).resolve_raw(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        AllocStorage::new(&self.alloc).grow(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        AllocStorage::new(&self.alloc).shrink(handle, old_layout, new_layout)
+    }
+}
+
+unsafe impl<A: Allocator> MultipleStorage for &'_ AllocStorage<A> {
+    unsafe fn resolve_many_mut<const N: usize>(
+        &mut self,
+        handles: [(Self::Handle, Layout); N],
+    ) -> [&mut Memory; N] {
+        debug_assert_distinct(&handles);
+        let storage: &AllocStorage<A> = self;
+        handles.map(|(handle, layout)| storage.resolve_raw(handle, layout))
+    }
+}
+
+unsafe impl<A: Allocator> SharedMutabilityStorage for &'_ AllocStorage<A> {
+    #[inline(always)]
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        (**self).resolve_raw(handle, layout)
+    }
+}
+
+unsafe impl<A: Allocator> PinningStorage for &'_ AllocStorage<A> {}
+
+unsafe impl<A: Allocator> InfallibleEmptyStorage for &'_ AllocStorage<A> {}
+
+unsafe impl<A: Allocator> CloneStorage for &'_ AllocStorage<A> {}
+
+/// The memory handle of [`AllocStorage`], a pointer to the memory.
+///
+/// With the `checked` feature, this additionally holds the generation of the
+/// allocation, which is checked against a global registry of the live
+/// allocations to catch resolving the handle after it was deallocated, even if
+/// the allocator reused the address since. The registry only has room to track
+/// 1024 allocations; beyond that, and for zero-sized and adopted handles, the
+/// generation is 0, which isn't checked.
+#[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct AllocHandle {
-    pointer: NonNull<()>,
+    pub(crate) pointer: NonNull<()>,
+    #[cfg(feature = "checked")]
+    generation: usize,
 }
 
 unsafe impl Send for AllocHandle {}
 unsafe impl Sync for AllocHandle {}
 
 impl AllocHandle {
-    const fn new<T: ?Sized>(pointer: NonNull<T>) -> Self {
+    pub(crate) const fn new<T: ?Sized>(pointer: NonNull<T>) -> Self {
+        Self::from_ptr(pointer.cast())
+    }
+
+    /// Create the handle of a new allocation, tracking it with the `checked`
+    /// feature.
+    #[inline(always)]
+    fn tracked<T: ?Sized>(pointer: NonNull<T>) -> Self {
         Self {
             pointer: pointer.cast(),
+            #[cfg(feature = "checked")]
+            generation: registry::track(pointer.cast::<()>().as_ptr() as usize),
+        }
+    }
+
+    /// Catch resolving a handle to deallocated memory, with the `checked`
+    /// feature.
+    #[inline(always)]
+    fn check(self) {
+        #[cfg(feature = "checked")]
+        assert!(
+            registry::is_live(self.pointer.as_ptr() as usize, self.generation),
+            "AllocStorage resolved an invalidated handle"
+        );
+    }
+
+    /// Stop tracking the allocation, as it is deallocated, or deallocated
+    /// outside of the storage.
+    #[inline(always)]
+    pub(crate) fn untrack(self) {
+        #[cfg(feature = "checked")]
+        registry::untrack(self.pointer.as_ptr() as usize, self.generation);
+    }
+
+    /// Create a handle from a pointer to memory allocated by an allocator,
+    /// e.g. to adopt memory from FFI into an [`AllocStorage`].
+    ///
+    /// A zero-sized handle is any aligned pointer, as it is never deallocated.
+    /// With the `checked` feature, the adopted memory isn't tracked.
+    pub const fn from_ptr(pointer: NonNull<()>) -> Self {
+        Self {
+            pointer,
+            #[cfg(feature = "checked")]
+            generation: 0,
+        }
+    }
+
+    /// Get the pointer to the handled memory.
+    ///
+    /// Resolving the handle in its [`AllocStorage`] gives this same pointer.
+    pub const fn as_ptr(self) -> NonNull<()> {
+        self.pointer
+    }
+}
+
+/// The generations of the live allocations of every [`AllocStorage`].
+///
+/// The handles are pointers, which can be reused by the allocator, so the
+/// generations have to be kept outside of the storage. This is a fixed-size
+/// hash table behind a spin lock, to stay `no_std`.
+#[cfg(feature = "checked")]
+mod registry {
+    use core::{
+        cell::UnsafeCell,
+        hint,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    const CAPACITY: usize = 2048;
+    /// Leave half of the table empty, to keep the probe sequences short.
+    const MAX_LIVE: usize = CAPACITY / 2;
+
+    struct Table {
+        /// The address and generation of each live allocation, or generation
+        /// 0 for an empty slot. Collisions probe linearly.
+        slots: [(usize, usize); CAPACITY],
+        live: usize,
+        /// The generation of the next allocation, skipping 0.
+        next_generation: usize,
+    }
+
+    struct Registry {
+        locked: AtomicBool,
+        table: UnsafeCell<Table>,
+    }
+
+    unsafe impl Sync for Registry {}
+
+    static REGISTRY: Registry = Registry {
+        locked: AtomicBool::new(false),
+        table: UnsafeCell::new(Table {
+            slots: [(0, 0); CAPACITY],
+            live: 0,
+            next_generation: 1,
+        }),
+    };
+
+    /// Run a closure with the locked table. It must not panic, as that would
+    /// keep the lock held.
+    fn with_table<R>(f: impl FnOnce(&mut Table) -> R) -> R {
+        while REGISTRY
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let result = f(unsafe { &mut *REGISTRY.table.get() });
+        REGISTRY.locked.store(false, Ordering::Release);
+        result
+    }
+
+    impl Table {
+        fn home(addr: usize) -> usize {
+            (addr >> 4) % CAPACITY
+        }
+
+        fn find(&self, addr: usize) -> Option<usize> {
+            // There is always an empty slot, so this terminates.
+            let mut slot = Self::home(addr);
+            loop {
+                match self.slots[slot] {
+                    (_, 0) => return None,
+                    (slot_addr, _) if slot_addr == addr => return Some(slot),
+                    _ => slot = (slot + 1) % CAPACITY,
+                }
+            }
+        }
+
+        fn remove(&mut self, mut slot: usize) {
+            // Shift back the later entries of the probe sequence, so that it
+            // stays unbroken.
+            let mut next = (slot + 1) % CAPACITY;
+            while let (addr, generation @ 1..) = self.slots[next] {
+                let probed = (next + CAPACITY - Self::home(addr)) % CAPACITY;
+                if probed >= (next + CAPACITY - slot) % CAPACITY {
+                    self.slots[slot] = (addr, generation);
+                    slot = next;
+                }
+                next = (next + 1) % CAPACITY;
+            }
+            self.slots[slot] = (0, 0);
+            self.live -= 1;
+        }
+    }
+
+    /// Track a new allocation, and get its generation, or 0 if the table is
+    /// full.
+    pub(super) fn track(addr: usize) -> usize {
+        with_table(|table| {
+            // The memory could have been deallocated outside of a storage.
+            if let Some(slot) = table.find(addr) {
+                table.remove(slot);
+            }
+            if table.live == MAX_LIVE {
+                return 0;
+            }
+
+            let generation = table.next_generation;
+            table.next_generation = generation.checked_add(1).unwrap_or(1);
+            let mut slot = Table::home(addr);
+            while table.slots[slot].1 != 0 {
+                slot = (slot + 1) % CAPACITY;
+            }
+            table.slots[slot] = (addr, generation);
+            table.live += 1;
+            generation
+        })
+    }
+
+    /// Check if the allocation of the generation is still live, or untracked.
+    pub(super) fn is_live(addr: usize, generation: usize) -> bool {
+        generation == 0
+            || with_table(|table| {
+                table.find(addr).map(|slot| table.slots[slot].1) == Some(generation)
+            })
+    }
+
+    /// Stop tracking the allocation of the generation, if it is tracked.
+    pub(super) fn untrack(addr: usize, generation: usize) {
+        if generation != 0 {
+            with_table(|table| match table.find(addr) {
+                Some(slot) if table.slots[slot].1 == generation => table.remove(slot),
+                _ => {},
+            });
         }
     }
 }
@@ -0,0 +1,128 @@
+use {
+    crate::{polyfill::handle_alloc_error, CloneStorage, SharedMutabilityStorage},
+    core::{
+        alloc::Layout,
+        marker::PhantomData,
+        ops::Deref,
+        ptr::{self, NonNull},
+        sync::atomic::{self, AtomicUsize, Ordering},
+    },
+};
+
+/// The control block of an [`Arc`], stored in the storage.
+#[repr(C)]
+struct ArcBox<T> {
+    strong: AtomicUsize,
+    value: T,
+}
+
+/// A thread-safe reference-counting pointer. A tiny subset of std's Arc.
+///
+/// This is the atomic analog of [`Rc`](crate::Rc): every `Arc` holds a clone
+/// of the storage. As clones of the storage are used from every thread holding
+/// an `Arc`, the storage must be `Send + Sync` for the `Arc` to be. (There's no
+/// `Weak` yet.)
+///
+/// The handle is only resolved (with [`SharedMutabilityStorage::resolve_raw`])
+/// when the `Arc` is created, and the pointer is shared by the clones.
+/// Resolving again from other threads would create aliasing `&mut Memory`
+/// racing with the reference count. As the clones of the storage all manage
+/// the memory, it isn't inside any one of them, so the pointer stays valid
+/// while the handle is allocated.
+pub struct Arc<T, S: SharedMutabilityStorage + CloneStorage> {
+    handle: S::Handle,
+    ptr: NonNull<ArcBox<T>>,
+    storage: S,
+    marker: PhantomData<ArcBox<T>>,
+}
+
+unsafe impl<T: Send + Sync, S> Send for Arc<T, S> where
+    S: SharedMutabilityStorage + CloneStorage + Send + Sync
+{
+}
+
+unsafe impl<T: Send + Sync, S> Sync for Arc<T, S> where
+    S: SharedMutabilityStorage + CloneStorage + Send + Sync
+{
+}
+
+const fn arc_layout<T>() -> Layout {
+    Layout::new::<ArcBox<T>>()
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Arc<T, S> {
+    #[track_caller]
+    pub fn new_in(value: T, mut storage: S) -> Self {
+        let handle = match storage.allocate(arc_layout::<T>()) {
+            Ok(handle) => handle,
+            Err(_) => handle_alloc_error(arc_layout::<T>()),
+        };
+        let ptr = unsafe {
+            let memory = storage.resolve_raw(handle, arc_layout::<T>());
+            let ptr = memory.as_mut_ptr().cast::<ArcBox<T>>();
+            ptr.write(ArcBox {
+                strong: AtomicUsize::new(1),
+                value,
+            });
+            NonNull::new_unchecked(ptr)
+        };
+        Self {
+            handle,
+            ptr,
+            storage,
+            marker: PhantomData,
+        }
+    }
+
+    fn inner(&self) -> &ArcBox<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// The number of `Arc`s to this value.
+    ///
+    /// Other threads may change the count at any time, so this is only a
+    /// snapshot.
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.load(Ordering::Acquire)
+    }
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Clone for Arc<T, S> {
+    fn clone(&self) -> Self {
+        // A new reference can only be made from an existing one, so no
+        // synchronization is needed (see std's Arc).
+        let old = self.inner().strong.fetch_add(1, Ordering::Relaxed);
+        // std aborts here, which core can't do; panicking still prevents the
+        // count from wrapping around, as the leaked increment is never undone.
+        assert!(old <= isize::MAX as usize, "Arc reference count overflow");
+        Self {
+            handle: self.handle,
+            ptr: self.ptr,
+            storage: self.storage.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Deref for Arc<T, S> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner().value
+    }
+}
+
+unsafe impl<#[may_dangle] T, S: SharedMutabilityStorage + CloneStorage> Drop for Arc<T, S> {
+    fn drop(&mut self) {
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Synchronize with the releases of all other `Arc`s, so that their
+        // uses of the value happen before it is dropped.
+        atomic::fence(Ordering::Acquire);
+        unsafe {
+            ptr::drop_in_place(ptr::addr_of_mut!((*self.ptr.as_ptr()).value));
+            self.storage.deallocate(self.handle, arc_layout::<T>());
+        }
+    }
+}
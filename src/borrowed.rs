@@ -1,5 +1,11 @@
 use {
-    crate::{polyfill::layout_fits_in, Memory, Storage},
+    crate::{
+        bump::{BumpHandle, Resets},
+        inline::SingleHandle,
+        polyfill::{dangling, debug_assert_distinct, layout_fits_in, poison},
+        Align16, InfallibleEmptyStorage, Memory, MultipleStorage, ReadStorage, ResettableStorage,
+        Storage,
+    },
     core::{
         alloc::{AllocError, Layout},
         mem::MaybeUninit,
@@ -12,39 +18,101 @@ use {
 /// The `DataStore` type parameter determines the layout of the inline storage.
 /// (It would be nice to use `const LAYOUT: Layout` instead, but the needed
 /// features are currently a little *too* incomplete to be usable here yet.)
+///
+/// With the `checked` feature, this additionally counts its allocations and
+/// deallocations in a generation, to catch a second allocation aliasing the
+/// single memory handle, and its handles carry the generation, to catch
+/// resolving a handle of an earlier generation. Otherwise, it is a transparent
+/// wrapper around `&mut MaybeUninit<DataStore>`.
+#[cfg_attr(not(feature = "checked"), repr(transparent))]
 pub struct BorrowedStorage<'a, DataStore> {
     data: &'a mut MaybeUninit<DataStore>,
+    /// Odd while the storage is allocated.
+    #[cfg(feature = "checked")]
+    generation: usize,
 }
 
 impl<'a, DataStore> BorrowedStorage<'a, DataStore> {
     pub fn new(store: &'a mut MaybeUninit<DataStore>) -> Self {
-        Self { data: store }
+        Self {
+            data: store,
+            #[cfg(feature = "checked")]
+            generation: 0,
+        }
     }
 
+    /// The layout of the borrowed memory.
+    ///
+    /// A layout fits if it is no bigger and no more aligned than this. When
+    /// allocating fails, comparing against this tells whether the size or the
+    /// alignment was too big.
+    pub fn available_layout(&self) -> Layout {
+        Layout::new::<DataStore>()
+    }
+
+    /// Check if memory of the given layout fits in this storage.
+    ///
+    /// Zero-sized layouts always fit, as they don't need any memory.
     pub fn fits(&self, needed_layout: Layout) -> bool {
-        let available_layout = Layout::new::<DataStore>();
-        layout_fits_in(needed_layout, available_layout)
+        if needed_layout.size() == 0 {
+            return true;
+        }
+
+        layout_fits_in(needed_layout, self.available_layout())
+    }
+
+    fn new_handle(&mut self) -> SingleHandle {
+        #[cfg(feature = "checked")]
+        return crate::inline::next_handle(&mut self.generation, "BorrowedStorage");
+    }
+
+    #[cfg(feature = "checked")]
+    fn check(&self, handle: SingleHandle) {
+        crate::inline::check_handle(self.generation, handle, "BorrowedStorage");
     }
 }
 
 unsafe impl<DataStore> Storage for BorrowedStorage<'_, DataStore> {
-    type Handle = ();
+    type Handle = SingleHandle;
+
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = false;
 
     fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
         if self.fits(layout) {
-            Ok(())
+            let handle = self.new_handle();
+            Ok(handle)
         } else {
             Err(AllocError)
         }
     }
 
-    unsafe fn deallocate(&mut self, _handle: Self::Handle, _layout: Layout) {}
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        poison(self.resolve_mut(handle, layout));
+        #[cfg(feature = "checked")]
+        {
+            self.generation = self.generation.wrapping_add(1);
+        }
+    }
 
     unsafe fn resolve(&self, _handle: Self::Handle, layout: Layout) -> &Memory {
+        #[cfg(feature = "checked")]
+        self.check(_handle);
+
+        if layout.size() == 0 {
+            return &*ptr::from_raw_parts(dangling(layout).as_ptr().cast(), 0);
+        }
+
         &*ptr::from_raw_parts(self.data.as_ptr().cast(), layout.size())
     }
 
     unsafe fn resolve_mut(&mut self, _handle: Self::Handle, layout: Layout) -> &mut Memory {
+        #[cfg(feature = "checked")]
+        self.check(_handle);
+
+        if layout.size() == 0 {
+            return &mut *ptr::from_raw_parts_mut(dangling(layout).as_ptr().cast(), 0);
+        }
+
         &mut *ptr::from_raw_parts_mut(self.data.as_mut_ptr().cast(), layout.size())
     }
 
@@ -54,6 +122,8 @@ unsafe impl<DataStore> Storage for BorrowedStorage<'_, DataStore> {
         _old_layout: Layout,
         new_layout: Layout,
     ) -> Result<Self::Handle, AllocError> {
+        #[cfg(feature = "checked")]
+        self.check(handle);
         if self.fits(new_layout) {
             Ok(handle)
         } else {
@@ -67,7 +137,168 @@ unsafe impl<DataStore> Storage for BorrowedStorage<'_, DataStore> {
         _old_layout: Layout,
         new_layout: Layout,
     ) -> Result<Self::Handle, AllocError> {
+        #[cfg(feature = "checked")]
+        self.check(handle);
         debug_assert!(self.fits(new_layout));
         Ok(handle)
     }
 }
+
+unsafe impl<DataStore> InfallibleEmptyStorage for BorrowedStorage<'_, DataStore> {}
+
+/// A read storage of borrowed read-only memory.
+///
+/// Handles are offsets into the memory, checked by
+/// [`handle_at`](Self::handle_at) to fit the memory and be aligned. There is
+/// no allocation: the memory holds whatever it was given with.
+#[derive(Clone, Copy)]
+pub struct RoBorrowedStorage<'a> {
+    memory: &'a [MaybeUninit<u8>],
+}
+
+impl<'a> RoBorrowedStorage<'a> {
+    pub fn new(memory: &'a [MaybeUninit<u8>]) -> Self {
+        Self { memory }
+    }
+
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        Self::new(unsafe { &*(bytes as *const [u8] as *const [MaybeUninit<u8>]) })
+    }
+
+    /// Get the handle of the memory at `offset`, if memory of the given layout
+    /// fits there and is aligned.
+    pub fn handle_at(&self, offset: usize, layout: Layout) -> Option<usize> {
+        let end = offset.checked_add(layout.size())?;
+        let addr = (self.memory.as_ptr() as usize).wrapping_add(offset);
+        if end <= self.memory.len() && addr & (layout.align() - 1) == 0 {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+}
+
+unsafe impl ReadStorage for RoBorrowedStorage<'_> {
+    type Handle = usize;
+
+    unsafe fn resolve_read(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.memory.get_unchecked(handle..handle + layout.size())
+    }
+}
+
+/// A multiple storage which bump allocates memory in borrowed space.
+///
+/// Handles are offsets into the borrowed memory. Allocation just bumps the
+/// offset, and deallocation does nothing; the memory is only reclaimed all at
+/// once by [`reset`](ResettableStorage::reset).
+pub struct BorrowedArena<'a> {
+    memory: &'a mut [MaybeUninit<u8>],
+    used: usize,
+    resets: Resets,
+}
+
+impl<'a> BorrowedArena<'a> {
+    pub fn new(memory: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            memory,
+            used: 0,
+            resets: Resets::new(),
+        }
+    }
+
+    /// The number of bytes which have been bumped past, including padding.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// The number of bytes which are still available.
+    pub fn remaining(&self) -> usize {
+        self.memory.len() - self.used
+    }
+
+    fn resolve_ptr(base: *mut MaybeUninit<u8>, offset: usize, layout: Layout) -> *mut Memory {
+        if layout.size() == 0 {
+            return ptr::from_raw_parts_mut(dangling(layout).as_ptr().cast(), 0);
+        }
+
+        ptr::from_raw_parts_mut(base.wrapping_add(offset).cast(), layout.size())
+    }
+
+    fn offset(&self, handle: BumpHandle, layout: Layout) -> usize {
+        self.resets.offset(handle, layout, "BorrowedArena")
+    }
+}
+
+unsafe impl Storage for BorrowedArena<'_> {
+    type Handle = BumpHandle;
+
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = false;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if layout.size() == 0 {
+            return Ok(self.resets.handle(0));
+        }
+
+        // The memory doesn't move, so align the address rather than the offset.
+        let addr = (self.memory.as_ptr() as usize).wrapping_add(self.used);
+        let start = self.used + (addr.wrapping_neg() & (layout.align() - 1));
+        match start.checked_add(layout.size()) {
+            Some(end) if end <= self.memory.len() => {
+                self.used = end;
+                Ok(self.resets.handle(start))
+            },
+            _ => Err(AllocError),
+        }
+    }
+
+    unsafe fn deallocate(&mut self, _handle: Self::Handle, _layout: Layout) {}
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        let offset = self.offset(handle, layout);
+        &*Self::resolve_ptr(self.memory.as_ptr() as *mut _, offset, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        let offset = self.offset(handle, layout);
+        &mut *Self::resolve_ptr(self.memory.as_mut_ptr(), offset, layout)
+    }
+}
+
+unsafe impl MultipleStorage for BorrowedArena<'_> {
+    unsafe fn resolve_many_mut<const N: usize>(
+        &mut self,
+        handles: [(Self::Handle, Layout); N],
+    ) -> [&mut Memory; N] {
+        debug_assert_distinct(&handles);
+        let offsets = handles.map(|(handle, layout)| (self.offset(handle, layout), layout));
+        let base = self.memory.as_mut_ptr();
+        offsets.map(|(offset, layout)| &mut *Self::resolve_ptr(base, offset, layout))
+    }
+}
+
+unsafe impl ResettableStorage for BorrowedArena<'_> {
+    fn reset(&mut self) {
+        self.used = 0;
+        self.resets.reset();
+    }
+}
+
+unsafe impl InfallibleEmptyStorage for BorrowedArena<'_> {}
+
+/// Lend a [`BorrowedArena`] of `N` bytes on the stack to a closure.
+///
+/// The memory is in the current stack frame, and lives exactly as long as the
+/// call to `f`. It is aligned to 16 bytes by its type, so allocations of up to
+/// that alignment don't depend on the address of the stack frame (and pass
+/// Miri's symbolic alignment check). Anything allocated in the arena borrows
+/// it, so it can't be returned from the closure:
+///
+/// ```compile_fail
+/// # use storage_api::{with_stack_storage, Box};
+/// let escaped = with_stack_storage::<64, _>(|arena| Box::new_in(1u32, arena));
+/// ```
+pub fn with_stack_storage<const N: usize, R>(f: impl FnOnce(BorrowedArena<'_>) -> R) -> R {
+    let mut memory = MaybeUninit::<Align16<N>>::uninit();
+    let memory = unsafe { &mut *(memory.as_mut_ptr() as *mut [MaybeUninit<u8>; N]) };
+    f(BorrowedArena::new(memory))
+}
@@ -0,0 +1,114 @@
+use {
+    crate::{InfallibleEmptyStorage, Memory, PinningStorage, SharedMutabilityStorage, Storage},
+    core::alloc::{AllocError, Layout},
+};
+
+/// A storage which limits the live bytes allocated from another storage.
+///
+/// Allocating or growing beyond the byte budget fails without touching the
+/// inner storage; deallocating or shrinking returns bytes to the budget. This
+/// is useful for deterministic out-of-memory testing.
+pub struct BudgetStorage<S> {
+    storage: S,
+    budget: usize,
+    live_bytes: usize,
+}
+
+impl<S> BudgetStorage<S> {
+    pub const fn new(storage: S, budget: usize) -> Self {
+        Self {
+            storage,
+            budget,
+            live_bytes: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    /// The number of bytes in currently allocated memory handles.
+    pub fn live_bytes(&self) -> usize {
+        self.live_bytes
+    }
+
+    /// The number of bytes which can still be allocated.
+    pub fn remaining(&self) -> usize {
+        self.budget - self.live_bytes
+    }
+
+    fn spend(&self, bytes: usize) -> Result<usize, AllocError> {
+        match self.live_bytes.checked_add(bytes) {
+            Some(live_bytes) if live_bytes <= self.budget => Ok(live_bytes),
+            _ => Err(AllocError),
+        }
+    }
+}
+
+unsafe impl<S: Storage> Storage for BudgetStorage<S> {
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        let live_bytes = self.spend(layout.size())?;
+        let handle = self.storage.allocate(layout)?;
+        self.live_bytes = live_bytes;
+        Ok(handle)
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        self.storage.allocate_empty(align)
+    }
+
+    fn current_handle(&self) -> Option<Self::Handle> {
+        self.storage.current_handle()
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.storage.deallocate(handle, layout);
+        self.live_bytes -= layout.size();
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.storage.resolve(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_mut(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        let live_bytes = self.spend(new_layout.size() - old_layout.size())?;
+        let handle = self.storage.grow(handle, old_layout, new_layout)?;
+        self.live_bytes = live_bytes;
+        Ok(handle)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        let handle = self.storage.shrink(handle, old_layout, new_layout)?;
+        self.live_bytes -= old_layout.size() - new_layout.size();
+        Ok(handle)
+    }
+}
+
+unsafe impl<S: SharedMutabilityStorage> SharedMutabilityStorage for BudgetStorage<S> {
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_raw(handle, layout)
+    }
+}
+
+unsafe impl<S: PinningStorage> PinningStorage for BudgetStorage<S> {}
+
+// Zero-sized allocations always fit in the budget.
+unsafe impl<S: InfallibleEmptyStorage> InfallibleEmptyStorage for BudgetStorage<S> {}
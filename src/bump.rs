@@ -0,0 +1,185 @@
+use {
+    crate::{
+        polyfill::{dangling, debug_assert_distinct},
+        InfallibleEmptyStorage, Memory, MultipleStorage, ResettableStorage, Storage,
+    },
+    core::{
+        alloc::{AllocError, Layout},
+        mem::{self, MaybeUninit},
+        ptr,
+    },
+};
+
+/// A multiple storage which bump allocates memory inline.
+///
+/// Handles are offsets into the inline `DataStore`. Allocation just bumps the
+/// offset, and deallocation does nothing; the memory is only reclaimed all at
+/// once by [`reset`](ResettableStorage::reset).
+///
+/// As the storage can move, handles cannot be aligned more than `DataStore`.
+pub struct BumpStorage<DataStore> {
+    data: MaybeUninit<DataStore>,
+    used: usize,
+    resets: Resets,
+}
+
+/// The memory handle of the bump storages, such as [`BumpStorage`].
+///
+/// This is the offset of the memory. With the `checked` feature, it is instead
+/// the offset along with the generation of the storage that allocated it.
+#[cfg(not(feature = "checked"))]
+pub type BumpHandle = usize;
+
+/// The memory handle of the bump storages, such as [`BumpStorage`].
+///
+/// This is the offset of the memory along with the generation of the storage
+/// that allocated it, which the storage checks to catch resolving the handle
+/// after a reset, even if the memory was allocated again since. Without the
+/// `checked` feature, this is just the offset.
+#[cfg(feature = "checked")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BumpHandle {
+    pub(crate) offset: usize,
+    pub(crate) generation: usize,
+}
+
+/// The generation of a bump storage, counting its resets.
+///
+/// Without the `checked` feature, this is zero-sized and checks nothing.
+pub(crate) struct Resets {
+    #[cfg(feature = "checked")]
+    generation: usize,
+}
+
+impl Resets {
+    pub(crate) const fn new() -> Self {
+        Self {
+            #[cfg(feature = "checked")]
+            generation: 0,
+        }
+    }
+
+    pub(crate) fn handle(&self, offset: usize) -> BumpHandle {
+        #[cfg(feature = "checked")]
+        return BumpHandle {
+            offset,
+            generation: self.generation,
+        };
+        #[cfg(not(feature = "checked"))]
+        offset
+    }
+
+    /// Get the offset of the memory, catching a handle invalidated by a reset.
+    pub(crate) fn offset(&self, handle: BumpHandle, layout: Layout, storage: &str) -> usize {
+        #[cfg(feature = "checked")]
+        {
+            assert!(
+                layout.size() == 0 || handle.generation == self.generation,
+                "{storage} resolved an invalidated handle"
+            );
+            handle.offset
+        }
+        #[cfg(not(feature = "checked"))]
+        {
+            let _ = (layout, storage);
+            handle
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        #[cfg(feature = "checked")]
+        {
+            self.generation = self.generation.wrapping_add(1);
+        }
+    }
+}
+
+impl<DataStore> BumpStorage<DataStore> {
+    pub const fn new() -> Self {
+        Self {
+            data: MaybeUninit::uninit(),
+            used: 0,
+            resets: Resets::new(),
+        }
+    }
+
+    /// The number of bytes which have been bumped past, including padding.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// The number of bytes which are still available.
+    pub fn remaining(&self) -> usize {
+        mem::size_of::<DataStore>() - self.used
+    }
+
+    fn resolve_ptr(base: *mut u8, offset: usize, layout: Layout) -> *mut Memory {
+        if layout.size() == 0 {
+            return ptr::from_raw_parts_mut(dangling(layout).as_ptr().cast(), 0);
+        }
+
+        ptr::from_raw_parts_mut(base.wrapping_add(offset).cast(), layout.size())
+    }
+
+    fn offset(&self, handle: BumpHandle, layout: Layout) -> usize {
+        self.resets.offset(handle, layout, "BumpStorage")
+    }
+}
+
+unsafe impl<DataStore> Storage for BumpStorage<DataStore> {
+    type Handle = BumpHandle;
+
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = false;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if layout.size() == 0 {
+            return Ok(self.resets.handle(0));
+        }
+
+        if layout.align() > mem::align_of::<DataStore>() {
+            return Err(AllocError);
+        }
+
+        let start = (self.used + layout.align() - 1) & !(layout.align() - 1);
+        match start.checked_add(layout.size()) {
+            Some(end) if end <= mem::size_of::<DataStore>() => {
+                self.used = end;
+                Ok(self.resets.handle(start))
+            },
+            _ => Err(AllocError),
+        }
+    }
+
+    unsafe fn deallocate(&mut self, _handle: Self::Handle, _layout: Layout) {}
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        let offset = self.offset(handle, layout);
+        &*Self::resolve_ptr(self.data.as_ptr() as *mut u8, offset, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        let offset = self.offset(handle, layout);
+        &mut *Self::resolve_ptr(self.data.as_mut_ptr().cast(), offset, layout)
+    }
+}
+
+unsafe impl<DataStore> MultipleStorage for BumpStorage<DataStore> {
+    unsafe fn resolve_many_mut<const N: usize>(
+        &mut self,
+        handles: [(Self::Handle, Layout); N],
+    ) -> [&mut Memory; N] {
+        debug_assert_distinct(&handles);
+        let offsets = handles.map(|(handle, layout)| (self.offset(handle, layout), layout));
+        let base = self.data.as_mut_ptr().cast();
+        offsets.map(|(offset, layout)| &mut *Self::resolve_ptr(base, offset, layout))
+    }
+}
+
+unsafe impl<DataStore> ResettableStorage for BumpStorage<DataStore> {
+    fn reset(&mut self) {
+        self.used = 0;
+        self.resets.reset();
+    }
+}
+
+unsafe impl<DataStore> InfallibleEmptyStorage for BumpStorage<DataStore> {}
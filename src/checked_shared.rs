@@ -0,0 +1,125 @@
+use {
+    crate::{Memory, PinningStorage, SharedMutabilityStorage, Storage},
+    core::{
+        alloc::{AllocError, Layout},
+        cell::{RefCell, RefMut},
+    },
+};
+
+/// A storage behind a `RefCell`, to catch aliasing mutable resolves.
+///
+/// This is a debugging aid. [`resolve_checked`](Self::resolve_checked)
+/// resolves a handle mutably through a shared reference, holding a mutable
+/// borrow of the storage for as long as the returned guard lives. Resolving
+/// again while a guard is alive, be it with `resolve_checked`, [`resolve`], or
+/// [`resolve_raw`], panics instead of aliasing the memory.
+///
+/// This is coarser than the aliasing rules: a live guard for one handle also
+/// makes resolving any other handle panic.
+///
+/// Only the guards are checked. The references returned by `resolve` and
+/// `resolve_raw` can't hold a borrow, so overlapping `resolve_raw` calls don't
+/// panic, and neither does `resolve_checked` while such a reference is alive.
+///
+/// [`resolve`]: Storage::resolve
+/// [`resolve_raw`]: SharedMutabilityStorage::resolve_raw
+pub struct CheckedSharedStorage<S> {
+    storage: RefCell<S>,
+}
+
+impl<S> CheckedSharedStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage: RefCell::new(storage),
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage.into_inner()
+    }
+
+    /// Get the inner storage, panicking if a checked resolve is alive.
+    fn inner(&self) -> &S {
+        unsafe { self.storage.try_borrow_unguarded() }
+            .unwrap_or_else(|_| panic!("CheckedSharedStorage resolved while mutably resolved"))
+    }
+}
+
+impl<S: Storage> CheckedSharedStorage<S> {
+    /// Resolve a memory handle in this storage to a guarded mutable reference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of any handle is already mutably resolved.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must have been created by this storage, and must not have
+    ///   been invalidated.
+    /// - The layout must be the same as used to allocate the handle.
+    #[track_caller]
+    pub unsafe fn resolve_checked(&self, handle: S::Handle, layout: Layout) -> RefMut<'_, Memory> {
+        let storage = self
+            .storage
+            .try_borrow_mut()
+            .unwrap_or_else(|_| panic!("CheckedSharedStorage mutably resolved twice at once"));
+        RefMut::map(storage, |storage| storage.resolve_mut(handle, layout))
+    }
+}
+
+unsafe impl<S: Storage> Storage for CheckedSharedStorage<S> {
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = S::NEEDS_LAYOUT_FOR_DEALLOC;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        self.storage.get_mut().allocate(layout)
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        self.storage.get_mut().allocate_empty(align)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.storage.get_mut().deallocate(handle, layout)
+    }
+
+    #[track_caller]
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.inner().resolve(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.get_mut().resolve_mut(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.storage.get_mut().grow(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.storage
+            .get_mut()
+            .shrink(handle, old_layout, new_layout)
+    }
+}
+
+unsafe impl<S: SharedMutabilityStorage> SharedMutabilityStorage for CheckedSharedStorage<S> {
+    #[track_caller]
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.inner().resolve_raw(handle, layout)
+    }
+}
+
+unsafe impl<S: PinningStorage> PinningStorage for CheckedSharedStorage<S> {}
@@ -0,0 +1,123 @@
+use {
+    crate::{InfallibleEmptyStorage, Memory, PinningStorage, SharedMutabilityStorage, Storage},
+    core::alloc::{AllocError, Layout},
+};
+
+/// A storage which counts the calls made to another storage.
+///
+/// This is pure instrumentation, e.g. to check that a collection avoids
+/// touching its storage.
+pub struct CountingStorage<S> {
+    storage: S,
+    allocations: usize,
+    deallocations: usize,
+    reallocations: usize,
+    live_bytes: usize,
+}
+
+impl<S> CountingStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            allocations: 0,
+            deallocations: 0,
+            reallocations: 0,
+            live_bytes: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    /// The number of successful calls to `allocate`.
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+
+    /// The number of calls to `deallocate`.
+    pub fn deallocations(&self) -> usize {
+        self.deallocations
+    }
+
+    /// The number of successful calls to `grow` or `shrink`.
+    pub fn reallocations(&self) -> usize {
+        self.reallocations
+    }
+
+    /// The number of bytes in currently allocated memory handles.
+    pub fn live_bytes(&self) -> usize {
+        self.live_bytes
+    }
+}
+
+unsafe impl<S: Storage> Storage for CountingStorage<S> {
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        let handle = self.storage.allocate(layout)?;
+        self.allocations += 1;
+        self.live_bytes += layout.size();
+        Ok(handle)
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        let handle = self.storage.allocate_empty(align)?;
+        self.allocations += 1;
+        Ok(handle)
+    }
+
+    fn current_handle(&self) -> Option<Self::Handle> {
+        self.storage.current_handle()
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.storage.deallocate(handle, layout);
+        self.deallocations += 1;
+        self.live_bytes -= layout.size();
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.storage.resolve(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_mut(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        let handle = self.storage.grow(handle, old_layout, new_layout)?;
+        self.reallocations += 1;
+        self.live_bytes += new_layout.size() - old_layout.size();
+        Ok(handle)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        let handle = self.storage.shrink(handle, old_layout, new_layout)?;
+        self.reallocations += 1;
+        self.live_bytes -= old_layout.size() - new_layout.size();
+        Ok(handle)
+    }
+}
+
+unsafe impl<S: SharedMutabilityStorage> SharedMutabilityStorage for CountingStorage<S> {
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_raw(handle, layout)
+    }
+}
+
+unsafe impl<S: PinningStorage> PinningStorage for CountingStorage<S> {}
+
+unsafe impl<S: InfallibleEmptyStorage> InfallibleEmptyStorage for CountingStorage<S> {}
@@ -180,7 +180,7 @@ use core::{
     ptr::DynMetadata,
 };
 
-use crate::{polyfill::layout_fits_in, Memory, SharedMutabilityStorage};
+use crate::{polyfill::layout_fits_in, Memory};
 
 /// Dynamic single storage for use with `RawBox<dyn Trait, DynStorage<A>>`.
 ///
@@ -212,6 +212,8 @@ unsafe impl<'a> Storage for DynStorage<'a> {
     // 2×usize big.
     type Handle = ();
 
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = false;
+
     // Allocation cannot happen. There is no way to construct DynStorage
     // directly; it is only constructed as part of an already-allocated RawBox.
     // However, it can be acquired by RawBox::into_raw_parts, so we always fail
@@ -323,7 +325,8 @@ where
         // Because we control AllocStorage, we know the handle is just a pointer
         // and that deallocating the handle is just calling Allocator::dealloc.
         // Convert the handle into just the pointer; forget the trivial storage.
-        let ptr = unsafe { alloc_storage.resolve_raw(alloc_handle, layout) }.as_mut_ptr();
+        let ptr = alloc_handle.as_ptr().as_ptr().cast::<MaybeUninit<u8>>();
+        alloc_handle.untrack();
         #[allow(clippy::forget_non_drop)]
         mem::forget(alloc_storage);
 
@@ -336,11 +339,11 @@ where
             // values have to be moved inline. Construct an inline box and call
             // the inline box conversion instead.
             let mut inline_storage = InlineStorage::<usize>::new();
-            inline_storage.allocate(layout).unwrap(); // already checked layout fits
+            let handle = inline_storage.allocate(layout).unwrap(); // already checked layout fits
             unsafe {
-                let inline_memory = inline_storage.resolve_mut((), layout);
+                let inline_memory = inline_storage.resolve_mut(handle, layout);
                 ptr::copy_nonoverlapping(ptr, inline_memory.as_mut_ptr(), layout.size());
-                return Self::inline(Box::from_raw_parts((), vtable, inline_storage));
+                return Self::inline(Box::from_raw_parts(handle, vtable, inline_storage));
             }
         }
 
@@ -368,13 +371,13 @@ where
         boxed: Box<U, InlineStorage<usize>>,
     ) -> Self {
         // Split the box into its vtable and inline storage.
-        let ((), vtable, inline_storage) = Box::into_raw_parts(boxed);
+        let (_handle, vtable, inline_storage) = Box::into_raw_parts(boxed);
 
-        // Because we control InlineStorage, we know it's just a transparent
-        // wrapper around MaybeUninit<usize>. Rather than trying to handle U,
-        // which is already unsized for us, we just move MaybeUninit<usize>
-        // around, which we know contains the actual U.
-        let memory = unsafe { mem::transmute::<_, MaybeUninit<usize>>(inline_storage) };
+        // Because we control InlineStorage, we know it's just a wrapper around
+        // MaybeUninit<usize>. Rather than trying to handle U, which is already
+        // unsized for us, we just move MaybeUninit<usize> around, which we know
+        // contains the actual U.
+        let memory = inline_storage.into_inner();
 
         // This is where the vtable wrapping should happen, but this is not
         // currently possible without new compiler features, so instead we've
@@ -406,11 +409,11 @@ where
             // values have to be moved inline. Construct an inline box and call
             // the inline box conversion instead.
             let mut inline_storage = InlineStorage::<usize>::new();
-            inline_storage.allocate(layout).unwrap(); // already checked layout fits
+            let handle = inline_storage.allocate(layout).unwrap(); // already checked layout fits
             unsafe {
-                let inline_memory = inline_storage.resolve_mut((), layout);
+                let inline_memory = inline_storage.resolve_mut(handle, layout);
                 ptr::copy_nonoverlapping(ptr.cast(), inline_memory.as_mut_ptr(), layout.size());
-                return Self::inline(Box::from_raw_parts((), vtable, inline_storage));
+                return Self::inline(Box::from_raw_parts(handle, vtable, inline_storage));
             }
         }
 
@@ -431,3 +434,50 @@ where
         unsafe { Box::from_raw_parts((), vtable, dyn_storage) }
     }
 }
+
+// And finally, getting a concrete box back out of dynamic storage.
+impl<U> Box<U, InlineStorage<usize>>
+where
+    // The unsized target type must be dyn Trait.
+    U: ?Sized + Pointee<Metadata = DynMetadata<U>>,
+{
+    /// Convert a dynamic storage box back into an inline box.
+    ///
+    /// This is the inverse of [`Box::inline`]. It only succeeds if the boxed
+    /// value is stored inline; otherwise, the dynamic storage box is dropped
+    /// and `None` is returned.
+    pub fn from_dyn(
+        // We start with a dynamic storage box of any provenance.
+        boxed: Box<U, DynStorage<'_>>,
+    ) -> Option<Self> {
+        // Get the layout of U before deconstructing the box.
+        let layout = Layout::for_value::<U>(&*boxed);
+
+        // Because the prototype moves all small values inline, a value is
+        // stored inline exactly when it fits inline. Anything else is a pointer
+        // to memory outside the box, which an inline box cannot own.
+        if !layout_fits_in(layout, Layout::new::<usize>()) {
+            return None;
+        }
+
+        // Split the box into its vtable and dynamic storage.
+        let ((), vtable, dyn_storage) = Box::into_raw_parts(boxed);
+
+        // Copy the inline data into a fresh inline storage, rather than
+        // reaching into InlineStorage's representation, so that the inline
+        // storage knows it is allocated.
+        let mut inline_storage = InlineStorage::<usize>::new();
+        let handle = inline_storage.allocate(layout).unwrap(); // already checked layout fits
+        unsafe {
+            let inline_memory = inline_storage.resolve_mut(handle, layout);
+            ptr::copy_nonoverlapping(
+                dyn_storage.storage.as_ptr().cast(),
+                inline_memory.as_mut_ptr(),
+                layout.size(),
+            );
+        }
+
+        // Construct the successfully concretized box.
+        Some(unsafe { Box::from_raw_parts(handle, vtable, inline_storage) })
+    }
+}
@@ -0,0 +1,156 @@
+use {
+    crate::{InfallibleEmptyStorage, Memory, PinningStorage, SharedMutabilityStorage, Storage},
+    core::{
+        alloc::{AllocError, Layout},
+        hint::unreachable_unchecked,
+    },
+};
+
+/// A storage which is one of two storages, chosen at construction.
+///
+/// Unlike a fallback storage, this never tries the other storage; e.g. one
+/// instance of a collection can be inline while another is allocated.
+pub enum EitherStorage<L, R> {
+    Left(L),
+    Right(R),
+}
+
+/// The handle type of [`EitherStorage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EitherHandle<L, R> {
+    Left(L),
+    Right(R),
+}
+
+// Handles are only ever used with the storage which created them, so the
+// variant of the handle always matches the variant of the storage.
+macro_rules! either {
+    ($storage:expr, $handle:expr, |$s:ident, $h:ident| $body:expr) => {
+        match ($storage, $handle) {
+            (EitherStorage::Left($s), EitherHandle::Left($h)) => $body,
+            (EitherStorage::Right($s), EitherHandle::Right($h)) => $body,
+            _ => {
+                debug_assert!(false, "EitherStorage handle used with the other storage");
+                unreachable_unchecked()
+            },
+        }
+    };
+}
+
+unsafe impl<L: Storage, R: Storage> Storage for EitherStorage<L, R> {
+    type Handle = EitherHandle<L::Handle, R::Handle>;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = L::EMPTY_LAYOUT_IS_FREE && R::EMPTY_LAYOUT_IS_FREE;
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool =
+        L::NEEDS_LAYOUT_FOR_DEALLOC || R::NEEDS_LAYOUT_FOR_DEALLOC;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        match self {
+            EitherStorage::Left(storage) => storage.allocate(layout).map(EitherHandle::Left),
+            EitherStorage::Right(storage) => storage.allocate(layout).map(EitherHandle::Right),
+        }
+    }
+
+    fn allocate_with_excess(
+        &mut self,
+        layout: Layout,
+    ) -> Result<(Self::Handle, Layout), AllocError> {
+        match self {
+            EitherStorage::Left(storage) => storage
+                .allocate_with_excess(layout)
+                .map(|(handle, layout)| (EitherHandle::Left(handle), layout)),
+            EitherStorage::Right(storage) => storage
+                .allocate_with_excess(layout)
+                .map(|(handle, layout)| (EitherHandle::Right(handle), layout)),
+        }
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        match self {
+            EitherStorage::Left(storage) => storage.allocate_empty(align).map(EitherHandle::Left),
+            EitherStorage::Right(storage) => storage.allocate_empty(align).map(EitherHandle::Right),
+        }
+    }
+
+    fn current_handle(&self) -> Option<Self::Handle> {
+        match self {
+            EitherStorage::Left(storage) => storage.current_handle().map(EitherHandle::Left),
+            EitherStorage::Right(storage) => storage.current_handle().map(EitherHandle::Right),
+        }
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        either!(self, handle, |storage, handle| storage
+            .deallocate(handle, layout))
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        either!(self, handle, |storage, handle| storage
+            .resolve(handle, layout))
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        either!(self, handle, |storage, handle| storage
+            .resolve_mut(handle, layout))
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        match (self, handle) {
+            (EitherStorage::Left(storage), EitherHandle::Left(handle)) => storage
+                .grow(handle, old_layout, new_layout)
+                .map(EitherHandle::Left),
+            (EitherStorage::Right(storage), EitherHandle::Right(handle)) => storage
+                .grow(handle, old_layout, new_layout)
+                .map(EitherHandle::Right),
+            _ => {
+                debug_assert!(false, "EitherStorage handle used with the other storage");
+                unreachable_unchecked()
+            },
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        match (self, handle) {
+            (EitherStorage::Left(storage), EitherHandle::Left(handle)) => storage
+                .shrink(handle, old_layout, new_layout)
+                .map(EitherHandle::Left),
+            (EitherStorage::Right(storage), EitherHandle::Right(handle)) => storage
+                .shrink(handle, old_layout, new_layout)
+                .map(EitherHandle::Right),
+            _ => {
+                debug_assert!(false, "EitherStorage handle used with the other storage");
+                unreachable_unchecked()
+            },
+        }
+    }
+}
+
+unsafe impl<L, R> SharedMutabilityStorage for EitherStorage<L, R>
+where
+    L: SharedMutabilityStorage,
+    R: SharedMutabilityStorage,
+{
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        either!(self, handle, |storage, handle| storage
+            .resolve_raw(handle, layout))
+    }
+}
+
+unsafe impl<L: PinningStorage, R: PinningStorage> PinningStorage for EitherStorage<L, R> {}
+
+unsafe impl<L, R> InfallibleEmptyStorage for EitherStorage<L, R>
+where
+    L: InfallibleEmptyStorage,
+    R: InfallibleEmptyStorage,
+{
+}
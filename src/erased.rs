@@ -0,0 +1,205 @@
+use {
+    crate::{AllocHandle, Memory, Storage},
+    core::{
+        alloc::{AllocError, Layout},
+        ptr,
+    },
+};
+
+#[cfg(feature = "checked")]
+use crate::{BumpHandle, PoolHandle, SingleHandle};
+
+/// An object-safe subset of [`Storage`], with a fixed handle type.
+///
+/// [`Storage`] cannot be used as a trait object, as its handle type varies
+/// between implementations. This trait instead uses [`ErasedHandle`] for all
+/// storages, so heterogeneous storages can be selected at runtime behind a
+/// `&mut dyn DynStorageTrait`.
+///
+/// Any [`Storage`] whose handle is [`ErasableHandle`] implements this trait
+/// when wrapped in [`Erased`], and `&mut dyn DynStorageTrait` in turn
+/// implements [`Storage`].
+///
+/// The methods have the same semantics and safety requirements as their
+/// [`Storage`] counterparts.
+pub unsafe trait DynStorageTrait {
+    /// See [`Storage::allocate`].
+    fn allocate(&mut self, layout: Layout) -> Result<ErasedHandle, AllocError>;
+
+    /// See [`Storage::deallocate`].
+    unsafe fn deallocate(&mut self, handle: ErasedHandle, layout: Layout);
+
+    /// See [`Storage::resolve`].
+    unsafe fn resolve(&self, handle: ErasedHandle, layout: Layout) -> &Memory;
+
+    /// See [`Storage::resolve_mut`].
+    unsafe fn resolve_mut(&mut self, handle: ErasedHandle, layout: Layout) -> &mut Memory;
+
+    /// See [`Storage::grow`].
+    unsafe fn grow(
+        &mut self,
+        handle: ErasedHandle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<ErasedHandle, AllocError>;
+
+    /// See [`Storage::shrink`].
+    unsafe fn shrink(
+        &mut self,
+        handle: ErasedHandle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<ErasedHandle, AllocError>;
+}
+
+/// The handle type of [`DynStorageTrait`].
+///
+/// This is pointer sized, and holds the bytes of some [`ErasableHandle`]. It
+/// is stored as a pointer so that pointer handles keep their provenance, and
+/// is passed like a pointer by the C ABI of [`CStorage`](crate::CStorage).
+///
+/// With the `checked` feature, this is instead a pointer followed by a
+/// `usize`, to also hold the generation of the built-in handles, and is passed
+/// like a `repr(C)` struct of the two.
+#[cfg_attr(not(feature = "checked"), repr(transparent))]
+#[cfg_attr(feature = "checked", repr(C))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ErasedHandle {
+    bits: *mut (),
+    #[cfg(feature = "checked")]
+    generation: usize,
+}
+
+unsafe impl Send for ErasedHandle {}
+unsafe impl Sync for ErasedHandle {}
+
+impl ErasedHandle {
+    pub(crate) fn erase<H: ErasableHandle>(handle: H) -> Self {
+        let mut this = Self {
+            bits: ptr::null_mut(),
+            #[cfg(feature = "checked")]
+            generation: 0,
+        };
+        unsafe { ptr::addr_of_mut!(this).cast::<H>().write(handle) };
+        this
+    }
+
+    /// # Safety
+    ///
+    /// - The handle must have been created by erasing a handle of type `H`.
+    pub(crate) unsafe fn unerase<H: ErasableHandle>(self) -> H {
+        ptr::addr_of!(self).cast::<H>().read()
+    }
+}
+
+/// A storage handle which can be stored in an [`ErasedHandle`].
+///
+/// # Safety
+///
+/// - The handle must not be larger or more aligned than an [`ErasedHandle`],
+///   which is a pointer, or with the `checked` feature, a pointer and a
+///   `usize`.
+/// - The handle must not contain any padding bytes.
+/// - A pointer in the handle must be at its start, so that it keeps its
+///   provenance.
+pub unsafe trait ErasableHandle: Copy {}
+
+unsafe impl ErasableHandle for () {}
+unsafe impl ErasableHandle for usize {}
+unsafe impl ErasableHandle for AllocHandle {}
+#[cfg(feature = "checked")]
+unsafe impl ErasableHandle for SingleHandle {}
+#[cfg(feature = "checked")]
+unsafe impl ErasableHandle for BumpHandle {}
+#[cfg(feature = "checked")]
+unsafe impl ErasableHandle for PoolHandle {}
+
+/// Adapts a [`Storage`] whose handle is [`ErasableHandle`] to
+/// [`DynStorageTrait`].
+///
+/// (A blanket impl for every such storage would be more convenient, but it
+/// currently crashes rustc.)
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(transparent)]
+pub struct Erased<S>(pub S);
+
+unsafe impl<S: Storage> DynStorageTrait for Erased<S>
+where
+    S::Handle: ErasableHandle,
+{
+    fn allocate(&mut self, layout: Layout) -> Result<ErasedHandle, AllocError> {
+        self.0.allocate(layout).map(ErasedHandle::erase)
+    }
+
+    unsafe fn deallocate(&mut self, handle: ErasedHandle, layout: Layout) {
+        self.0.deallocate(handle.unerase(), layout)
+    }
+
+    unsafe fn resolve(&self, handle: ErasedHandle, layout: Layout) -> &Memory {
+        self.0.resolve(handle.unerase(), layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: ErasedHandle, layout: Layout) -> &mut Memory {
+        self.0.resolve_mut(handle.unerase(), layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: ErasedHandle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<ErasedHandle, AllocError> {
+        self.0
+            .grow(handle.unerase(), old_layout, new_layout)
+            .map(ErasedHandle::erase)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: ErasedHandle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<ErasedHandle, AllocError> {
+        self.0
+            .shrink(handle.unerase(), old_layout, new_layout)
+            .map(ErasedHandle::erase)
+    }
+}
+
+unsafe impl<'a, 'b> Storage for &'a mut (dyn DynStorageTrait + 'b) {
+    type Handle = ErasedHandle;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        (**self).deallocate(handle, layout)
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        (**self).resolve(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        (**self).resolve_mut(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        (**self).grow(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        (**self).shrink(handle, old_layout, new_layout)
+    }
+}
@@ -0,0 +1,308 @@
+use {
+    crate::{ErasableHandle, ErasedHandle, Memory, Storage},
+    core::{
+        alloc::{AllocError, Layout},
+        marker::PhantomData,
+        mem::{self, MaybeUninit},
+        ptr,
+    },
+};
+
+/// The vtable of a [`CStorage`], with C ABI functions.
+///
+/// This is for storages provided across an ABI boundary, e.g. by the host of
+/// a plugin, so nothing in it is generic. Each function takes the storage's
+/// data pointer first, and layouts as a size and alignment pair.
+///
+/// The functions have the same semantics and safety requirements as their
+/// [`Storage`] counterparts. Fallible functions return whether they succeeded,
+/// writing the new handle through the last argument if so.
+#[repr(C)]
+pub struct CStorageVtable {
+    pub allocate: unsafe extern "C" fn(*mut (), usize, usize, *mut ErasedHandle) -> bool,
+    pub deallocate: unsafe extern "C" fn(*mut (), ErasedHandle, usize, usize),
+    pub resolve: unsafe extern "C" fn(*const (), ErasedHandle, usize, usize) -> *const u8,
+    pub resolve_mut: unsafe extern "C" fn(*mut (), ErasedHandle, usize, usize) -> *mut u8,
+    pub grow: unsafe extern "C" fn(
+        *mut (),
+        ErasedHandle,
+        usize,
+        usize,
+        usize,
+        usize,
+        *mut ErasedHandle,
+    ) -> bool,
+    pub shrink: unsafe extern "C" fn(
+        *mut (),
+        ErasedHandle,
+        usize,
+        usize,
+        usize,
+        usize,
+        *mut ErasedHandle,
+    ) -> bool,
+}
+
+/// A storage behind a [`CStorageVtable`].
+///
+/// This is a (data pointer, vtable) pair with a C layout, so it can be passed
+/// across an ABI boundary whole. [`FromStorage`] provides the vtable for any
+/// [`Storage`] whose handle is [`ErasableHandle`].
+#[repr(C)]
+pub struct CStorage<'a> {
+    data: *mut (),
+    vtable: &'a CStorageVtable,
+    _marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a> CStorage<'a> {
+    /// Create a storage from a data pointer and vtable.
+    ///
+    /// # Safety
+    ///
+    /// - The vtable functions must implement a [`Storage`] when given `data`.
+    /// - `data` must be valid for the vtable functions for `'a`.
+    pub unsafe fn from_raw_parts(data: *mut (), vtable: &'a CStorageVtable) -> Self {
+        Self {
+            data,
+            vtable,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the data pointer and vtable of this storage.
+    pub fn into_raw_parts(self) -> (*mut (), &'a CStorageVtable) {
+        (self.data, self.vtable)
+    }
+}
+
+/// The handle written through an out pointer, if the vtable function returned
+/// success.
+fn written(success: bool, handle: MaybeUninit<ErasedHandle>) -> Result<ErasedHandle, AllocError> {
+    if success {
+        Ok(unsafe { handle.assume_init() })
+    } else {
+        Err(AllocError)
+    }
+}
+
+/// Memory of `layout.size()` bytes at `ptr`.
+fn memory(ptr: *mut u8, layout: Layout) -> *mut Memory {
+    ptr::from_raw_parts_mut(ptr.cast(), layout.size())
+}
+
+unsafe impl Storage for CStorage<'_> {
+    type Handle = ErasedHandle;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        let mut handle = MaybeUninit::uninit();
+        let allocated = unsafe {
+            (self.vtable.allocate)(
+                self.data,
+                layout.size(),
+                layout.align(),
+                handle.as_mut_ptr(),
+            )
+        };
+        written(allocated, handle)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        (self.vtable.deallocate)(self.data, handle, layout.size(), layout.align())
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        let ptr = (self.vtable.resolve)(self.data, handle, layout.size(), layout.align());
+        // Only reborrow as shared, as the pointer is only valid for reads.
+        &*memory(ptr as *mut u8, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        let ptr = (self.vtable.resolve_mut)(self.data, handle, layout.size(), layout.align());
+        &mut *memory(ptr, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        let mut new_handle = MaybeUninit::uninit();
+        let grown = (self.vtable.grow)(
+            self.data,
+            handle,
+            old_layout.size(),
+            old_layout.align(),
+            new_layout.size(),
+            new_layout.align(),
+            new_handle.as_mut_ptr(),
+        );
+        written(grown, new_handle)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        let mut new_handle = MaybeUninit::uninit();
+        let shrunk = (self.vtable.shrink)(
+            self.data,
+            handle,
+            old_layout.size(),
+            old_layout.align(),
+            new_layout.size(),
+            new_layout.align(),
+            new_handle.as_mut_ptr(),
+        );
+        written(shrunk, new_handle)
+    }
+}
+
+/// Provides a [`CStorageVtable`] for a [`Storage`].
+///
+/// Unwinding out of the C ABI functions would be undefined behavior, so if one
+/// of the storage's functions panics, the process aborts instead.
+pub struct FromStorage<S>(pub S);
+
+/// Run `f`, aborting the process if it panics.
+fn abort_on_unwind<R>(f: impl FnOnce() -> R) -> R {
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            // core can't abort directly, but panicking while unwinding does.
+            panic!("a storage panicked behind a C ABI vtable");
+        }
+    }
+
+    let guard = Guard;
+    let result = f();
+    mem::forget(guard);
+    result
+}
+
+impl<S: Storage> FromStorage<S>
+where
+    S::Handle: ErasableHandle,
+{
+    /// The vtable for `S`, to be used with a pointer to an `S` as data.
+    pub const VTABLE: CStorageVtable = CStorageVtable {
+        allocate: Self::allocate,
+        deallocate: Self::deallocate,
+        resolve: Self::resolve,
+        resolve_mut: Self::resolve_mut,
+        grow: Self::grow,
+        shrink: Self::shrink,
+    };
+
+    /// Borrow the storage as a [`CStorage`].
+    pub fn as_c_storage(&mut self) -> CStorage<'_> {
+        unsafe { CStorage::from_raw_parts((&mut self.0 as *mut S).cast(), &Self::VTABLE) }
+    }
+
+    unsafe extern "C" fn allocate(
+        data: *mut (),
+        size: usize,
+        align: usize,
+        handle: *mut ErasedHandle,
+    ) -> bool {
+        abort_on_unwind(|| {
+            let layout = Layout::from_size_align_unchecked(size, align);
+            match (*data.cast::<S>()).allocate(layout) {
+                Ok(allocated) => {
+                    handle.write(ErasedHandle::erase(allocated));
+                    true
+                },
+                Err(AllocError) => false,
+            }
+        })
+    }
+
+    unsafe extern "C" fn deallocate(
+        data: *mut (),
+        handle: ErasedHandle,
+        size: usize,
+        align: usize,
+    ) {
+        abort_on_unwind(|| {
+            let layout = Layout::from_size_align_unchecked(size, align);
+            (*data.cast::<S>()).deallocate(handle.unerase(), layout)
+        })
+    }
+
+    unsafe extern "C" fn resolve(
+        data: *const (),
+        handle: ErasedHandle,
+        size: usize,
+        align: usize,
+    ) -> *const u8 {
+        abort_on_unwind(|| {
+            let layout = Layout::from_size_align_unchecked(size, align);
+            (*data.cast::<S>())
+                .resolve(handle.unerase(), layout)
+                .as_ptr()
+                .cast()
+        })
+    }
+
+    unsafe extern "C" fn resolve_mut(
+        data: *mut (),
+        handle: ErasedHandle,
+        size: usize,
+        align: usize,
+    ) -> *mut u8 {
+        abort_on_unwind(|| {
+            let layout = Layout::from_size_align_unchecked(size, align);
+            (*data.cast::<S>())
+                .resolve_mut(handle.unerase(), layout)
+                .as_mut_ptr()
+                .cast()
+        })
+    }
+
+    unsafe extern "C" fn grow(
+        data: *mut (),
+        handle: ErasedHandle,
+        old_size: usize,
+        old_align: usize,
+        new_size: usize,
+        new_align: usize,
+        new_handle: *mut ErasedHandle,
+    ) -> bool {
+        abort_on_unwind(|| {
+            let old_layout = Layout::from_size_align_unchecked(old_size, old_align);
+            let new_layout = Layout::from_size_align_unchecked(new_size, new_align);
+            match (*data.cast::<S>()).grow(handle.unerase(), old_layout, new_layout) {
+                Ok(grown) => {
+                    new_handle.write(ErasedHandle::erase(grown));
+                    true
+                },
+                Err(AllocError) => false,
+            }
+        })
+    }
+
+    unsafe extern "C" fn shrink(
+        data: *mut (),
+        handle: ErasedHandle,
+        old_size: usize,
+        old_align: usize,
+        new_size: usize,
+        new_align: usize,
+        new_handle: *mut ErasedHandle,
+    ) -> bool {
+        abort_on_unwind(|| {
+            let old_layout = Layout::from_size_align_unchecked(old_size, old_align);
+            let new_layout = Layout::from_size_align_unchecked(new_size, new_align);
+            match (*data.cast::<S>()).shrink(handle.unerase(), old_layout, new_layout) {
+                Ok(shrunk) => {
+                    new_handle.write(ErasedHandle::erase(shrunk));
+                    true
+                },
+                Err(AllocError) => false,
+            }
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use {
+    crate::{Box, EitherStorage, InlineStorage, Storage},
+    core::{
+        alloc::Layout,
+        ops::{Deref, DerefMut},
+    },
+};
+
+#[cfg(feature = "alloc")]
+use {crate::AllocStorage, liballoc::alloc::Global};
+
+/// A box which stores its value inline if it fits in `DataStore`, and
+/// otherwise in the storage `S`.
+///
+/// This is a [`Box`] in an [`EitherStorage`] of an [`InlineStorage`] and `S`,
+/// with the storage chosen by the value's layout. Unlike a box in a
+/// [`SmallStorage`](crate::SmallStorage), the storage `S` is only kept (and
+/// never allocated from) if the value doesn't fit inline.
+pub struct FlexBox<T, DataStore, S: Storage> {
+    boxed: Box<T, EitherStorage<InlineStorage<DataStore>, S>>,
+}
+
+impl<T, DataStore, S: Storage> FlexBox<T, DataStore, S> {
+    fn fits_inline() -> bool {
+        InlineStorage::<DataStore>::new().fits(Layout::new::<T>())
+    }
+
+    /// Box a value inline if it fits, otherwise in the given storage.
+    #[track_caller]
+    pub fn new_in(value: T, storage: S) -> Self {
+        let storage = if Self::fits_inline() {
+            EitherStorage::Left(InlineStorage::new())
+        } else {
+            EitherStorage::Right(storage)
+        };
+        Self {
+            boxed: Box::new_in(value, storage),
+        }
+    }
+
+    /// Check if the value is stored inline.
+    pub fn is_inline(_this: &Self) -> bool {
+        Self::fits_inline()
+    }
+
+    /// Convert into the underlying box.
+    pub fn into_box(this: Self) -> Box<T, EitherStorage<InlineStorage<DataStore>, S>> {
+        this.boxed
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, DataStore> FlexBox<T, DataStore, AllocStorage<Global>> {
+    /// Box a value inline if it fits, otherwise in the global allocator.
+    #[track_caller]
+    pub fn new(value: T) -> Self {
+        Self::new_in(value, AllocStorage::new(Global))
+    }
+}
+
+impl<T, DataStore, S: Storage> Deref for FlexBox<T, DataStore, S> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.boxed
+    }
+}
+
+impl<T, DataStore, S: Storage> DerefMut for FlexBox<T, DataStore, S> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.boxed
+    }
+}
@@ -0,0 +1,210 @@
+use {
+    crate::{polyfill::handle_alloc_error, InfallibleEmptyStorage, RawVec, Storage},
+    core::{
+        alloc::Layout,
+        hash::{BuildHasher, Hash},
+        mem::{self, MaybeUninit},
+        ptr,
+    },
+};
+
+/// A bucket of a [`HashMap`].
+enum Bucket<K, V> {
+    Empty,
+    Full(K, V),
+    /// An entry which hasn't been moved to its new place since growing.
+    Stale(K, V),
+}
+
+/// A hash map with open addressing, in the single allocation of a [`RawVec`].
+///
+/// There are no per-entry handles, so this works with single storages.
+/// Colliding entries probe linearly, and removing an entry shifts the
+/// following ones back instead of leaving a tombstone. The number of buckets
+/// is always zero or a power of two, and doubles when the map is 3/4 full;
+/// the buckets are grown in place and the entries rehashed within them, as a
+/// single storage can't hold a second bucket array to move them to.
+pub struct HashMap<K, V, S: Storage, H> {
+    buckets: RawVec<Bucket<K, V>, S>,
+    len: usize,
+    hasher: H,
+}
+
+/// The bucket index a key probes from.
+fn home<K: Hash, H: BuildHasher>(hasher: &H, key: &K, mask: usize) -> usize {
+    hasher.hash_one(key) as usize & mask
+}
+
+/// # Safety
+///
+/// - The buckets must be initialized. (Growing fills the new buckets with
+///   [`Bucket::Empty`], so every bucket of a map always is.)
+unsafe fn assume_init<K, V>(buckets: &mut [MaybeUninit<Bucket<K, V>>]) -> &mut [Bucket<K, V>] {
+    &mut *(buckets as *mut [MaybeUninit<Bucket<K, V>>] as *mut [Bucket<K, V>])
+}
+
+impl<K: Hash + Eq, V, S: Storage, H: BuildHasher> HashMap<K, V, S, H> {
+    pub fn with_hasher_in(hasher: H, storage: S) -> Self
+    where
+        S: InfallibleEmptyStorage,
+    {
+        Self {
+            buckets: RawVec::new_in(storage),
+            len: 0,
+            hasher,
+        }
+    }
+
+    pub fn new_in(storage: S) -> Self
+    where
+        S: InfallibleEmptyStorage,
+        H: Default,
+    {
+        Self::with_hasher_in(H::default(), storage)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    fn mask(&self) -> usize {
+        self.buckets.len().wrapping_sub(1)
+    }
+
+    fn buckets(&self) -> &[Bucket<K, V>] {
+        let buckets = self.buckets.as_ref();
+        unsafe { &*(buckets as *const [MaybeUninit<Bucket<K, V>>] as *const [Bucket<K, V>]) }
+    }
+
+    /// Find the bucket index of a key.
+    fn find(&self, key: &K) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let (buckets, mask) = (self.buckets(), self.mask());
+        let mut i = home(&self.hasher, key, mask);
+        loop {
+            match &buckets[i] {
+                Bucket::Full(k, _) if k == key => return Some(i),
+                Bucket::Full(..) => i = (i + 1) & mask,
+                _ => return None,
+            }
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        match &self.buckets()[self.find(key)?] {
+            Bucket::Full(_, value) => Some(value),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let i = self.find(key)?;
+        match unsafe { &mut assume_init(self.buckets.as_mut())[i] } {
+            Bucket::Full(_, value) => Some(value),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Insert a value, returning the old value if the key was already present.
+    #[track_caller]
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(old) = self.get_mut(&key) {
+            return Some(mem::replace(old, value));
+        }
+
+        // Keep an empty bucket to end every probe.
+        if (self.len + 1) * 4 > self.buckets.len() * 3 {
+            self.grow();
+        }
+        let mask = self.mask();
+        let mut i = home(&self.hasher, &key, mask);
+        let buckets = unsafe { assume_init(self.buckets.as_mut()) };
+        while let Bucket::Full(..) = buckets[i] {
+            i = (i + 1) & mask;
+        }
+        buckets[i] = Bucket::Full(key, value);
+        self.len += 1;
+        None
+    }
+
+    /// Remove an entry, returning its value.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let mut hole = self.find(key)?;
+        let mask = self.mask();
+        let buckets = unsafe { assume_init(self.buckets.as_mut()) };
+        let value = match mem::replace(&mut buckets[hole], Bucket::Empty) {
+            Bucket::Full(_, value) => value,
+            _ => unreachable!(),
+        };
+        self.len -= 1;
+
+        // Shift back the following entries which probed past the hole, so
+        // that no probe stops short at it.
+        let mut i = (hole + 1) & mask;
+        while let Bucket::Full(key, _) = &buckets[i] {
+            let start = home(&self.hasher, key, mask);
+            if i.wrapping_sub(start) & mask >= i.wrapping_sub(hole) & mask {
+                buckets[hole] = mem::replace(&mut buckets[i], Bucket::Empty);
+                hole = i;
+            }
+            i = (i + 1) & mask;
+        }
+        Some(value)
+    }
+
+    /// Double the number of buckets, and rehash the entries in place.
+    #[track_caller]
+    fn grow(&mut self) {
+        let old_len = self.buckets.len();
+        let new_len = if old_len == 0 { 4 } else { old_len * 2 };
+        if self.buckets.grow_to(new_len).is_err() {
+            handle_alloc_error(
+                Layout::array::<Bucket<K, V>>(new_len).unwrap_or(Layout::new::<Bucket<K, V>>()),
+            )
+        }
+        for bucket in &mut self.buckets.as_mut()[old_len..] {
+            bucket.write(Bucket::Empty);
+        }
+
+        let mask = self.mask();
+        let buckets = unsafe { assume_init(self.buckets.as_mut()) };
+        for bucket in &mut buckets[..old_len] {
+            if let Bucket::Full(..) = bucket {
+                *bucket = match mem::replace(bucket, Bucket::Empty) {
+                    Bucket::Full(key, value) => Bucket::Stale(key, value),
+                    _ => unreachable!(),
+                };
+            }
+        }
+
+        // Move each stale entry to the first bucket along its probe which is
+        // empty or stale. If that is another stale entry, swap them and go on
+        // with that one. Full buckets never move, so every bucket between an
+        // entry's home and the entry stays full.
+        for i in 0..old_len {
+            while let Bucket::Stale(key, _) = &buckets[i] {
+                let mut j = home(&self.hasher, key, mask);
+                while let Bucket::Full(..) = buckets[j] {
+                    j = (j + 1) & mask;
+                }
+                let entry = match mem::replace(&mut buckets[i], Bucket::Empty) {
+                    Bucket::Stale(key, value) => Bucket::Full(key, value),
+                    _ => unreachable!(),
+                };
+                let displaced = mem::replace(&mut buckets[j], entry);
+                if j != i {
+                    buckets[i] = displaced;
+                }
+            }
+        }
+    }
+}
+
+unsafe impl<#[may_dangle] K, #[may_dangle] V, S: Storage, H> Drop for HashMap<K, V, S, H> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(assume_init(self.buckets.as_mut())) }
+    }
+}
@@ -0,0 +1,105 @@
+use {
+    crate::{InfallibleEmptyStorage, Memory, PinningStorage, SharedMutabilityStorage, Storage},
+    core::alloc::{AllocError, Layout},
+};
+
+/// A storage which records a histogram of the sizes requested from another
+/// storage.
+///
+/// This is pure instrumentation, e.g. to pick the inline size of a
+/// [`SmallStorage`](crate::SmallStorage). Every size passed to `allocate`,
+/// `grow`, or `shrink` is counted, whether or not the request succeeds, and
+/// `allocate_empty` counts as size zero.
+pub struct HistogramStorage<S> {
+    storage: S,
+    histogram: [usize; 64],
+}
+
+impl<S> HistogramStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            histogram: [0; 64],
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    /// The number of requests by size, bucketed by power of two.
+    ///
+    /// Bucket `i` counts the sizes which round up to `2^i`, i.e. sizes in
+    /// `2^(i-1)+1..=2^i`. Bucket 0 also counts zero-sized requests.
+    pub fn histogram(&self) -> [usize; 64] {
+        self.histogram
+    }
+
+    fn record(&mut self, layout: Layout) {
+        let bucket = usize::BITS - layout.size().saturating_sub(1).leading_zeros();
+        self.histogram[bucket as usize] += 1;
+    }
+}
+
+unsafe impl<S: Storage> Storage for HistogramStorage<S> {
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = S::NEEDS_LAYOUT_FOR_DEALLOC;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        self.record(layout);
+        self.storage.allocate(layout)
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        self.histogram[0] += 1;
+        self.storage.allocate_empty(align)
+    }
+
+    fn current_handle(&self) -> Option<Self::Handle> {
+        self.storage.current_handle()
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.storage.deallocate(handle, layout)
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.storage.resolve(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_mut(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.record(new_layout);
+        self.storage.grow(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.record(new_layout);
+        self.storage.shrink(handle, old_layout, new_layout)
+    }
+}
+
+unsafe impl<S: SharedMutabilityStorage> SharedMutabilityStorage for HistogramStorage<S> {
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_raw(handle, layout)
+    }
+}
+
+unsafe impl<S: PinningStorage> PinningStorage for HistogramStorage<S> {}
+
+unsafe impl<S: InfallibleEmptyStorage> InfallibleEmptyStorage for HistogramStorage<S> {}
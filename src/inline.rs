@@ -1,5 +1,8 @@
 use {
-    crate::{polyfill::layout_fits_in, Memory, Storage},
+    crate::{
+        polyfill::{dangling, layout_fits_in, poison},
+        InfallibleEmptyStorage, LayoutDataStore, Memory, Storage,
+    },
     core::{
         alloc::{AllocError, Layout},
         mem::MaybeUninit,
@@ -11,43 +14,192 @@ use {
 ///
 /// The `DataStore` type parameter determines the layout of the inline storage.
 /// (It would be nice to use `const LAYOUT: Layout` instead, but the needed
-/// features are currently a little *too* incomplete to be usable here yet.)
-#[repr(transparent)]
+/// features are currently a little *too* incomplete to be usable here yet.
+/// [`InlineLayoutStorage`] gets close, taking the size and alignment.)
+///
+/// With the `checked` feature, this additionally counts its allocations and
+/// deallocations in a generation, to catch a second allocation aliasing the
+/// single memory handle, and its handles carry the generation, to catch
+/// resolving a handle of an earlier generation. Otherwise, it is a transparent
+/// wrapper around `MaybeUninit<DataStore>`.
+#[cfg_attr(not(feature = "checked"), repr(transparent))]
 pub struct InlineStorage<DataStore> {
     data: MaybeUninit<DataStore>,
+    /// Odd while the storage is allocated.
+    #[cfg(feature = "checked")]
+    generation: usize,
+}
+
+/// The memory handle of the single storages, such as [`InlineStorage`].
+///
+/// There is only one memory handle, so this is `()`. With the `checked`
+/// feature, it is instead the generation of the storage that allocated it.
+#[cfg(not(feature = "checked"))]
+pub type SingleHandle = ();
+
+/// The memory handle of the single storages, such as [`InlineStorage`].
+///
+/// This is the generation of the storage that allocated it, which the storage
+/// checks to catch resolving the handle after it was deallocated, even if the
+/// storage was allocated again since. Without the `checked` feature, this is
+/// `()`, as there is only one memory handle.
+#[cfg(feature = "checked")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SingleHandle {
+    pub(crate) generation: usize,
+}
+
+/// Count an allocation of a single storage, and get its handle.
+#[cfg(feature = "checked")]
+pub(crate) fn next_handle(generation: &mut usize, storage: &str) -> SingleHandle {
+    assert!(*generation & 1 == 0, "{storage} is already allocated");
+    *generation = generation.wrapping_add(1);
+    SingleHandle {
+        generation: *generation,
+    }
+}
+
+/// Catch a single storage resolving a handle of an earlier generation.
+#[cfg(feature = "checked")]
+pub(crate) fn check_handle(generation: usize, handle: SingleHandle, storage: &str) {
+    assert!(
+        handle.generation == generation,
+        "{storage} resolved an invalidated handle"
+    );
 }
 
 impl<DataStore> InlineStorage<DataStore> {
     pub const fn new() -> Self {
         Self {
             data: MaybeUninit::uninit(),
+            #[cfg(feature = "checked")]
+            generation: 0,
         }
     }
 
+    /// Allocate the memory in a const context, initialized to `value`.
+    pub(crate) const fn const_allocate_with(mut self, value: DataStore) -> (Self, SingleHandle) {
+        #[cfg(feature = "checked")]
+        {
+            assert!(
+                self.generation & 1 == 0,
+                "InlineStorage is already allocated"
+            );
+            self.generation = self.generation.wrapping_add(1);
+        }
+        self.data = MaybeUninit::new(value);
+        #[cfg(not(feature = "checked"))]
+        return (self, ());
+        #[cfg(feature = "checked")]
+        {
+            let generation = self.generation;
+            (self, SingleHandle { generation })
+        }
+    }
+
+    /// Take the inline memory out of this storage.
+    pub(crate) fn into_inner(self) -> MaybeUninit<DataStore> {
+        self.data
+    }
+
+    /// Check if memory of the given layout fits in this storage.
+    ///
+    /// Zero-sized layouts always fit, as they don't need any memory.
     pub fn fits(&self, needed_layout: Layout) -> bool {
+        if needed_layout.size() == 0 {
+            return true;
+        }
+
         let available_layout = Layout::new::<DataStore>();
         layout_fits_in(needed_layout, available_layout)
     }
+
+    fn new_handle(&mut self) -> SingleHandle {
+        #[cfg(feature = "checked")]
+        return next_handle(&mut self.generation, "InlineStorage");
+    }
+
+    #[cfg(feature = "checked")]
+    fn check(&self, handle: SingleHandle) {
+        check_handle(self.generation, handle, "InlineStorage");
+    }
 }
 
+/// A single storage which stores memory inline in a byte array.
+///
+/// This has an exact capacity of `N` bytes, with an alignment of 1.
+pub type ByteInlineStorage<const N: usize> = InlineStorage<[MaybeUninit<u8>; N]>;
+
+impl<const N: usize> ByteInlineStorage<N> {
+    /// Use an existing byte array as inline storage.
+    pub const fn from_bytes(bytes: [MaybeUninit<u8>; N]) -> Self {
+        Self {
+            data: MaybeUninit::new(bytes),
+            #[cfg(feature = "checked")]
+            generation: 0,
+        }
+    }
+
+    /// Take the byte array back out of this storage.
+    pub fn into_bytes(self) -> [MaybeUninit<u8>; N] {
+        unsafe { self.data.assume_init() }
+    }
+}
+
+/// A single storage which stores memory inline in an array of `N` `T`s.
+///
+/// This fits a slice of up to `N` `T`s, and is aligned to `T`; it's the
+/// backing of an [`ArrayVec`](crate::ArrayVec).
+pub type InlineArrayStorage<T, const N: usize> = InlineStorage<[T; N]>;
+
+/// A single storage which stores memory inline, with (at least) a layout of
+/// `SIZE` bytes aligned to `ALIGN`.
+///
+/// `ALIGN` must be a power of two no greater than 4096; see [`LayoutDataStore`].
+pub type InlineLayoutStorage<const SIZE: usize, const ALIGN: usize> =
+    InlineStorage<LayoutDataStore<SIZE, ALIGN>>;
+
 unsafe impl<DataStore> Storage for InlineStorage<DataStore> {
-    type Handle = ();
+    type Handle = SingleHandle;
+
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = false;
 
     fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
         if self.fits(layout) {
-            Ok(())
+            let handle = self.new_handle();
+            Ok(handle)
         } else {
             Err(AllocError)
         }
     }
 
-    unsafe fn deallocate(&mut self, _handle: Self::Handle, _layout: Layout) {}
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        poison(self.resolve_mut(handle, layout));
+        #[cfg(feature = "checked")]
+        {
+            self.generation = self.generation.wrapping_add(1);
+        }
+    }
 
     unsafe fn resolve(&self, _handle: Self::Handle, layout: Layout) -> &Memory {
+        #[cfg(feature = "checked")]
+        self.check(_handle);
+
+        if layout.size() == 0 {
+            return &*ptr::from_raw_parts(dangling(layout).as_ptr().cast(), 0);
+        }
+
         &*ptr::from_raw_parts(self.data.as_ptr().cast(), layout.size())
     }
 
     unsafe fn resolve_mut(&mut self, _handle: Self::Handle, layout: Layout) -> &mut Memory {
+        #[cfg(feature = "checked")]
+        self.check(_handle);
+
+        if layout.size() == 0 {
+            return &mut *ptr::from_raw_parts_mut(dangling(layout).as_ptr().cast(), 0);
+        }
+
         &mut *ptr::from_raw_parts_mut(self.data.as_mut_ptr().cast(), layout.size())
     }
 
@@ -57,6 +209,8 @@ unsafe impl<DataStore> Storage for InlineStorage<DataStore> {
         _old_layout: Layout,
         new_layout: Layout,
     ) -> Result<Self::Handle, AllocError> {
+        #[cfg(feature = "checked")]
+        self.check(handle);
         if self.fits(new_layout) {
             Ok(handle)
         } else {
@@ -70,7 +224,11 @@ unsafe impl<DataStore> Storage for InlineStorage<DataStore> {
         _old_layout: Layout,
         new_layout: Layout,
     ) -> Result<Self::Handle, AllocError> {
+        #[cfg(feature = "checked")]
+        self.check(handle);
         debug_assert!(self.fits(new_layout));
         Ok(handle)
     }
 }
+
+unsafe impl<DataStore> InfallibleEmptyStorage for InlineStorage<DataStore> {}
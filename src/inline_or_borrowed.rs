@@ -0,0 +1,162 @@
+use {
+    crate::{
+        BorrowedStorage, EitherHandle, InfallibleEmptyStorage, InlineStorage, Memory, SingleHandle,
+        Storage,
+    },
+    core::{
+        alloc::{AllocError, Layout},
+        mem::MaybeUninit,
+        ptr::copy_nonoverlapping,
+    },
+};
+
+/// A single storage which stores memory inline if it fits, otherwise falling
+/// back to borrowed scratch memory.
+///
+/// This is [`SmallStorage`](crate::SmallStorage) for when there is no
+/// allocator: memory which doesn't fit the inline `DataStore` goes in memory
+/// lent by the caller, laid out like `ScratchStore`. The handle is
+/// [`Left`](EitherHandle::Left) for inline memory, and
+/// [`Right`](EitherHandle::Right) for borrowed memory.
+pub struct InlineOrBorrowed<'a, DataStore, ScratchStore> {
+    inline: InlineStorage<DataStore>,
+    borrowed: BorrowedStorage<'a, ScratchStore>,
+}
+
+impl<'a, DataStore, ScratchStore> InlineOrBorrowed<'a, DataStore, ScratchStore> {
+    pub fn new(scratch: &'a mut MaybeUninit<ScratchStore>) -> Self {
+        Self {
+            inline: InlineStorage::new(),
+            borrowed: BorrowedStorage::new(scratch),
+        }
+    }
+
+    /// Check if memory of the given layout is stored inline, rather than in
+    /// the borrowed scratch memory.
+    pub fn is_inline(&self, layout: Layout) -> bool {
+        self.inline.fits(layout)
+    }
+
+    /// Move the memory to the other backing, copying `size` bytes.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must be valid for `old_layout`.
+    /// - `size` must be at most the size of both layouts.
+    unsafe fn move_backing(
+        &mut self,
+        handle: EitherHandle<SingleHandle, SingleHandle>,
+        old_layout: Layout,
+        new_layout: Layout,
+        size: usize,
+    ) -> Result<EitherHandle<SingleHandle, SingleHandle>, AllocError> {
+        let (inline, borrowed) = (&mut self.inline, &mut self.borrowed);
+        match handle {
+            EitherHandle::Left(handle) => {
+                let new_handle = borrowed.allocate(new_layout)?;
+                let old_ptr = inline.resolve_mut(handle, old_layout);
+                let new_ptr = borrowed.resolve_mut(new_handle, new_layout);
+                copy_nonoverlapping(old_ptr.as_ptr(), new_ptr.as_mut_ptr(), size);
+                inline.deallocate(handle, old_layout);
+                Ok(EitherHandle::Right(new_handle))
+            },
+            EitherHandle::Right(handle) => {
+                let new_handle = inline.allocate(new_layout)?;
+                let old_ptr = borrowed.resolve_mut(handle, old_layout);
+                let new_ptr = inline.resolve_mut(new_handle, new_layout);
+                copy_nonoverlapping(old_ptr.as_ptr(), new_ptr.as_mut_ptr(), size);
+                borrowed.deallocate(handle, old_layout);
+                Ok(EitherHandle::Left(new_handle))
+            },
+        }
+    }
+}
+
+unsafe impl<DataStore, ScratchStore> Storage for InlineOrBorrowed<'_, DataStore, ScratchStore> {
+    type Handle = EitherHandle<SingleHandle, SingleHandle>;
+
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = false;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if self.inline.fits(layout) {
+            self.inline.allocate(layout).map(EitherHandle::Left)
+        } else {
+            self.borrowed.allocate(layout).map(EitherHandle::Right)
+        }
+    }
+
+    fn current_handle(&self) -> Option<Self::Handle> {
+        match self.inline.current_handle() {
+            Some(handle) => Some(EitherHandle::Left(handle)),
+            None => self.borrowed.current_handle().map(EitherHandle::Right),
+        }
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        match handle {
+            EitherHandle::Left(handle) => self.inline.deallocate(handle, layout),
+            EitherHandle::Right(handle) => self.borrowed.deallocate(handle, layout),
+        }
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        match handle {
+            EitherHandle::Left(handle) => self.inline.resolve(handle, layout),
+            EitherHandle::Right(handle) => self.borrowed.resolve(handle, layout),
+        }
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        match handle {
+            EitherHandle::Left(handle) => self.inline.resolve_mut(handle, layout),
+            EitherHandle::Right(handle) => self.borrowed.resolve_mut(handle, layout),
+        }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        match handle {
+            EitherHandle::Left(handle) if self.inline.fits(new_layout) => self
+                .inline
+                .grow(handle, old_layout, new_layout)
+                .map(EitherHandle::Left),
+            EitherHandle::Right(handle) if self.borrowed.fits(new_layout) => self
+                .borrowed
+                .grow(handle, old_layout, new_layout)
+                .map(EitherHandle::Right),
+            // Spill into the borrowed memory, or move back inline if the new
+            // layout is less aligned (as grow may change the alignment).
+            _ => self.move_backing(handle, old_layout, new_layout, old_layout.size()),
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        match handle {
+            EitherHandle::Left(handle) if self.inline.fits(new_layout) => self
+                .inline
+                .shrink(handle, old_layout, new_layout)
+                .map(EitherHandle::Left),
+            EitherHandle::Right(handle) if self.borrowed.fits(new_layout) => self
+                .borrowed
+                .shrink(handle, old_layout, new_layout)
+                .map(EitherHandle::Right),
+            // The new layout may only fit the other backing if it is more
+            // aligned (as shrink may change the alignment).
+            _ => self.move_backing(handle, old_layout, new_layout, new_layout.size()),
+        }
+    }
+}
+
+unsafe impl<DataStore, ScratchStore> InfallibleEmptyStorage
+    for InlineOrBorrowed<'_, DataStore, ScratchStore>
+{
+}
@@ -5,18 +5,79 @@
 //! - [`Storage`]: a storage that manages a single memory allocation
 //! - [`MultipleStorage`]: a storage that can manage multiple handles
 //! - [`SharedMutabilityStorage`] and [`PinningStorage`] for advanced use
+//! - [`DynStorageTrait`]: an object-safe subset of `Storage`, implemented by
+//!   [`Erased`] storages
+//! - [`CStorage`]: a storage behind a C ABI vtable, e.g. provided by a plugin host
+//! - [`InfallibleEmptyStorage`]: a storage which can always allocate empties
+//! - [`ResettableStorage`]: a storage which can deallocate everything at once
+//! - [`CloneStorage`]: a storage whose clones share memory handles
+//! - [`ReadStorage`]: the read-only subset of `Storage`, e.g. for flash memory
 //!
 //! Providing a safe wrapper around `Storage` use (up to uninit memory):
 //!
 //! - [`RawBox`]: a raw (uninit payload) version of std `Box`
 //! - [`RawVec`]: a raw (uninit payload) version of std `Vec`
+//! - [`RawRef`]: a read-only `RawBox` over a [`ReadStorage`]
+//! - [`Box`] and [`Vec`]: tiny initialized subsets of std `Box` and `Vec`
+//! - [`ArrayVec`]: a `Vec` of fixed capacity `N`, stored inline
+//! - [`FlexBox`]: a `Box` stored inline if the value fits, otherwise in
+//!   another storage
+//! - [`VecDeque`]: a ring buffer over a `RawVec`, growing to powers of two
+//! - [`Rc`] and [`Weak`]: a tiny subset of std's reference counting
+//! - [`Arc`]: the thread-safe analog of `Rc`
+//! - [`LinkedList`]: a tiny subset of std's `LinkedList`, singly linked
+//! - [`TreeMap`]: a binary search tree, storing nodes of a type users never name
+//! - [`HashMap`]: an open addressing hash map in a single allocation
+//! - [`inline_box`] and `alloc_box` (with the `alloc` feature): shorthands
+//!   for boxing a value inline or in the global allocator
 //!
 //! Useful implementations of [`Storage`]:
 //!
 //! - [`InlineStorage`]: single storage located in the storage's bytes
+//! - [`ByteInlineStorage`]: inline storage of an exact number of bytes
+//! - [`InlineArrayStorage`]: inline storage of an array of `N` `T`s
+//! - [`InlineLayoutStorage`]: inline storage of a const size and alignment
 //! - [`AllocStorage`]: full-featured storage via allocation
 //! - [`SmallStorage`]: inline storage with a fallback to allocation
 //! - [`BorrowedStorage`]: single storage located in someone else's memory
+//! - [`RoBorrowedStorage`]: read storage of someone else's read-only memory
+//! - [`InlineOrBorrowed`]: inline storage with a fallback to borrowed memory
+//! - [`BumpStorage`]: multiple storage bump allocating inline
+//! - [`BorrowedArena`]: multiple storage bump allocating in borrowed memory
+//!   (and [`with_stack_storage`] to lend one on the stack to a closure)
+//! - [`ConstPoolStorage`]: multiple storage of inline blocks, `const` to create
+//! - [`SyncStorage`]: a storage shared behind a lock
+//! - [`ScratchStorage`]: a storage reusing the last freed memory handle
+//! - [`CountingStorage`]: a storage counting calls to another storage
+//! - [`HistogramStorage`]: a storage recording the sizes requested from another
+//!   storage
+//! - [`BudgetStorage`]: a storage capping the live bytes of another storage
+//! - [`EitherStorage`]: one of two storages, chosen at construction
+//! - [`AlignedStorage`]: a storage raising the alignment of another storage
+//! - [`CheckedSharedStorage`]: a storage behind a `RefCell`, catching aliasing
+//!   mutable resolves
+//!
+//! To choose the `DataStore` of the inline storages, [`Align1`] through
+//! [`Align4096`] provide a data store of any size with a given alignment, and
+//! [`LayoutDataStore`] picks between them by a const alignment (used by
+//! [`InlineLayoutStorage`]).
+//!
+//! With the `checked` feature, the built-in storages panic when resolving a
+//! memory handle that has been invalidated, even if the memory was allocated
+//! again since. Their handles additionally hold a generation, which the storage
+//! checks: see [`SingleHandle`], [`BumpHandle`], [`PoolHandle`], and
+//! [`AllocHandle`]. [`AllocStorage`] is stateless, so it keeps the generations
+//! of its live allocations in a global registry. (This makes the handles, and
+//! so the collections, bigger than without the feature.)
+//!
+//! In debug builds and with the `checked` feature, [`AllocStorage`],
+//! [`InlineStorage`], and [`BorrowedStorage`] also overwrite memory with `0xDE`
+//! bytes when deallocating it, so that using it after deallocation reads
+//! obviously wrong data.
+//!
+//! In debug builds, the collections report where an allocation failed to the
+//! hook registered with [`set_alloc_error_hook`], before calling the alloc
+//! error handler.
 
 #![no_std]
 #![feature(
@@ -28,10 +89,14 @@
     let_chains,
     maybe_uninit_array_assume_init,
     specialization,
+    unsize,
     ptr_metadata
 )]
 #![allow(
     clippy::len_without_is_empty,
+    // Without the `checked` feature, `SingleHandle` is `()`, but the handles
+    // are still bound and passed around so the code is the same either way.
+    clippy::let_unit_value,
     clippy::missing_safety_doc,
     clippy::mut_from_ref,
     clippy::new_without_default,
@@ -40,24 +105,84 @@
     unused_unsafe
 )]
 
+#[cfg(feature = "alloc")]
+extern crate alloc as liballoc;
+
+mod align;
+mod aligned;
 mod alloc;
+mod arc;
 mod borrowed;
+mod budget;
+mod bump;
+mod checked_shared;
+mod counting;
 mod dynamic;
+mod either;
+mod erased;
+mod ffi;
+mod flex_box;
+mod hash_map;
+mod histogram;
 mod inline;
+mod inline_or_borrowed;
+mod list;
 mod polyfill;
+mod pool;
 mod raw_box;
+mod raw_ref;
 mod raw_vec;
+mod rc;
+mod scratch;
 mod small;
+mod sync;
 mod traits;
+mod tree;
+mod vec_deque;
 
 #[doc(inline)]
 pub use crate::{
+    align::{
+        Align1, Align1024, Align128, Align16, Align2, Align2048, Align256, Align32, Align4,
+        Align4096, Align512, Align64, Align8, Alignment, LayoutDataStore, SupportedAlignment,
+    },
+    aligned::AlignedStorage,
     alloc::{AllocHandle, AllocStorage},
-    borrowed::BorrowedStorage,
+    arc::Arc,
+    borrowed::{with_stack_storage, BorrowedArena, BorrowedStorage, RoBorrowedStorage},
+    budget::BudgetStorage,
+    bump::{BumpHandle, BumpStorage},
+    checked_shared::CheckedSharedStorage,
+    counting::CountingStorage,
     dynamic::DynStorage,
-    inline::InlineStorage,
-    raw_box::{Box, RawBox},
-    raw_vec::RawVec,
+    either::{EitherHandle, EitherStorage},
+    erased::{DynStorageTrait, ErasableHandle, Erased, ErasedHandle},
+    ffi::{CStorage, CStorageVtable, FromStorage},
+    flex_box::FlexBox,
+    hash_map::HashMap,
+    histogram::HistogramStorage,
+    inline::{
+        ByteInlineStorage, InlineArrayStorage, InlineLayoutStorage, InlineStorage, SingleHandle,
+    },
+    inline_or_borrowed::InlineOrBorrowed,
+    list::{LinkedList, LinkedListIter},
+    polyfill::{set_alloc_error_hook, AllocErrorHook},
+    pool::{ConstPoolStorage, PoolHandle},
+    raw_box::{inline_box, Box, RawBox},
+    raw_ref::RawRef,
+    raw_vec::{ArrayVec, Doubling, GrowthPolicy, RawVec, Vec},
+    rc::{Rc, Weak},
+    scratch::ScratchStorage,
     small::SmallStorage,
-    traits::{Memory, MultipleStorage, PinningStorage, SharedMutabilityStorage, Storage},
+    sync::{RawMutex, SyncStorage},
+    traits::{
+        CloneStorage, InfallibleEmptyStorage, Memory, MultipleStorage, PinningStorage, ReadStorage,
+        ResettableStorage, SharedMutabilityStorage, Storage,
+    },
+    tree::TreeMap,
+    vec_deque::VecDeque,
 };
+
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use crate::raw_box::alloc_box;
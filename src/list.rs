@@ -0,0 +1,124 @@
+use {
+    crate::{polyfill::handle_alloc_error, MultipleStorage},
+    core::{alloc::Layout, marker::PhantomData},
+};
+
+/// A node of a [`LinkedList`], stored in the storage.
+struct Node<T, H> {
+    value: T,
+    next: Option<H>,
+}
+
+/// A singly linked list. A tiny subset of std's LinkedList.
+///
+/// Each element is a node allocated in the storage, linked to the next by
+/// handle. As with [`TreeMap`](crate::TreeMap), the node type is internal.
+pub struct LinkedList<T, S: MultipleStorage> {
+    head: Option<S::Handle>,
+    len: usize,
+    storage: S,
+    marker: PhantomData<Node<T, S::Handle>>,
+}
+
+const fn node_layout<T, H>() -> Layout {
+    Layout::new::<Node<T, H>>()
+}
+
+impl<T, S: MultipleStorage> LinkedList<T, S> {
+    pub fn new_in(storage: S) -> Self {
+        Self {
+            head: None,
+            len: 0,
+            storage,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[track_caller]
+    pub fn push_front(&mut self, value: T) {
+        let layout = node_layout::<T, S::Handle>();
+        let handle = match self.storage.allocate(layout) {
+            Ok(handle) => handle,
+            Err(_) => handle_alloc_error(layout),
+        };
+        unsafe {
+            let memory = self.storage.resolve_mut(handle, layout);
+            memory
+                .as_mut_ptr()
+                .cast::<Node<T, S::Handle>>()
+                .write(Node {
+                    value,
+                    next: self.head,
+                });
+        }
+        self.head = Some(handle);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let layout = node_layout::<T, S::Handle>();
+        let handle = self.head?;
+        let node = unsafe {
+            let memory = self.storage.resolve_mut(handle, layout);
+            let node = memory.as_mut_ptr().cast::<Node<T, S::Handle>>().read();
+            self.storage.deallocate(handle, layout);
+            node
+        };
+        self.head = node.next;
+        self.len -= 1;
+        Some(node.value)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn iter(&self) -> LinkedListIter<'_, T, S> {
+        LinkedListIter {
+            next: self.head,
+            list: self,
+        }
+    }
+}
+
+impl<T, S: MultipleStorage> Drop for LinkedList<T, S> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+/// An iterator over the elements of a [`LinkedList`].
+pub struct LinkedListIter<'a, T, S: MultipleStorage> {
+    next: Option<S::Handle>,
+    list: &'a LinkedList<T, S>,
+}
+
+impl<'a, T, S: MultipleStorage> Iterator for LinkedListIter<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let handle = self.next?;
+        let node = unsafe {
+            let memory = self
+                .list
+                .storage
+                .resolve(handle, node_layout::<T, S::Handle>());
+            &*(memory.as_ptr() as *const Node<T, S::Handle>)
+        };
+        self.next = node.next;
+        Some(&node.value)
+    }
+}
+
+impl<'a, T, S: MultipleStorage> IntoIterator for &'a LinkedList<T, S> {
+    type Item = &'a T;
+    type IntoIter = LinkedListIter<'a, T, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -1,27 +1,155 @@
-use core::{
-    alloc::Layout,
-    ptr::{self, Pointee},
+use {
+    crate::Memory,
+    core::{
+        alloc::Layout,
+        mem::MaybeUninit,
+        panic::Location,
+        ptr::{self, DynMetadata, NonNull, Pointee},
+        sync::atomic::{AtomicPtr, Ordering},
+    },
 };
 
-// For now, we're just assuming that metadata passed in describes a valid
-// layout. Ideally, we shouldn't have to, but there's no current way to go from
-// metadata to size without breaking the "must be <= isize::MAX" rule.
+/// Compute the layout of a `T` with the given pointer metadata.
+///
+/// Sized types, slices, and `str` compute their layout with checked math, and
+/// return `None` if the layout would overflow. Only other unsized types (i.e.
+/// trait objects and composites) go through `Layout::for_value_raw`.
+///
+/// # Safety
+///
+/// If `T` is not sized, a slice, or `str`, the metadata must describe a valid
+/// layout. For now, we're just assuming that it does. Ideally, we shouldn't
+/// have to, but there's no current way to go from metadata to size without
+/// breaking the "must be <= isize::MAX" rule.
 pub(crate) unsafe fn layout_for_metadata<T: ?Sized>(
     meta: <T as Pointee>::Metadata,
 ) -> Option<Layout> {
-    let ptr: *const T = ptr::from_raw_parts(ptr::null(), meta);
+    <T as MetadataLayout>::layout_for_metadata(meta)
+}
+
+/// Compute the layout of a `T` with the given pointer metadata, if it can be
+/// checked to be valid.
+///
+/// Sized types, slices, and `str` compute their layout with checked math, and
+/// trait objects trust their vtable. Other unsized types (i.e. composites and
+/// extern types) can't be checked, so always return `None`.
+pub(crate) fn checked_layout_for_metadata<T: ?Sized>(
+    meta: <T as Pointee>::Metadata,
+) -> Option<Layout> {
+    <T as MetadataLayout>::checked_layout_for_metadata(meta)
+}
+
+trait MetadataLayout: Pointee {
+    unsafe fn layout_for_metadata(meta: Self::Metadata) -> Option<Layout>;
+
+    fn checked_layout_for_metadata(meta: Self::Metadata) -> Option<Layout>;
+}
+
+impl<T: ?Sized> MetadataLayout for T {
+    default unsafe fn layout_for_metadata(meta: Self::Metadata) -> Option<Layout> {
+        let ptr: *const T = ptr::from_raw_parts(ptr::null(), meta);
+
+        // We *need* a way to check that this is sound
+        unsafe {
+            // SAFETY: it's *not*, but there's no way to pre-check
+            Some(Layout::for_value_raw(ptr))
+        }
+    }
+
+    default fn checked_layout_for_metadata(meta: Self::Metadata) -> Option<Layout> {
+        // Only trait objects can be checked here, through their vtable.
+        <Self::Metadata as VtableLayout>::vtable_layout(meta)
+    }
+}
+
+/// Trait objects can't be picked out by specializing `MetadataLayout` on `T`,
+/// as that would overlap with the sized impl, so instead specialize on `T`'s
+/// metadata type.
+trait VtableLayout {
+    fn vtable_layout(self) -> Option<Layout>;
+}
+
+impl<M> VtableLayout for M {
+    default fn vtable_layout(self) -> Option<Layout> {
+        None
+    }
+}
+
+impl<T: ?Sized> VtableLayout for DynMetadata<T> {
+    fn vtable_layout(self) -> Option<Layout> {
+        Layout::from_size_align(self.size_of(), self.align_of()).ok()
+    }
+}
+
+impl<T> MetadataLayout for T {
+    unsafe fn layout_for_metadata((): ()) -> Option<Layout> {
+        Self::checked_layout_for_metadata(())
+    }
 
-    // We *need* a way to check that this is sound
-    unsafe {
-        // SAFETY: it's *not*, but there's no way to pre-check
-        Some(Layout::for_value_raw(ptr))
+    fn checked_layout_for_metadata((): ()) -> Option<Layout> {
+        Some(Layout::new::<T>())
     }
 }
 
+impl<T> MetadataLayout for [T] {
+    unsafe fn layout_for_metadata(len: usize) -> Option<Layout> {
+        Self::checked_layout_for_metadata(len)
+    }
+
+    fn checked_layout_for_metadata(len: usize) -> Option<Layout> {
+        Layout::array::<T>(len).ok()
+    }
+}
+
+impl MetadataLayout for str {
+    unsafe fn layout_for_metadata(len: usize) -> Option<Layout> {
+        Self::checked_layout_for_metadata(len)
+    }
+
+    fn checked_layout_for_metadata(len: usize) -> Option<Layout> {
+        Layout::array::<u8>(len).ok()
+    }
+}
+
+/// A dangling pointer aligned for `layout`, for zero-sized memory handles.
+pub(crate) fn dangling(layout: Layout) -> NonNull<u8> {
+    unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+}
+
 pub(crate) fn layout_fits_in(inner: Layout, outer: Layout) -> bool {
     inner.align() <= outer.align() && inner.size() <= outer.size()
 }
 
+/// In debug builds, check that `resolve_many_mut` isn't given the same handle
+/// twice, which would alias the returned references.
+///
+/// Zero-sized handles are ignored, as they don't alias anything.
+pub(crate) fn debug_assert_distinct<H: Copy + Ord, const N: usize>(handles: &[(H, Layout); N]) {
+    if cfg!(debug_assertions) {
+        let mut sorted = handles.map(|(handle, layout)| (layout.size() != 0).then_some(handle));
+        sorted.sort_unstable();
+        assert!(
+            sorted
+                .windows(2)
+                .all(|pair| pair[0].is_none() || pair[0] != pair[1]),
+            "the same handle was passed twice to MultipleStorage::resolve_many_mut",
+        );
+    }
+}
+
+/// The byte which deallocated memory is overwritten with.
+pub(crate) const POISON: u8 = 0xDE;
+
+/// In debug builds and with the `checked` feature, overwrite memory which is
+/// being deallocated with [`POISON`], so that reading it through a stale
+/// pointer gives obviously wrong data rather than plausible values.
+#[inline]
+pub(crate) fn poison(memory: &mut Memory) {
+    if cfg!(any(debug_assertions, feature = "checked")) {
+        memory.fill(MaybeUninit::new(POISON));
+    }
+}
+
 // CRIMES 😈😈😈😈😈😈
 extern "Rust" {
     // This is the magic symbol to call the global alloc error handler.  rustc generates
@@ -30,7 +158,37 @@ extern "Rust" {
     fn __rust_alloc_error_handler(size: usize, align: usize) -> !;
 }
 
+/// A hook reporting a failed allocation: the layout which failed to allocate,
+/// and the location of the call which allocated it.
+pub type AllocErrorHook = fn(Layout, &'static Location<'static>);
+
+/// The registered [`AllocErrorHook`], or null.
+static ALLOC_ERROR_HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Register a hook to report where allocations fail.
+///
+/// In debug builds, when a collection fails to allocate (e.g. in
+/// [`Box::new_in`](crate::Box::new_in) or [`Vec::push`](crate::Vec::push)),
+/// it calls the hook with the location of that call before calling the alloc
+/// error handler, which aborts by default. Release builds don't call the hook.
+pub fn set_alloc_error_hook(hook: AllocErrorHook) {
+    ALLOC_ERROR_HOOK.store(hook as *mut (), Ordering::Release);
+}
+
+/// Call the alloc error handler on allocation failure.
+///
+/// In debug builds, this first reports the location of the failed allocation
+/// to the [`AllocErrorHook`]. Callers should be `#[track_caller]` as well.
+#[track_caller]
 pub(crate) fn handle_alloc_error(layout: Layout) -> ! {
+    #[cfg(debug_assertions)]
+    {
+        let hook = ALLOC_ERROR_HOOK.load(Ordering::Acquire);
+        if !hook.is_null() {
+            let hook = unsafe { core::mem::transmute::<*mut (), AllocErrorHook>(hook) };
+            hook(layout, Location::caller());
+        }
+    }
     unsafe { __rust_alloc_error_handler(layout.size(), layout.align()) }
 }
 
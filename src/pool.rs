@@ -0,0 +1,219 @@
+use {
+    crate::{
+        polyfill::{dangling, debug_assert_distinct, layout_fits_in},
+        InfallibleEmptyStorage, Memory, MultipleStorage, SharedMutabilityStorage, Storage,
+    },
+    core::{
+        alloc::{AllocError, Layout},
+        cell::UnsafeCell,
+        mem::MaybeUninit,
+        ptr,
+    },
+};
+
+/// A multiple storage of `N` inline blocks, each holding a `T`.
+///
+/// Handles are block indices. Deallocated blocks go on a free list and are
+/// reused; layouts which don't fit in a `T` fail to allocate, and zero-sized
+/// layouts don't take a block. The free list is built by the `const fn new`,
+/// so a pool can be put in a `static` (e.g. behind a [`SyncStorage`]).
+///
+/// As the storage can move, handles cannot be aligned more than `T`.
+///
+/// [`SyncStorage`]: crate::SyncStorage
+pub struct ConstPoolStorage<T, const N: usize> {
+    blocks: UnsafeCell<MaybeUninit<[T; N]>>,
+    /// The next free block after each free block, or `N` for the last one.
+    next: [usize; N],
+    /// The first free block, or `N` if the pool is full.
+    free: usize,
+    /// The generation of each block, odd while it is allocated.
+    #[cfg(feature = "checked")]
+    generations: [usize; N],
+}
+
+/// The memory handle of [`ConstPoolStorage`].
+///
+/// This is the index of the block. With the `checked` feature, it is instead
+/// the index along with the generation of the block when it was allocated.
+#[cfg(not(feature = "checked"))]
+pub type PoolHandle = usize;
+
+/// The memory handle of [`ConstPoolStorage`].
+///
+/// This is the index of the block along with the generation of the block when
+/// it was allocated, which the storage checks to catch resolving the handle
+/// after it was deallocated, even if the block was allocated again since.
+/// Without the `checked` feature, this is just the index.
+#[cfg(feature = "checked")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PoolHandle {
+    pub(crate) block: usize,
+    pub(crate) generation: usize,
+}
+
+impl<T, const N: usize> ConstPoolStorage<T, N> {
+    pub const fn new() -> Self {
+        let mut next = [0; N];
+        let mut i = 0;
+        while i < N {
+            next[i] = i + 1;
+            i += 1;
+        }
+        Self {
+            blocks: UnsafeCell::new(MaybeUninit::uninit()),
+            next,
+            free: 0,
+            #[cfg(feature = "checked")]
+            generations: [0; N],
+        }
+    }
+
+    /// Check if all blocks are allocated.
+    pub fn is_full(&self) -> bool {
+        self.free == N
+    }
+
+    fn resolve_ptr(&self, handle: PoolHandle, layout: Layout) -> *mut Memory {
+        if layout.size() == 0 {
+            return ptr::from_raw_parts_mut(dangling(layout).as_ptr().cast(), 0);
+        }
+
+        let block = self.block(handle);
+        let block = self.blocks.get().cast::<T>().wrapping_add(block);
+        ptr::from_raw_parts_mut(block.cast(), layout.size())
+    }
+
+    /// The handle of the zero-sized memory, which isn't a block.
+    fn empty_handle() -> PoolHandle {
+        #[cfg(feature = "checked")]
+        return PoolHandle {
+            block: N,
+            generation: 0,
+        };
+        #[cfg(not(feature = "checked"))]
+        N
+    }
+
+    /// Take the block off the free list, and get its handle.
+    fn take_block(&mut self, block: usize) -> PoolHandle {
+        self.free = self.next[block];
+        #[cfg(feature = "checked")]
+        {
+            self.generations[block] = self.generations[block].wrapping_add(1);
+            PoolHandle {
+                block,
+                generation: self.generations[block],
+            }
+        }
+        #[cfg(not(feature = "checked"))]
+        block
+    }
+
+    /// Get the block of a handle to memory which isn't zero-sized, catching a
+    /// handle to a block which was deallocated since.
+    fn block(&self, handle: PoolHandle) -> usize {
+        #[cfg(feature = "checked")]
+        {
+            assert!(
+                handle.block < N && handle.generation == self.generations[handle.block],
+                "ConstPoolStorage resolved an invalidated handle"
+            );
+            handle.block
+        }
+        #[cfg(not(feature = "checked"))]
+        handle
+    }
+}
+
+unsafe impl<T, const N: usize> Storage for ConstPoolStorage<T, N> {
+    type Handle = PoolHandle;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if layout.size() == 0 {
+            // Not a block, so never put on the free list.
+            return Ok(Self::empty_handle());
+        }
+
+        if !layout_fits_in(layout, Layout::new::<T>()) || self.is_full() {
+            return Err(AllocError);
+        }
+
+        Ok(self.take_block(self.free))
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        if layout.size() != 0 {
+            let block = self.block(handle);
+            #[cfg(feature = "checked")]
+            {
+                self.generations[block] = self.generations[block].wrapping_add(1);
+            }
+            self.next[block] = self.free;
+            self.free = block;
+        }
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        &*self.resolve_ptr(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        &mut *self.resolve_ptr(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        if new_layout == old_layout {
+            Ok(handle)
+        } else if old_layout.size() == 0 {
+            // There are no contents to keep, and no block to free.
+            self.allocate(new_layout)
+        } else if layout_fits_in(new_layout, Layout::new::<T>()) {
+            // Every block is big enough for a `T`, so stay in place.
+            Ok(handle)
+        } else {
+            Err(AllocError)
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        if new_layout == old_layout {
+            Ok(handle)
+        } else if new_layout.size() == 0 {
+            self.deallocate(handle, old_layout);
+            Ok(Self::empty_handle())
+        } else if layout_fits_in(new_layout, Layout::new::<T>()) {
+            Ok(handle)
+        } else {
+            Err(AllocError)
+        }
+    }
+}
+
+unsafe impl<T, const N: usize> MultipleStorage for ConstPoolStorage<T, N> {
+    unsafe fn resolve_many_mut<const M: usize>(
+        &mut self,
+        handles: [(Self::Handle, Layout); M],
+    ) -> [&mut Memory; M] {
+        debug_assert_distinct(&handles);
+        handles.map(|(handle, layout)| &mut *self.resolve_ptr(handle, layout))
+    }
+}
+
+unsafe impl<T, const N: usize> SharedMutabilityStorage for ConstPoolStorage<T, N> {
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        &mut *self.resolve_ptr(handle, layout)
+    }
+}
+
+unsafe impl<T, const N: usize> InfallibleEmptyStorage for ConstPoolStorage<T, N> {}
@@ -1,27 +1,93 @@
 use {
     crate::{
-        polyfill::{handle_alloc_error, layout_for_metadata},
-        Storage,
+        polyfill::{checked_layout_for_metadata, handle_alloc_error, layout_for_metadata},
+        InlineStorage, Memory, PinningStorage, Storage,
     },
     core::{
-        alloc::Layout,
-        mem::{ManuallyDrop, MaybeUninit},
+        alloc::{AllocError, Layout},
+        any::Any,
+        borrow::{Borrow, BorrowMut},
+        cmp, fmt,
+        marker::{PhantomData, Unsize},
+        mem::{self, ManuallyDrop, MaybeUninit},
         ops::{Deref, DerefMut},
+        panic::{RefUnwindSafe, UnwindSafe},
+        pin::Pin,
         ptr::{self, Pointee},
     },
     unsize::CoerciblePtr,
 };
 
+#[cfg(feature = "alloc")]
+use {crate::AllocStorage, liballoc::alloc::Global};
+
 /// A raw box around some storage. Bundles the storage and its handle.
+///
+/// The storage may store the object inline (e.g. [`InlineStorage`]), so moving
+/// the box may move the object. The accessors resolve the handle every time,
+/// and pointers they return are invalidated when the box is moved.
+///
+/// This is `repr(C)`: the handle, then the metadata, then the storage (then,
+/// only for a box of [`Memory`], its alignment). E.g. a
+/// `RawBox<[u8], InlineStorage<D>>` is the slice length followed by the
+/// inline bytes. This layout doesn't hold with the `checked` feature, with
+/// which the handles carry generations and the single storages such as
+/// [`InlineStorage`] aren't transparent.
+///
+/// [`InlineStorage`]: crate::InlineStorage
+#[repr(C)]
 pub struct RawBox<T: ?Sized, S: Storage> {
     handle: S::Handle,
     metadata: <T as Pointee>::Metadata,
     storage: S,
+    // Zero-sized, except for byte slices; see `HeapAlign`.
+    align: MaybeUninit<<T as HeapAlign>::Align>,
+    // Owns a T for dropck. The raw box never drops the object, but Box does,
+    // and the object may still use any borrows it holds when dropped.
+    marker: PhantomData<T>,
+}
+
+/// The alignment a [`RawBox`] keeps besides its metadata.
+///
+/// Only byte slices ([`Memory`]) keep one, as [`RawBox::new_bytes`] allocates
+/// them with any alignment; for every other type, it follows from the metadata.
+/// It is zeroed for none, or left uninitialized where the type is known not to
+/// be a byte slice.
+pub(crate) trait HeapAlign {
+    type Align: Copy + Send + Sync + Unpin + UnwindSafe + RefUnwindSafe;
+
+    fn heap_layout(layout: Layout, align: MaybeUninit<Self::Align>) -> Layout;
+}
+
+impl<T: ?Sized> HeapAlign for T {
+    default type Align = ();
+
+    #[inline]
+    default fn heap_layout(layout: Layout, _: MaybeUninit<Self::Align>) -> Layout {
+        layout
+    }
+}
+
+impl HeapAlign for Memory {
+    type Align = usize;
+
+    #[inline]
+    fn heap_layout(layout: Layout, align: MaybeUninit<usize>) -> Layout {
+        // SAFETY: a byte box's alignment is zeroed or that of a valid layout
+        // of its size.
+        unsafe {
+            layout
+                .align_to(cmp::max(align.assume_init(), 1))
+                .unwrap_unchecked()
+        }
+    }
 }
 
 impl<T: ?Sized, S: Storage> RawBox<T, S> {
+    #[inline]
     fn heap_layout(&self) -> Layout {
-        unsafe { layout_for_metadata::<T>(self.metadata).unwrap_unchecked() }
+        let layout = unsafe { layout_for_metadata::<T>(self.metadata).unwrap_unchecked() };
+        T::heap_layout(layout, self.align)
     }
 
     /// Create a new box for the object described by the given metadata.
@@ -33,6 +99,8 @@ impl<T: ?Sized, S: Storage> RawBox<T, S> {
     /// # Safety
     ///
     /// - The metadata must describe a layout valid for a rust object.
+    ///   - This is checked for sized types, slices, and `str`; an overflowing
+    ///     slice length fails to allocate instead.
     ///   - This requirement exists due to the safety requirements of
     ///     `size_of_val_raw` and `align_of_val_raw`. I think we definitely want
     ///     a way to go compute layout from type and metadata safely, with a
@@ -46,16 +114,41 @@ impl<T: ?Sized, S: Storage> RawBox<T, S> {
     ///   - [rust-lang/rust#95832](https://github.com/rust-lang/rust/pull/95832)
     ///     is an attempt to quantize how expensive it would be to make slice
     ///     size computation *always* use saturating math.
+    #[track_caller]
     pub unsafe fn new(metadata: <T as Pointee>::Metadata, mut storage: S) -> Result<Self, S> {
         if let Some(layout) = layout_for_metadata::<T>(metadata)
         && let Ok(handle) = storage.allocate(layout)
         {
-            Ok(RawBox { handle, metadata, storage })
+            Ok(RawBox {
+                handle,
+                metadata,
+                storage,
+                align: MaybeUninit::zeroed(),
+                marker: PhantomData,
+            })
         } else {
             Err(storage)
         }
     }
 
+    /// Create a new box for the object described by the given metadata,
+    /// checking that the metadata describes a valid layout.
+    ///
+    /// The object is not initialized.
+    ///
+    /// Unlike [`new`](Self::new), this is safe. Sized types, slices, and `str`
+    /// compute their layout with checked math, and trait objects trust their
+    /// vtable. Metadata which overflows, or for which the layout can't be
+    /// checked (i.e. composites and extern types), fails like allocation.
+    #[track_caller]
+    pub fn new_checked(metadata: <T as Pointee>::Metadata, storage: S) -> Result<Self, S> {
+        match checked_layout_for_metadata::<T>(metadata) {
+            // SAFETY: the metadata describes a valid layout
+            Some(_) => unsafe { Self::new(metadata, storage) },
+            None => Err(storage),
+        }
+    }
+
     /// Get a reference to the boxed object.
     pub fn as_ref(&self) -> &MaybeUninit<T>
     where
@@ -68,14 +161,13 @@ impl<T: ?Sized, S: Storage> RawBox<T, S> {
     ///
     /// The pointer is invalidated when the box is moved or used by mutable
     /// reference.
+    #[inline]
     pub fn as_ptr(&self) -> *const T {
         unsafe {
-            let (addr, _) = self
-                .storage
-                .resolve(self.handle, self.heap_layout())
-                .as_ptr()
-                .to_raw_parts();
-            ptr::from_raw_parts(addr, self.metadata())
+            // Cast the resolved pointer, rather than rebuilding it from its
+            // address, so that it keeps the provenance given by the storage.
+            let memory = self.storage.resolve(self.handle, self.heap_layout());
+            ptr::from_raw_parts(memory.as_ptr().cast(), self.metadata())
         }
     }
 
@@ -90,18 +182,28 @@ impl<T: ?Sized, S: Storage> RawBox<T, S> {
     /// Get a pointer valid for reads and writes to the boxed object.
     ///
     /// The pointer is invalidated when the box is moved or used by reference.
+    #[inline]
     pub fn as_mut_ptr(&mut self) -> *mut T {
         unsafe {
-            let (addr, _) = self
-                .storage
-                .resolve_mut(self.handle, self.heap_layout())
-                .as_mut_ptr()
-                .to_raw_parts();
-            ptr::from_raw_parts_mut(addr, self.metadata())
+            let metadata = self.metadata();
+            let memory = self.storage.resolve_mut(self.handle, self.heap_layout());
+            ptr::from_raw_parts_mut(memory.as_mut_ptr().cast(), metadata)
         }
     }
 
+    /// Drop the boxed object in place, without deallocating the box.
+    ///
+    /// # Safety
+    ///
+    /// - The boxed object must be initialized.
+    /// - The boxed object must not be used afterwards, as it is no longer
+    ///   initialized.
+    pub unsafe fn drop_contents(&mut self) {
+        ptr::drop_in_place(self.as_mut_ptr())
+    }
+
     /// Get the metadata of the boxed object.
+    #[inline]
     pub fn metadata(&self) -> <T as Pointee>::Metadata {
         self.metadata
     }
@@ -124,10 +226,105 @@ impl<T: ?Sized, S: Storage> RawBox<T, S> {
             handle,
             metadata,
             storage,
+            align: MaybeUninit::zeroed(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S: Storage> RawBox<Memory, S> {
+    /// Create a new box owning `layout.size()` uninitialized bytes, e.g. for a
+    /// type-erased buffer.
+    ///
+    /// The memory is allocated with exactly `layout`, and the box keeps its
+    /// alignment when it is resolved, grown, or deallocated. The raw parts of
+    /// the box don't include the alignment, so only rebuild the box from them
+    /// if it is 1.
+    pub fn new_bytes(layout: Layout, mut storage: S) -> Result<Self, S> {
+        match storage.allocate(layout) {
+            Ok(handle) => Ok(RawBox {
+                handle,
+                metadata: layout.size(),
+                storage,
+                align: MaybeUninit::new(layout.align()),
+                marker: PhantomData,
+            }),
+            Err(AllocError) => Err(storage),
         }
     }
 }
 
+impl<T, S: Storage> RawBox<[T], S> {
+    /// Grow the length of the boxed slice to `new_len`, keeping the existing
+    /// elements. Does not change the length if the slice is already long
+    /// enough.
+    ///
+    /// The new elements are uninitialized. Fails without touching the storage
+    /// if the new length would overflow the maximum size of an allocation.
+    pub fn grow_to(&mut self, new_len: usize) -> Result<(), AllocError> {
+        if new_len <= self.metadata {
+            return Ok(());
+        }
+
+        let new_layout = Layout::array::<T>(new_len).map_err(|_| AllocError)?;
+        let new_layout = <[T]>::heap_layout(new_layout, self.align);
+        self.handle = unsafe {
+            self.storage
+                .grow(self.handle, self.heap_layout(), new_layout)
+        }?;
+        self.metadata = new_len;
+        Ok(())
+    }
+}
+
+impl<T, S: Storage> RawBox<MaybeUninit<T>, S> {
+    /// Create a new box for an uninitialized `T`.
+    pub fn new_uninit(storage: S) -> Result<Self, S> {
+        unsafe { Self::new((), storage) }
+    }
+
+    /// Initialize the boxed object, overwriting any previous value without
+    /// dropping it.
+    pub fn write(&mut self, value: T) -> &mut T {
+        unsafe { &mut *self.as_mut_ptr() }.write(value)
+    }
+
+    /// Convert to a raw box of `T`, reusing the same memory handle.
+    ///
+    /// # Safety
+    ///
+    /// - The boxed object must be initialized.
+    pub unsafe fn assume_init(self) -> RawBox<T, S> {
+        let (handle, (), storage) = self.into_raw_parts();
+        RawBox::from_raw_parts(handle, (), storage)
+    }
+}
+
+impl<T, S: Storage> RawBox<T, S> {
+    /// Convert to a raw box of `MaybeUninit<T>`, reusing the same memory
+    /// handle.
+    pub fn into_uninit(self) -> RawBox<MaybeUninit<T>, S> {
+        let (handle, (), storage) = self.into_raw_parts();
+        unsafe { RawBox::from_raw_parts(handle, (), storage) }
+    }
+}
+
+/// Shows the handle, metadata, and layout, but not the (maybe uninitialized)
+/// boxed object.
+impl<T: ?Sized, S: Storage> fmt::Debug for RawBox<T, S>
+where
+    S::Handle: fmt::Debug,
+    <T as Pointee>::Metadata: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawBox")
+            .field("handle", &self.handle)
+            .field("metadata", &self.metadata)
+            .field("layout", &self.heap_layout())
+            .finish_non_exhaustive()
+    }
+}
+
 unsafe impl<#[may_dangle] T: ?Sized, S: Storage> Drop for RawBox<T, S> {
     fn drop(&mut self) {
         unsafe { self.storage.deallocate(self.handle, self.heap_layout()) }
@@ -135,21 +332,154 @@ unsafe impl<#[may_dangle] T: ?Sized, S: Storage> Drop for RawBox<T, S> {
 }
 
 /// A pointer type for heap allocation. A tiny subset of std's Box.
+///
+/// As with std's Box, the boxed object may hold borrows which don't outlive
+/// the box, as long as dropping the object doesn't use them:
+///
+/// ```compile_fail,E0597
+/// # use storage_api::{InlineStorage, Box};
+/// struct PrintOnDrop<'a>(&'a str);
+///
+/// impl Drop for PrintOnDrop<'_> {
+///     fn drop(&mut self) {
+///         println!("{}", self.0);
+///     }
+/// }
+///
+/// let boxed;
+/// let value = String::from("dangling");
+/// boxed = Box::new_in(PrintOnDrop(&value), InlineStorage::<&str>::new());
+/// ```
 pub struct Box<T: ?Sized, S: Storage> {
     raw: RawBox<T, S>,
 }
 
 impl<T: ?Sized, S: Storage> Box<T, S> {
+    #[track_caller]
     pub fn new_in(t: T, storage: S) -> Self
     where
         T: Sized,
     {
-        let mut this = Self {
-            raw: unsafe { RawBox::new((), storage) }
-                .unwrap_or_else(|_| handle_alloc_error(Layout::new::<T>())),
+        match Self::try_new_in(t, storage) {
+            Ok(this) => this,
+            Err(_) => handle_alloc_error(Layout::new::<T>()),
+        }
+    }
+
+    /// Box a value, giving back the value and the storage if allocation
+    /// fails.
+    pub fn try_new_in(t: T, storage: S) -> Result<Self, (T, S)>
+    where
+        T: Sized,
+    {
+        let mut this = match unsafe { RawBox::new((), storage) } {
+            Ok(raw) => Self { raw },
+            Err(storage) => return Err((t, storage)),
+        };
+        if mem::size_of::<T>() == 0 {
+            // There are no bytes to write, so don't bother resolving the
+            // handle; the value is logically moved into the box.
+            mem::forget(t);
+        } else {
+            unsafe { this.raw.as_mut_ptr().write(t) };
+        }
+        Ok(this)
+    }
+
+    /// Box a value in pinned memory, giving back the value and the storage if
+    /// allocation fails.
+    pub fn try_pin_in(t: T, storage: S) -> Result<Pin<Self>, (T, S)>
+    where
+        T: Sized,
+        S: PinningStorage,
+    {
+        // SAFETY: the storage doesn't move the memory until it's deallocated
+        Self::try_new_in(t, storage).map(|this| unsafe { Pin::new_unchecked(this) })
+    }
+
+    /// Box a value, coercing it to an unsized `T` (e.g. a trait object).
+    ///
+    /// This is [`new_in`](Self::new_in) followed by an unsizing coercion, in
+    /// one call and without going through the `unsize` crate.
+    #[track_caller]
+    pub fn new_unsized<U: Unsize<T>>(value: U, storage: S) -> Self {
+        let (handle, (), storage) = Box::into_raw_parts(Box::new_in(value, storage));
+        // The coercion only attaches metadata, so take it from a null pointer.
+        let metadata = ptr::metadata(ptr::null::<U>() as *const T);
+        unsafe { Self::from_raw_parts(handle, metadata, storage) }
+    }
+
+    /// Replace the boxed value, returning the old value.
+    ///
+    /// This is [`mem::replace`] through the box, and does not reallocate.
+    pub fn replace(&mut self, value: T) -> T
+    where
+        T: Sized,
+    {
+        mem::replace(&mut **self, value)
+    }
+
+    /// Transform the boxed value, possibly changing its type.
+    ///
+    /// If `U` has the same layout as `T`, the memory handle is reused and the
+    /// new value is written in place. Otherwise, the old memory handle is
+    /// deallocated and a new one allocated for `U`.
+    #[track_caller]
+    pub fn map<U>(this: Self, f: impl FnOnce(T) -> U) -> Box<U, S>
+    where
+        T: Sized,
+    {
+        // Keep the raw box alive over `f`, so that the memory handle is
+        // deallocated if `f` panics.
+        let mut raw = Self::forget_contents(this).into_uninit();
+        let t = unsafe { raw.as_mut_ptr().read().assume_init() };
+        let u = f(t);
+
+        let (handle, (), mut storage) = raw.into_raw_parts();
+        if Layout::new::<U>() == Layout::new::<T>() {
+            let mut raw: RawBox<MaybeUninit<U>, S> =
+                unsafe { RawBox::from_raw_parts(handle, (), storage) };
+            raw.write(u);
+            Box {
+                raw: unsafe { raw.assume_init() },
+            }
+        } else {
+            unsafe { storage.deallocate(handle, Layout::new::<T>()) };
+            Box::new_in(u, storage)
+        }
+    }
+
+    /// Move the boxed value into another storage, e.g. to move an inline
+    /// value to the heap.
+    ///
+    /// The value is moved bytewise, keeping its metadata, and the memory in
+    /// the old storage is deallocated. Gives back the box and the new storage
+    /// if the new storage can't hold the value.
+    pub fn replace_storage<S2: Storage>(self, storage: S2) -> Result<Box<T, S2>, (Self, S2)> {
+        let layout = self.raw.heap_layout();
+        let mut raw = match unsafe { RawBox::<T, S2>::new(self.raw.metadata(), storage) } {
+            Ok(raw) => raw,
+            Err(storage) => return Err((self, storage)),
+        };
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.raw.as_ptr().cast::<u8>(),
+                raw.as_mut_ptr().cast::<u8>(),
+                layout.size(),
+            )
         };
-        unsafe { this.raw.as_mut_ptr().write(t) };
-        this
+        let (handle, _, mut old_storage) = Self::into_raw_parts(self);
+        unsafe { old_storage.deallocate(handle, layout) };
+        Ok(Box { raw })
+    }
+
+    /// Convert to a raw box without dropping the boxed value.
+    ///
+    /// The raw box still deallocates the memory handle when dropped, but never
+    /// drops the value; use [`RawBox::drop_contents`] for that.
+    pub fn forget_contents(this: Self) -> RawBox<T, S> {
+        let this = ManuallyDrop::new(this);
+        unsafe { ptr::read(&this.raw) }
     }
 
     pub fn into_raw_parts(this: Self) -> (S::Handle, <T as Pointee>::Metadata, S) {
@@ -168,6 +498,93 @@ impl<T: ?Sized, S: Storage> Box<T, S> {
     }
 }
 
+impl<S: Storage> Box<str, S> {
+    /// Copy a string into a new box.
+    ///
+    /// Fails, returning the storage, if the storage can't hold the string.
+    pub fn from_str_in(s: &str, storage: S) -> Result<Self, S> {
+        let mut raw = RawBox::<str, S>::new_checked(s.len(), storage)?;
+        unsafe {
+            raw.as_mut_ptr()
+                .cast::<u8>()
+                .copy_from_nonoverlapping(s.as_ptr(), s.len())
+        };
+        Ok(Self { raw })
+    }
+}
+
+impl<T> Box<T, InlineStorage<T>> {
+    /// Box a value inline, in a const context, e.g. to put the box in a
+    /// `static`.
+    ///
+    /// # Panics
+    ///
+    /// With the `checked` feature, panics if the storage is already
+    /// allocated.
+    pub const fn const_new_in(t: T, storage: InlineStorage<T>) -> Self {
+        let (storage, handle) = storage.const_allocate_with(t);
+        Self {
+            raw: RawBox {
+                handle,
+                metadata: (),
+                storage,
+                // A sized type isn't a byte slice.
+                align: MaybeUninit::uninit(),
+                marker: PhantomData,
+            },
+        }
+    }
+}
+
+impl<R, S: Storage> Box<dyn Fn() -> R, S> {
+    /// Box a closure as a trait object.
+    ///
+    /// This is [`new_unsized`](Box::new_unsized) without having to name the
+    /// trait object type, as it follows from the closure's return type.
+    #[track_caller]
+    pub fn new_fn_in(f: impl Fn() -> R + 'static, storage: S) -> Self {
+        Self::new_unsized(f, storage)
+    }
+}
+
+impl<S: Storage> Box<dyn Any, S> {
+    /// Attempt to downcast the box to a concrete type.
+    ///
+    /// Returns the box unchanged if it doesn't hold a `T`.
+    pub fn downcast<T: Any>(self) -> Result<Box<T, S>, Self> {
+        if self.is::<T>() {
+            Ok(unsafe { self.downcast_unchecked() })
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Downcast the box to a concrete type, without checking the type.
+    ///
+    /// # Safety
+    ///
+    /// - The box must hold a `T`.
+    pub unsafe fn downcast_unchecked<T: Any>(self) -> Box<T, S> {
+        debug_assert!(self.is::<T>());
+        let (handle, _, storage) = Self::into_raw_parts(self);
+        Box::from_raw_parts(handle, (), storage)
+    }
+}
+
+/// Box a value in an inline storage sized for exactly the value.
+///
+/// This cannot fail, as the value always fits.
+pub fn inline_box<T>(t: T) -> Box<T, InlineStorage<T>> {
+    Box::new_in(t, InlineStorage::new())
+}
+
+/// Box a value in the global allocator.
+#[cfg(feature = "alloc")]
+#[track_caller]
+pub fn alloc_box<T>(t: T) -> Box<T, AllocStorage<Global>> {
+    Box::new_in(t, AllocStorage::new(Global))
+}
+
 impl<T: ?Sized, S: Storage> Deref for Box<T, S> {
     type Target = T;
 
@@ -182,9 +599,71 @@ impl<T: ?Sized, S: Storage> DerefMut for Box<T, S> {
     }
 }
 
+impl<T: ?Sized, S: Storage> AsRef<T> for Box<T, S> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized, S: Storage> AsMut<T> for Box<T, S> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl<T: ?Sized, S: Storage> Borrow<T> for Box<T, S> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized, S: Storage> BorrowMut<T> for Box<T, S> {
+    fn borrow_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+/// Clone a boxed slice into a new box of the same length, in a default
+/// storage.
+impl<T: Clone, S: Storage + Default> Clone for Box<[T], S> {
+    #[track_caller]
+    fn clone(&self) -> Self {
+        /// Drops the elements cloned so far if a clone panics.
+        struct Guard<'a, T, S: Storage> {
+            raw: &'a mut RawBox<[T], S>,
+            len: usize,
+        }
+
+        impl<T, S: Storage> Drop for Guard<'_, T, S> {
+            fn drop(&mut self) {
+                let elements = self.raw.as_mut_ptr().cast::<T>();
+                unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(elements, self.len)) }
+            }
+        }
+
+        let mut raw = match RawBox::<[T], S>::new_checked(self.len(), S::default()) {
+            Ok(raw) => raw,
+            Err(_) => handle_alloc_error(Layout::for_value::<[T]>(self)),
+        };
+        let mut guard = Guard {
+            raw: &mut raw,
+            len: 0,
+        };
+        for value in self.iter() {
+            let elements = guard.raw.as_mut_ptr().cast::<T>();
+            unsafe { elements.add(guard.len).write(value.clone()) };
+            guard.len += 1;
+        }
+        mem::forget(guard);
+        Self { raw }
+    }
+}
+
 unsafe impl<#[may_dangle] T: ?Sized, S: Storage> Drop for Box<T, S> {
     fn drop(&mut self) {
-        unsafe { ptr::drop_in_place(self.raw.as_mut_ptr()) }
+        if mem::needs_drop::<T>() {
+            unsafe { ptr::drop_in_place(self.raw.as_mut_ptr()) }
+        }
     }
 }
 
@@ -0,0 +1,92 @@
+use {
+    crate::{
+        polyfill::{checked_layout_for_metadata, layout_for_metadata},
+        ReadStorage, RoBorrowedStorage,
+    },
+    core::{
+        alloc::Layout,
+        mem::MaybeUninit,
+        ptr::{self, Pointee},
+    },
+};
+
+/// A read-only raw box around a read storage. Bundles the storage and its
+/// handle, but can't allocate, deallocate, or mutate the object.
+///
+/// Like [`RawBox`](crate::RawBox), the object may not be initialized, and
+/// pointers returned are invalidated when the reference is moved.
+pub struct RawRef<T: ?Sized, S: ReadStorage> {
+    handle: S::Handle,
+    metadata: <T as Pointee>::Metadata,
+    storage: S,
+}
+
+impl<T: ?Sized, S: ReadStorage> RawRef<T, S> {
+    fn layout(&self) -> Layout {
+        unsafe { layout_for_metadata::<T>(self.metadata).unwrap_unchecked() }
+    }
+
+    /// Get a reference to the object.
+    pub fn as_ref(&self) -> &MaybeUninit<T>
+    where
+        T: Sized,
+    {
+        unsafe { &*(self.as_ptr() as *const _) }
+    }
+
+    /// Get a pointer valid for reads to the object.
+    ///
+    /// The pointer is invalidated when the reference is moved.
+    pub fn as_ptr(&self) -> *const T {
+        unsafe {
+            let memory = self.storage.resolve_read(self.handle, self.layout());
+            ptr::from_raw_parts(memory.as_ptr().cast(), self.metadata)
+        }
+    }
+
+    /// Get the metadata of the object.
+    pub fn metadata(&self) -> <T as Pointee>::Metadata {
+        self.metadata
+    }
+
+    /// Break a raw reference into its component parts.
+    pub fn into_raw_parts(self) -> (S::Handle, <T as Pointee>::Metadata, S) {
+        (self.handle, self.metadata, self.storage)
+    }
+
+    /// Reassemble a raw reference from its component parts.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must have been created by the storage with the layout
+    ///   described by the metadata, and must not have been invalidated.
+    pub unsafe fn from_raw_parts(
+        handle: S::Handle,
+        metadata: <T as Pointee>::Metadata,
+        storage: S,
+    ) -> Self {
+        Self {
+            handle,
+            metadata,
+            storage,
+        }
+    }
+}
+
+impl<'a, T: ?Sized> RawRef<T, RoBorrowedStorage<'a>> {
+    /// Reference the object described by the given metadata at `offset` in
+    /// the borrowed memory.
+    ///
+    /// Fails if the layout can't be checked (as for
+    /// [`RawBox::new_checked`](crate::RawBox::new_checked)), or if the object
+    /// doesn't fit the memory there or wouldn't be aligned.
+    pub fn at(
+        storage: RoBorrowedStorage<'a>,
+        offset: usize,
+        metadata: <T as Pointee>::Metadata,
+    ) -> Option<Self> {
+        let layout = checked_layout_for_metadata::<T>(metadata)?;
+        let handle = storage.handle_at(offset, layout)?;
+        Some(unsafe { Self::from_raw_parts(handle, metadata, storage) })
+    }
+}
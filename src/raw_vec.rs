@@ -1,68 +1,327 @@
 use {
-    crate::{polyfill::layout_for_metadata, Storage},
+    crate::{
+        polyfill::{dangling, handle_alloc_error, layout_for_metadata},
+        InfallibleEmptyStorage, InlineArrayStorage, InlineStorage, Storage,
+    },
     core::{
         alloc::{AllocError, Layout},
-        mem::MaybeUninit,
-        ptr::{self, Pointee},
+        cmp, fmt,
+        hash::{Hash, Hasher},
+        marker::PhantomData,
+        mem::{self, ManuallyDrop, MaybeUninit},
+        ops::{Deref, DerefMut},
+        ptr::{self, NonNull, Pointee},
     },
 };
 
+#[cfg(feature = "alloc")]
+use {
+    crate::{AllocHandle, AllocStorage},
+    liballoc::alloc::Global,
+};
+
 /// A raw vec around some slice storage. Bundles the storage and its handle.
 ///
 /// Note that this is *even lower level* than [alloc's `RawVec`] currently. That
-/// raw vec handles amortized growth; this raw vec just does exactly as asked.
+/// raw vec handles amortized growth; this raw vec just does exactly as asked,
+/// unless amortized growth is asked for with a [`GrowthPolicy`].
+///
+/// If the storage's [`EMPTY_LAYOUT_IS_FREE`] is false, an empty raw vec holds
+/// no memory handle, and only allocates when it first grows.
 ///
 /// [alloc's `RawVec`]: https://github.com/rust-lang/rust/blob/master/library/alloc/src/raw_vec.rs
+/// [`EMPTY_LAYOUT_IS_FREE`]: Storage::EMPTY_LAYOUT_IS_FREE
 pub struct RawVec<T, S: Storage> {
-    handle: S::Handle,
+    // Uninitialized exactly when no memory is allocated; see `handle`.
+    handle: MaybeUninit<S::Handle>,
     metadata: <[T] as Pointee>::Metadata,
+    // A power of two, at least the alignment of `T`. `metadata` elements
+    // aligned to it always fit in a layout; see `heap_layout_with`.
+    align: usize,
     storage: S,
+    // Owns the elements for dropck. The raw vec never drops them, but Vec
+    // does, and they may still use any borrows they hold when dropped.
+    marker: PhantomData<T>,
 }
 
 impl<T, S: Storage> RawVec<T, S> {
+    /// The memory handle, or `None` if no memory is allocated, which is only
+    /// the case for empty slices if allocating empty memory isn't free.
+    fn handle(&self) -> Option<S::Handle> {
+        if S::EMPTY_LAYOUT_IS_FREE || self.metadata != 0 {
+            Some(unsafe { self.handle.assume_init() })
+        } else {
+            None
+        }
+    }
+
+    /// Allocate the memory handle for an empty slice, unless that is deferred
+    /// until the slice first grows.
+    fn allocate_empty(storage: &mut S, align: usize) -> Result<MaybeUninit<S::Handle>, AllocError> {
+        if S::EMPTY_LAYOUT_IS_FREE {
+            storage.allocate_empty(align).map(MaybeUninit::new)
+        } else {
+            Ok(MaybeUninit::uninit())
+        }
+    }
+
     fn heap_layout(&self) -> Layout {
-        Self::heap_layout_for(self.len())
+        self.heap_layout_for(self.len())
+    }
+
+    fn heap_layout_for(&self, len: usize) -> Layout {
+        Self::heap_layout_with(len, self.align)
     }
 
-    fn heap_layout_for(len: usize) -> Layout {
-        unsafe { layout_for_metadata::<[T]>(len).unwrap_unchecked() }
+    /// Compute the layout for a new length, checking for overflow.
+    fn checked_heap_layout_for(&self, len: usize) -> Result<Layout, AllocError> {
+        Layout::array::<T>(len)
+            .and_then(|layout| layout.align_to(self.align))
+            .map_err(|_| AllocError)
+    }
+
+    /// The layout of `len` elements aligned to `align`, which must fit.
+    ///
+    /// This is only called with the current length or a shorter one, which
+    /// fits: a length is only stored after its layout was checked (by
+    /// `checked_heap_layout_for`, or by the storage allocating it), and every
+    /// constructor checks its alignment.
+    fn heap_layout_with(len: usize, align: usize) -> Layout {
+        unsafe {
+            layout_for_metadata::<[T]>(len)
+                .unwrap_unchecked()
+                .align_to(align)
+                .unwrap_unchecked()
+        }
     }
 
     /// Create a new empty growable slice in the given storage.
-    pub fn new(mut storage: S) -> Result<Self, S> {
-        if let Ok(handle) = storage.allocate(Self::heap_layout_for(0)) {
+    pub fn new(storage: S) -> Result<Self, S> {
+        Self::with_align(mem::align_of::<T>(), storage)
+    }
+
+    /// Create a new empty growable slice in the given storage, aligned to at
+    /// least `align`.
+    ///
+    /// The alignment applies to the slice as a whole, e.g. to align a buffer
+    /// to a cache line; the elements are still laid out as in `[T]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn with_align(align: usize, mut storage: S) -> Result<Self, S> {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        let align = cmp::max(align, mem::align_of::<T>());
+        if let Ok(handle) = Self::allocate_empty(&mut storage, align) {
             Ok(Self {
                 handle,
                 metadata: 0,
+                align,
                 storage,
+                marker: PhantomData,
             })
         } else {
             Err(storage)
         }
     }
 
+    /// Create a new growable slice in the given storage, with a length of at
+    /// least `capacity`.
+    ///
+    /// If the storage rounds up the allocation, the extra space is included
+    /// in the length of the slice for free.
+    pub fn with_capacity(capacity: usize, mut storage: S) -> Result<Self, S> {
+        if capacity == 0 {
+            return Self::new(storage);
+        }
+
+        let allocated = Layout::array::<T>(capacity)
+            .map_err(|_| AllocError)
+            .and_then(|layout| storage.allocate_with_excess(layout));
+        match allocated {
+            Ok((handle, layout)) => Ok(Self {
+                handle: MaybeUninit::new(handle),
+                // Zero-sized elements have no size to round up.
+                metadata: layout
+                    .size()
+                    .checked_div(mem::size_of::<T>())
+                    .unwrap_or(capacity),
+                align: mem::align_of::<T>(),
+                storage,
+                marker: PhantomData,
+            }),
+            Err(AllocError) => Err(storage),
+        }
+    }
+
+    /// Create a new empty growable slice in the given storage.
+    ///
+    /// Unlike [`new`](Self::new), this cannot fail, as the storage guarantees
+    /// that allocating an empty slice always succeeds.
+    pub fn new_in(mut storage: S) -> Self
+    where
+        S: InfallibleEmptyStorage,
+    {
+        let align = mem::align_of::<T>();
+        let handle = unsafe { Self::allocate_empty(&mut storage, align).unwrap_unchecked() };
+        Self {
+            handle,
+            metadata: 0,
+            align,
+            storage,
+            marker: PhantomData,
+        }
+    }
+
     /// Get a reference to the boxed slice.
     pub fn as_ref(&self) -> &[MaybeUninit<T>] {
-        unsafe {
-            let (addr, _) = self
-                .storage
-                .resolve(self.handle, self.heap_layout())
-                .as_ptr()
-                .to_raw_parts();
-            &*ptr::from_raw_parts(addr, self.len())
-        }
+        let data = match self.handle() {
+            Some(handle) => unsafe { self.storage.resolve(handle, self.heap_layout()).as_ptr() },
+            None => dangling(self.heap_layout()).as_ptr().cast_const().cast(),
+        };
+        unsafe { &*ptr::from_raw_parts(data.cast(), self.len()) }
+    }
+
+    /// Get a pointer valid *for reads only* to the boxed slice.
+    ///
+    /// The pointer is invalidated when the raw vec is moved or used by mutable
+    /// reference.
+    pub fn as_non_null(&self) -> NonNull<[T]> {
+        let data: NonNull<()> = match self.handle() {
+            Some(handle) => unsafe {
+                NonNull::from(self.storage.resolve(handle, self.heap_layout())).cast()
+            },
+            None => dangling(self.heap_layout()).cast(),
+        };
+        NonNull::from_raw_parts(data, self.len())
     }
 
     /// Get a mutable reference to the boxed slice.
     pub fn as_mut(&mut self) -> &mut [MaybeUninit<T>] {
-        unsafe {
-            let (addr, _) = self
-                .storage
-                .resolve_mut(self.handle, self.heap_layout())
-                .as_mut_ptr()
-                .to_raw_parts();
-            &mut *ptr::from_raw_parts_mut(addr, self.len())
+        let (len, layout) = (self.len(), self.heap_layout());
+        let data = match self.handle() {
+            Some(handle) => unsafe { self.storage.resolve_mut(handle, layout).as_mut_ptr() },
+            None => dangling(layout).as_ptr().cast(),
+        };
+        unsafe { &mut *ptr::from_raw_parts_mut(data.cast(), len) }
+    }
+
+    /// Split the slice into the initialized prefix of `len` elements and the
+    /// uninitialized spare capacity.
+    ///
+    /// # Safety
+    ///
+    /// - The first `len` elements must be initialized.
+    pub unsafe fn split_at_spare_mut(&mut self, len: usize) -> (&mut [T], &mut [MaybeUninit<T>]) {
+        let (init, spare) = self.as_mut().split_at_mut(len);
+        (&mut *(init as *mut [MaybeUninit<T>] as *mut [T]), spare)
+    }
+
+    /// Compact the slice in place, moving the elements at indices for which
+    /// `keep` returns true to the front, and returning how many were kept.
+    ///
+    /// Kept elements stay in their original order. The removed elements are
+    /// swapped into the tail rather than dropped, so that the caller can drop
+    /// them if needed. The length of the slice is unchanged.
+    pub fn compact(&mut self, mut keep: impl FnMut(usize) -> bool) -> usize {
+        let slice = self.as_mut();
+        let mut kept = 0;
+        for i in 0..slice.len() {
+            if keep(i) {
+                slice.swap(kept, i);
+                kept += 1;
+            }
+        }
+        kept
+    }
+
+    /// Remove consecutive elements for which `same` returns true, moving the
+    /// survivors to the front, and returning how many survived.
+    ///
+    /// As with std's `dedup_by`, `same(a, b)` is passed each element `a` and
+    /// the last survivor `b` before it, and `a` is removed if it returns true.
+    /// Like [`compact`](Self::compact), the removed elements are swapped into
+    /// the tail rather than dropped, and the length of the slice is unchanged.
+    ///
+    /// # Safety
+    ///
+    /// - All elements of the slice must be initialized.
+    pub unsafe fn dedup(&mut self, mut same: impl FnMut(&T, &T) -> bool) -> usize {
+        let slice = &mut *(self.as_mut() as *mut [MaybeUninit<T>] as *mut [T]);
+        if slice.is_empty() {
+            return 0;
+        }
+
+        let mut kept = 1;
+        for i in 1..slice.len() {
+            if !same(&slice[i], &slice[kept - 1]) {
+                slice.swap(kept, i);
+                kept += 1;
+            }
         }
+        kept
+    }
+
+    /// Shift the elements from `at` towards the end by `by` places, opening a
+    /// gap of `by` elements at `at` (e.g. to insert into).
+    ///
+    /// The last `by` elements of the slice are overwritten, and the gap is
+    /// left holding bitwise copies of the elements that were shifted out of
+    /// it; treat both as uninitialized.
+    ///
+    /// # Safety
+    ///
+    /// - `at + by <= self.len()`.
+    /// - The last `by` elements must not need to be dropped, as they are
+    ///   overwritten without dropping.
+    pub unsafe fn shift_right(&mut self, at: usize, by: usize) {
+        debug_assert!(
+            by <= self.len() && at <= self.len() - by,
+            "invalid arguments to RawVec::shift_right"
+        );
+        let data = self.as_mut().as_mut_ptr();
+        ptr::copy(data.add(at), data.add(at + by), self.len() - at - by);
+    }
+
+    /// Shift the elements after the `by` elements at `at` towards the start by
+    /// `by` places, closing the gap (e.g. after removing from it).
+    ///
+    /// The elements in the gap are overwritten, and the last `by` elements of
+    /// the slice are left holding bitwise copies of the elements that were
+    /// shifted out of them; treat both as uninitialized.
+    ///
+    /// # Safety
+    ///
+    /// - `at + by <= self.len()`.
+    /// - The `by` elements at `at` must not need to be dropped, as they are
+    ///   overwritten without dropping.
+    pub unsafe fn shift_left(&mut self, at: usize, by: usize) {
+        debug_assert!(
+            by <= self.len() && at <= self.len() - by,
+            "invalid arguments to RawVec::shift_left"
+        );
+        let data = self.as_mut().as_mut_ptr();
+        ptr::copy(data.add(at + by), data.add(at), self.len() - at - by);
+    }
+
+    /// Drop the first `len` elements of the slice in place.
+    ///
+    /// This does not deallocate the slice; dropping the raw vec still does
+    /// that. Wrappers tracking initialization should call this before the raw
+    /// vec is dropped, as the raw vec never drops its elements itself.
+    ///
+    /// # Safety
+    ///
+    /// - The first `len` elements must be initialized.
+    /// - The elements must not be used afterwards, so this must only be called
+    ///   once for any initialized element.
+    pub unsafe fn drop_elements(&mut self, len: usize) {
+        debug_assert!(
+            len <= self.len(),
+            "invalid arguments to RawVec::drop_elements"
+        );
+        let elements = self.as_mut().as_mut_ptr().cast::<T>();
+        ptr::drop_in_place(ptr::slice_from_raw_parts_mut(elements, len));
     }
 
     /// Get the length of the slice.
@@ -72,42 +331,404 @@ impl<T, S: Storage> RawVec<T, S> {
 
     /// Grow the length of the slice to `new_len`. Does not change the length
     /// if the slice is already long enough. Does not do amortization.
+    ///
+    /// Fails without touching the storage if the new length would overflow
+    /// the maximum size of an allocation.
     pub fn grow_to(&mut self, new_len: usize) -> Result<(), AllocError> {
         if new_len <= self.len() {
             Ok(())
         } else {
-            self.handle = unsafe {
-                self.storage.grow(
-                    self.handle,
-                    self.heap_layout(),
-                    Self::heap_layout_for(new_len),
-                )
-            }?;
+            let new_layout = self.checked_heap_layout_for(new_len)?;
+            let handle = match self.handle() {
+                Some(handle) => {
+                    unsafe { self.storage.grow(handle, self.heap_layout(), new_layout) }?
+                },
+                None => self.storage.allocate(new_layout)?,
+            };
+            self.handle = MaybeUninit::new(handle);
             self.metadata = new_len;
             Ok(())
         }
     }
 
+    /// Grow the length of the slice to at least `required_len`, amortized by
+    /// the [`Doubling`] growth policy.
+    pub fn grow_amortized(&mut self, required_len: usize) -> Result<(), AllocError> {
+        self.grow_amortized_with::<Doubling>(required_len)
+    }
+
+    /// Grow the length of the slice to at least `required_len`, amortized by
+    /// the given growth policy. Does not change the length if the slice is
+    /// already long enough.
+    pub fn grow_amortized_with<G: GrowthPolicy>(
+        &mut self,
+        required_len: usize,
+    ) -> Result<(), AllocError> {
+        if required_len <= self.len() {
+            Ok(())
+        } else {
+            let new_len = G::next_capacity(self.len(), required_len);
+            self.grow_to(cmp::max(new_len, required_len))
+        }
+    }
+
     /// Shrink the length of the slice to `new_len`. Does not change the length
     /// if the slice is already shorter than the given length.
     pub fn shrink_to(&mut self, new_len: usize) -> Result<(), AllocError> {
         if new_len >= self.len() {
             Ok(())
+        } else if !S::EMPTY_LAYOUT_IS_FREE && new_len == 0 {
+            // Give back the memory, rather than holding an empty handle.
+            unsafe {
+                self.storage
+                    .deallocate(self.handle.assume_init(), self.heap_layout())
+            };
+            self.metadata = 0;
+            Ok(())
         } else {
-            self.handle = unsafe {
+            let handle = unsafe {
                 self.storage.shrink(
-                    self.handle,
+                    self.handle.assume_init(),
                     self.heap_layout(),
-                    Self::heap_layout_for(new_len),
+                    self.heap_layout_for(new_len),
                 )
             }?;
+            self.handle = MaybeUninit::new(handle);
+            self.metadata = new_len;
             Ok(())
         }
     }
+
+    /// Break a raw vec into its memory handle (`None` if no memory is
+    /// allocated), length, alignment, and storage.
+    pub fn into_raw_parts(self) -> (Option<S::Handle>, usize, usize, S) {
+        let this = &*ManuallyDrop::new(self);
+        (this.handle(), this.metadata, this.align, unsafe {
+            ptr::read(&this.storage)
+        })
+    }
+
+    /// Reassemble a raw vec from its component parts.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must have been created by the storage with the layout of
+    ///   `len` elements aligned to `align`, and must not have been invalidated.
+    /// - The handle must be `None` exactly when no memory is allocated, i.e.
+    ///   when `len` is zero and the storage's [`EMPTY_LAYOUT_IS_FREE`] is false.
+    /// - `align` must be a power of two, at least the alignment of `T`.
+    ///
+    /// [`EMPTY_LAYOUT_IS_FREE`]: Storage::EMPTY_LAYOUT_IS_FREE
+    pub unsafe fn from_raw_parts(
+        handle: Option<S::Handle>,
+        len: usize,
+        align: usize,
+        storage: S,
+    ) -> Self {
+        debug_assert!(
+            handle.is_some() == (S::EMPTY_LAYOUT_IS_FREE || len != 0)
+                && align >= mem::align_of::<T>()
+                && Layout::array::<T>(len)
+                    .and_then(|layout| layout.align_to(align))
+                    .is_ok(),
+            "invalid arguments to RawVec::from_raw_parts"
+        );
+        Self {
+            handle: handle.map_or(MaybeUninit::uninit(), MaybeUninit::new),
+            metadata: len,
+            align,
+            storage,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Shows the handle (`None` if no memory is allocated), length, and layout,
+/// but not the (maybe uninitialized) elements.
+impl<T, S: Storage> fmt::Debug for RawVec<T, S>
+where
+    S::Handle: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawVec")
+            .field("handle", &self.handle())
+            .field("len", &self.len())
+            .field("layout", &self.heap_layout())
+            .finish_non_exhaustive()
+    }
 }
 
 unsafe impl<#[may_dangle] T, S: Storage> Drop for RawVec<T, S> {
     fn drop(&mut self) {
-        unsafe { self.storage.deallocate(self.handle, self.heap_layout()) }
+        if let Some(handle) = self.handle() {
+            unsafe { self.storage.deallocate(handle, self.heap_layout()) }
+        }
+    }
+}
+
+/// A contiguous growable array type. A tiny subset of std's Vec.
+pub struct Vec<T, S: Storage> {
+    raw: RawVec<T, S>,
+    len: usize,
+}
+
+impl<T, S: Storage> Vec<T, S> {
+    pub fn new_in(storage: S) -> Self
+    where
+        S: InfallibleEmptyStorage,
+    {
+        Self {
+            raw: RawVec::new_in(storage),
+            len: 0,
+        }
+    }
+
+    /// The number of initialized elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The number of elements the vec can hold without growing.
+    pub fn capacity(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Reserve capacity for at least `additional` more elements.
+    #[track_caller]
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len.saturating_add(additional);
+        if required > self.capacity() && self.raw.grow_amortized(required).is_err() {
+            handle_alloc_error(Layout::array::<T>(required).unwrap_or(Layout::new::<T>()))
+        }
+    }
+
+    /// Split the vec into its initialized elements and its uninitialized
+    /// spare capacity.
+    ///
+    /// The spare capacity can be initialized and then included in the vec
+    /// with [`set_len`](Self::set_len).
+    pub fn split_at_spare_mut(&mut self) -> (&mut [T], &mut [MaybeUninit<T>]) {
+        unsafe { self.raw.split_at_spare_mut(self.len) }
+    }
+
+    /// Set the number of initialized elements.
+    ///
+    /// # Safety
+    ///
+    /// - `new_len` must be at most the capacity.
+    /// - The first `new_len` elements must be initialized.
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(
+            new_len <= self.capacity(),
+            "invalid arguments to Vec::set_len"
+        );
+        self.len = new_len;
+    }
+
+    #[track_caller]
+    pub fn push(&mut self, value: T) {
+        self.reserve(1);
+        self.raw.as_mut()[self.len].write(value);
+        self.len += 1;
+    }
+
+    /// Push a value, or give it back if the vec can't grow to hold it, e.g.
+    /// because it is a full [`ArrayVec`].
+    ///
+    /// If amortized growth fails, this still tries to grow by exactly one
+    /// element, so that e.g. inline storages are filled up completely.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.len == self.capacity() {
+            let grown = match self.len.checked_add(1) {
+                Some(required) => self
+                    .raw
+                    .grow_amortized(required)
+                    .or_else(|_| self.raw.grow_to(required)),
+                None => Err(AllocError),
+            };
+            if grown.is_err() {
+                return Err(value);
+            }
+        }
+        self.raw.as_mut()[self.len].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { self.raw.as_ref()[self.len].assume_init_read() })
+        }
+    }
+
+    /// Break a vec into its memory handle (`None` if no memory is allocated),
+    /// length, capacity, and storage, without touching the elements.
+    ///
+    /// The memory is not shrunk to the length, so the capacity survives
+    /// reassembling the vec with [`from_raw_parts`](Self::from_raw_parts).
+    pub fn into_raw_parts(self) -> (Option<S::Handle>, usize, usize, S) {
+        let this = ManuallyDrop::new(self);
+        let len = this.len;
+        let (handle, capacity, _, storage) = unsafe { ptr::read(&this.raw) }.into_raw_parts();
+        (handle, len, capacity, storage)
+    }
+
+    /// Reassemble a vec from its component parts.
+    ///
+    /// # Safety
+    ///
+    /// - The parts must be valid for [`RawVec::from_raw_parts`] with a length
+    ///   of `capacity` and the alignment of `T`.
+    /// - `len` must be at most `capacity`, and the first `len` elements must
+    ///   be initialized.
+    pub unsafe fn from_raw_parts(
+        handle: Option<S::Handle>,
+        len: usize,
+        capacity: usize,
+        storage: S,
+    ) -> Self {
+        debug_assert!(len <= capacity, "invalid arguments to Vec::from_raw_parts");
+        Self {
+            raw: RawVec::from_raw_parts(handle, capacity, mem::align_of::<T>(), storage),
+            len,
+        }
+    }
+}
+
+/// A vec of fixed capacity `N`, stored inline.
+///
+/// Pushing more than `N` elements fails to allocate, as the inline storage
+/// can't grow; use [`try_push`](Vec::try_push) to get the value back instead.
+pub type ArrayVec<T, const N: usize> = Vec<T, InlineArrayStorage<T, N>>;
+
+impl<T, const N: usize> ArrayVec<T, N> {
+    /// Create an empty array vec, with its full capacity of `N` elements.
+    pub fn new() -> Self {
+        match RawVec::with_capacity(N, InlineStorage::new()) {
+            Ok(raw) => Self { raw, len: 0 },
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl<T, S: Storage + Default> Default for Vec<T, S> {
+    /// Create an empty vec in the default storage.
+    ///
+    /// If the storage's [`EMPTY_LAYOUT_IS_FREE`] is false, the vec holds no
+    /// memory handle until it first grows, so this never allocates.
+    ///
+    /// [`EMPTY_LAYOUT_IS_FREE`]: Storage::EMPTY_LAYOUT_IS_FREE
+    #[track_caller]
+    fn default() -> Self {
+        match RawVec::new(S::default()) {
+            Ok(raw) => Self { raw, len: 0 },
+            Err(_) => handle_alloc_error(Layout::new::<[T; 0]>()),
+        }
+    }
+}
+
+impl<T, S: Storage> Deref for Vec<T, S> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*(&self.raw.as_ref()[..self.len] as *const [MaybeUninit<T>] as *const [T]) }
+    }
+}
+
+impl<T, S: Storage> DerefMut for Vec<T, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let len = self.len;
+        unsafe { &mut *(&mut self.raw.as_mut()[..len] as *mut [MaybeUninit<T>] as *mut [T]) }
+    }
+}
+
+unsafe impl<#[may_dangle] T, S: Storage> Drop for Vec<T, S> {
+    fn drop(&mut self) {
+        unsafe { self.raw.drop_elements(self.len) }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Vec<T, AllocStorage<Global>> {
+    /// Convert into a std `Vec`, without copying the elements.
+    ///
+    /// The length and capacity are preserved exactly.
+    pub fn into_std_vec(self) -> liballoc::vec::Vec<T> {
+        let mut this = ManuallyDrop::new(self);
+        let (len, capacity) = (this.len, this.capacity());
+        let ptr = this.raw.as_mut().as_mut_ptr().cast::<T>();
+        if let Some(handle) = this.raw.handle() {
+            // The std `Vec` deallocates the memory outside of the storage.
+            handle.untrack();
+        }
+        unsafe { liballoc::vec::Vec::from_raw_parts(ptr, len, capacity) }
+    }
+}
+
+/// Convert from a std `Vec`, without copying the elements.
+///
+/// The length and capacity are preserved exactly.
+#[cfg(feature = "alloc")]
+impl<T> From<liballoc::vec::Vec<T>> for Vec<T, AllocStorage<Global>> {
+    fn from(vec: liballoc::vec::Vec<T>) -> Self {
+        let mut vec = ManuallyDrop::new(vec);
+        let ptr = unsafe { NonNull::new_unchecked(vec.as_mut_ptr()) };
+        Self {
+            raw: RawVec {
+                handle: MaybeUninit::new(AllocHandle::new(ptr)),
+                metadata: vec.capacity(),
+                align: mem::align_of::<T>(),
+                storage: AllocStorage::new(Global),
+                marker: PhantomData,
+            },
+            len: vec.len(),
+        }
+    }
+}
+
+impl<T: PartialEq<U>, U, S: Storage, S2: Storage> PartialEq<Vec<U, S2>> for Vec<T, S> {
+    fn eq(&self, other: &Vec<U, S2>) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq, S: Storage> Eq for Vec<T, S> {}
+
+impl<T: PartialOrd, S: Storage, S2: Storage> PartialOrd<Vec<T, S2>> for Vec<T, S> {
+    fn partial_cmp(&self, other: &Vec<T, S2>) -> Option<cmp::Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<T: Ord, S: Storage> Ord for Vec<T, S> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T: Hash, S: Storage> Hash for Vec<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+/// A strategy for amortizing the growth of a [`RawVec`].
+pub trait GrowthPolicy {
+    /// Choose the length to grow a slice of length `current` to, such that it
+    /// can hold at least `required` elements.
+    ///
+    /// Lengths smaller than `required` are treated as `required`.
+    fn next_capacity(current: usize, required: usize) -> usize;
+}
+
+/// The default [`GrowthPolicy`], doubling the length of the slice.
+pub struct Doubling;
+
+impl GrowthPolicy for Doubling {
+    fn next_capacity(current: usize, required: usize) -> usize {
+        cmp::max(current.saturating_mul(2), required)
     }
 }
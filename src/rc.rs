@@ -0,0 +1,206 @@
+use {
+    crate::{polyfill::handle_alloc_error, CloneStorage, SharedMutabilityStorage},
+    core::{
+        alloc::Layout,
+        cell::Cell,
+        marker::PhantomData,
+        ops::Deref,
+        ptr::{self, NonNull},
+    },
+};
+
+/// The control block of an [`Rc`], stored in the storage.
+#[repr(C)]
+struct RcBox<T> {
+    strong: Cell<usize>,
+    /// The number of weak references, plus one for all strong references.
+    weak: Cell<usize>,
+    value: T,
+}
+
+/// A single-threaded reference-counting pointer. A tiny subset of std's Rc.
+///
+/// Every `Rc` and [`Weak`] holds a clone of the storage, so the storage must
+/// be a [`CloneStorage`].
+///
+/// As for [`Arc`](crate::Arc), the handle is only resolved (with
+/// [`SharedMutabilityStorage::resolve_raw`]) when the `Rc` is created, and the
+/// pointer is shared by the clones and [`Weak`]s. Resolving again would create
+/// a new `&mut Memory` over the whole control block, invalidating references
+/// to the value borrowed from other `Rc`s.
+pub struct Rc<T, S: SharedMutabilityStorage + CloneStorage> {
+    handle: S::Handle,
+    ptr: NonNull<RcBox<T>>,
+    storage: S,
+    marker: RcMarker<T>,
+}
+
+/// A non-owning reference to the value of an [`Rc`].
+///
+/// This keeps the control block allocated, but not the value alive.
+pub struct Weak<T, S: SharedMutabilityStorage + CloneStorage> {
+    handle: S::Handle,
+    ptr: NonNull<RcBox<T>>,
+    storage: S,
+    marker: RcMarker<T>,
+}
+
+/// Owns the control block, and makes the pointers `!Send` and `!Sync`, as the
+/// reference counts aren't atomic.
+type RcMarker<T> = PhantomData<(RcBox<T>, *const ())>;
+
+const fn rc_layout<T>() -> Layout {
+    Layout::new::<RcBox<T>>()
+}
+
+/// Release one weak reference, deallocating the control block if it was the
+/// last one.
+///
+/// # Safety
+///
+/// - The handle must be an undeallocated control block for a `T` in `storage`,
+///   and `ptr` the pointer it was resolved to.
+/// - The weak reference must not be used afterwards.
+unsafe fn release_weak<T, S: SharedMutabilityStorage>(
+    storage: &mut S,
+    handle: S::Handle,
+    ptr: NonNull<RcBox<T>>,
+) {
+    let weak = &ptr.as_ref().weak;
+    weak.set(weak.get() - 1);
+    if weak.get() == 0 {
+        storage.deallocate(handle, rc_layout::<T>());
+    }
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Rc<T, S> {
+    #[track_caller]
+    pub fn new_in(value: T, mut storage: S) -> Self {
+        let handle = match storage.allocate(rc_layout::<T>()) {
+            Ok(handle) => handle,
+            Err(_) => handle_alloc_error(rc_layout::<T>()),
+        };
+        let ptr = unsafe {
+            let memory = storage.resolve_raw(handle, rc_layout::<T>());
+            let ptr = memory.as_mut_ptr().cast::<RcBox<T>>();
+            ptr.write(RcBox {
+                strong: Cell::new(1),
+                weak: Cell::new(1),
+                value,
+            });
+            NonNull::new_unchecked(ptr)
+        };
+        Self {
+            handle,
+            ptr,
+            storage,
+            marker: PhantomData,
+        }
+    }
+
+    fn inner(&self) -> &RcBox<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Create a new [`Weak`] reference to this value.
+    pub fn downgrade(this: &Self) -> Weak<T, S> {
+        let weak = &this.inner().weak;
+        weak.set(weak.get() + 1);
+        Weak {
+            handle: this.handle,
+            ptr: this.ptr,
+            storage: this.storage.clone(),
+            marker: PhantomData,
+        }
+    }
+
+    /// The number of `Rc`s to this value.
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.get()
+    }
+
+    /// The number of [`Weak`]s to this value.
+    pub fn weak_count(this: &Self) -> usize {
+        this.inner().weak.get() - 1
+    }
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Clone for Rc<T, S> {
+    fn clone(&self) -> Self {
+        let strong = &self.inner().strong;
+        strong.set(strong.get() + 1);
+        Self {
+            handle: self.handle,
+            ptr: self.ptr,
+            storage: self.storage.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Deref for Rc<T, S> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner().value
+    }
+}
+
+unsafe impl<#[may_dangle] T, S: SharedMutabilityStorage + CloneStorage> Drop for Rc<T, S> {
+    fn drop(&mut self) {
+        let strong = &self.inner().strong;
+        strong.set(strong.get() - 1);
+        if strong.get() == 0 {
+            unsafe {
+                ptr::drop_in_place(ptr::addr_of_mut!((*self.ptr.as_ptr()).value));
+                release_weak(&mut self.storage, self.handle, self.ptr);
+            }
+        }
+    }
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Weak<T, S> {
+    fn inner(&self) -> &RcBox<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Get an [`Rc`] to the value, if it hasn't been dropped yet.
+    pub fn upgrade(&self) -> Option<Rc<T, S>> {
+        let strong = &self.inner().strong;
+        if strong.get() == 0 {
+            None
+        } else {
+            strong.set(strong.get() + 1);
+            Some(Rc {
+                handle: self.handle,
+                ptr: self.ptr,
+                storage: self.storage.clone(),
+                marker: PhantomData,
+            })
+        }
+    }
+
+    /// The number of [`Rc`]s to the value.
+    pub fn strong_count(&self) -> usize {
+        self.inner().strong.get()
+    }
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Clone for Weak<T, S> {
+    fn clone(&self) -> Self {
+        let weak = &self.inner().weak;
+        weak.set(weak.get() + 1);
+        Self {
+            handle: self.handle,
+            ptr: self.ptr,
+            storage: self.storage.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S: SharedMutabilityStorage + CloneStorage> Drop for Weak<T, S> {
+    fn drop(&mut self) {
+        unsafe { release_weak(&mut self.storage, self.handle, self.ptr) }
+    }
+}
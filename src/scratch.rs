@@ -0,0 +1,116 @@
+use {
+    crate::{Memory, PinningStorage, SharedMutabilityStorage, Storage},
+    core::alloc::{AllocError, Layout},
+};
+
+/// A storage which reuses the last deallocated memory handle.
+///
+/// Deallocating a handle stows it away (freeing any previously stowed handle)
+/// instead of freeing it, and the next allocation of the same layout reuses the
+/// stowed handle. Only allocations of other layouts go to the inner storage
+/// (freeing the stowed handle first). This avoids churning the inner storage in
+/// loops which repeatedly allocate and free same-sized temporaries.
+///
+/// The stowed handle is freed when the scratch storage is dropped.
+pub struct ScratchStorage<S: Storage> {
+    storage: S,
+    cache: Option<(S::Handle, Layout)>,
+}
+
+impl<S: Storage> ScratchStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            cache: None,
+        }
+    }
+
+    /// Get a reference to the inner storage.
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    /// Take the stowed handle if it has this layout, or free it otherwise.
+    fn take_cached(&mut self, layout: Layout) -> Option<S::Handle> {
+        match self.cache.take() {
+            Some((handle, cached_layout)) if cached_layout == layout => Some(handle),
+            cache => {
+                // Free the stowed handle first, as allocating may invalidate
+                // it if the inner storage isn't a MultipleStorage.
+                if let Some((handle, layout)) = cache {
+                    unsafe { self.storage.deallocate(handle, layout) };
+                }
+                None
+            },
+        }
+    }
+}
+
+impl<S: Storage> Drop for ScratchStorage<S> {
+    fn drop(&mut self) {
+        if let Some((handle, layout)) = self.cache.take() {
+            unsafe { self.storage.deallocate(handle, layout) }
+        }
+    }
+}
+
+unsafe impl<S: Storage> Storage for ScratchStorage<S> {
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        match self.take_cached(layout) {
+            Some(handle) => Ok(handle),
+            None => self.storage.allocate(layout),
+        }
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        let layout = Layout::from_size_align(0, align).map_err(|_| AllocError)?;
+        match self.take_cached(layout) {
+            Some(handle) => Ok(handle),
+            None => self.storage.allocate_empty(align),
+        }
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        if let Some((handle, layout)) = self.cache.replace((handle, layout)) {
+            self.storage.deallocate(handle, layout);
+        }
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.storage.resolve(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_mut(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.storage.grow(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.storage.shrink(handle, old_layout, new_layout)
+    }
+}
+
+unsafe impl<S: SharedMutabilityStorage> SharedMutabilityStorage for ScratchStorage<S> {
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.resolve_raw(handle, layout)
+    }
+}
+
+unsafe impl<S: PinningStorage> PinningStorage for ScratchStorage<S> {}
@@ -1,9 +1,12 @@
 use {
-    crate::{AllocStorage, InlineStorage, Memory, Storage},
+    crate::{
+        AllocHandle, AllocStorage, InfallibleEmptyStorage, InlineStorage, Memory, SingleHandle,
+        Storage,
+    },
     core::{
         alloc::{AllocError, Allocator, Layout},
-        hint::unreachable_unchecked,
-        ptr::copy_nonoverlapping,
+        mem,
+        ptr::{copy_nonoverlapping, NonNull},
     },
 };
 
@@ -13,39 +16,98 @@ use {
 /// The `DataStore` type parameter determines the layout of the inline storage.
 /// (It would be nice to use `const LAYOUT: Layout` instead, but the needed
 /// features are currently a little *too* incomplete to be usable here.)
+///
+/// Allocated memory is handled by a pointer stored inline, so the `DataStore`
+/// must be able to hold a pointer for memory to spill outline. If it can't,
+/// e.g. `SmallStorage<u8, _>` or a byte array, memory which doesn't fit inline
+/// fails to allocate.
+///
+/// This is `repr(C)`: the [`InlineStorage`], then the allocator. Whether the
+/// inline bytes hold the memory or the outline pointer depends only on the
+/// layout the memory is used with, which isn't stored, so reading them from C
+/// needs the layout as well.
+#[repr(C)]
 pub struct SmallStorage<DataStore, A: Allocator> {
     inline: InlineStorage<DataStore>,
     outline: AllocStorage<A>,
 }
 
 impl<DataStore, A: Allocator> SmallStorage<DataStore, A> {
-    pub fn new(alloc: A) -> Self {
+    pub const fn new(alloc: A) -> Self {
         Self {
             inline: InlineStorage::new(),
             outline: AllocStorage::new(alloc),
         }
     }
 
-    const OUTLINE_HANDLE_LAYOUT: Layout = Layout::new::<<AllocStorage<A> as Storage>::Handle>();
+    /// Check if memory of the given layout is stored inline, rather than
+    /// being allocated.
+    pub fn is_inline(&self, layout: Layout) -> bool {
+        self.inline.fits(layout)
+    }
+
+    /// The number of bytes which can be stored inline.
+    pub const INLINE_CAPACITY: usize = mem::size_of::<DataStore>();
+
+    /// The number of bytes which can be stored inline.
+    ///
+    /// This is the same as [`Self::INLINE_CAPACITY`].
+    pub fn inline_capacity(&self) -> usize {
+        Self::INLINE_CAPACITY
+    }
+
+    /// The outline memory is handled by its pointer, stored inline.
+    ///
+    /// With the `checked` feature, the outline handle isn't tracked, as the
+    /// inline handle already catches being invalidated, so that the pointer
+    /// still fits in a `usize` data store.
+    const OUTLINE_HANDLE_LAYOUT: Layout = Layout::new::<NonNull<()>>();
+
+    /// Store the outline handle in the inline memory of `handle`.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must be valid for the outline handle layout.
+    unsafe fn write_outline(&mut self, handle: SingleHandle, addr: AllocHandle) {
+        addr.untrack();
+        *self
+            .inline
+            .resolve_mut(handle, Self::OUTLINE_HANDLE_LAYOUT)
+            .as_mut_ptr()
+            .cast() = addr.pointer;
+    }
+
+    /// Read the outline handle from the inline memory of `handle`.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must be valid for the outline handle layout, and hold an
+    ///   outline handle.
+    unsafe fn read_outline(&self, handle: SingleHandle) -> AllocHandle {
+        AllocHandle::new::<()>(
+            *self
+                .inline
+                .resolve(handle, Self::OUTLINE_HANDLE_LAYOUT)
+                .as_ptr()
+                .cast(),
+        )
+    }
 }
 
 unsafe impl<DataStore, A: Allocator> Storage for SmallStorage<DataStore, A> {
-    type Handle = ();
+    type Handle = SingleHandle;
 
     fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
         if self.inline.fits(layout) {
             self.inline.allocate(layout)
-        } else {
+        } else if self.inline.fits(Self::OUTLINE_HANDLE_LAYOUT) {
             let addr = self.outline.allocate(layout)?;
             let addr_handle = self.inline.allocate(Self::OUTLINE_HANDLE_LAYOUT)?;
-            unsafe {
-                *self
-                    .inline
-                    .resolve_mut(addr_handle, Self::OUTLINE_HANDLE_LAYOUT)
-                    .as_mut_ptr()
-                    .cast() = addr;
-            }
+            unsafe { self.write_outline(addr_handle, addr) };
             Ok(addr_handle)
+        } else {
+            // There's no room to store the outline handle inline.
+            Err(AllocError)
         }
     }
 
@@ -53,11 +115,7 @@ unsafe impl<DataStore, A: Allocator> Storage for SmallStorage<DataStore, A> {
         if self.inline.fits(layout) {
             self.inline.deallocate(handle, layout)
         } else {
-            let addr = *self
-                .inline
-                .resolve_mut(handle, Self::OUTLINE_HANDLE_LAYOUT)
-                .as_ptr()
-                .cast();
+            let addr = self.read_outline(handle);
             self.inline.deallocate(handle, Self::OUTLINE_HANDLE_LAYOUT);
             self.outline.deallocate(addr, layout);
         }
@@ -67,11 +125,7 @@ unsafe impl<DataStore, A: Allocator> Storage for SmallStorage<DataStore, A> {
         if self.inline.fits(layout) {
             self.inline.resolve(handle, layout)
         } else {
-            let addr = *self
-                .inline
-                .resolve(handle, Self::OUTLINE_HANDLE_LAYOUT)
-                .as_ptr()
-                .cast();
+            let addr = self.read_outline(handle);
             self.outline.resolve(addr, layout)
         }
     }
@@ -80,11 +134,7 @@ unsafe impl<DataStore, A: Allocator> Storage for SmallStorage<DataStore, A> {
         if self.inline.fits(layout) {
             self.inline.resolve_mut(handle, layout)
         } else {
-            let addr = *self
-                .inline
-                .resolve_mut(handle, Self::OUTLINE_HANDLE_LAYOUT)
-                .as_mut_ptr()
-                .cast();
+            let addr = self.read_outline(handle);
             self.outline.resolve_mut(addr, layout)
         }
     }
@@ -97,42 +147,18 @@ unsafe impl<DataStore, A: Allocator> Storage for SmallStorage<DataStore, A> {
     ) -> Result<Self::Handle, AllocError> {
         match (self.inline.fits(old_layout), self.inline.fits(new_layout)) {
             (true, true) => self.inline.grow(handle, old_layout, new_layout),
-            (false, true) => unreachable_unchecked(),
             (false, false) => {
-                let addr = self
-                    .inline
-                    .resolve_mut(handle, Self::OUTLINE_HANDLE_LAYOUT)
-                    .as_mut_ptr()
-                    .cast();
-                *addr = self.outline.grow(*addr, old_layout, new_layout)?;
+                let addr = self.read_outline(handle);
+                let addr = self.outline.grow(addr, old_layout, new_layout)?;
+                self.write_outline(handle, addr);
                 Ok(handle)
             },
-            (true, false) => {
-                if !self.inline.fits(Self::OUTLINE_HANDLE_LAYOUT) {
-                    return Err(AllocError);
-                }
-
-                let addr = self.outline.allocate(new_layout)?;
-                let new_ptr = self.outline.resolve_mut(addr, new_layout);
-                let old_ptr = self.inline.resolve_mut(handle, old_layout);
-
-                copy_nonoverlapping(
-                    old_ptr.as_mut_ptr(),
-                    new_ptr.as_mut_ptr(),
-                    old_layout.size(),
-                );
-
-                self.inline.deallocate(handle, old_layout);
-                let addr_handle = self
-                    .inline
-                    .allocate(Self::OUTLINE_HANDLE_LAYOUT)
-                    .unwrap_unchecked();
-                *self
-                    .inline
-                    .resolve_mut(addr_handle, Self::OUTLINE_HANDLE_LAYOUT)
-                    .as_mut_ptr()
-                    .cast() = addr;
-                Ok(addr_handle)
+            (true, false) => self.move_outline(handle, old_layout, new_layout, old_layout.size()),
+            // The new layout can fit when the old one doesn't if it is less
+            // aligned, as grow is allowed to change the alignment.
+            (false, true) => {
+                let handle = self.move_inline(handle, old_layout, new_layout, old_layout.size());
+                Ok(handle)
             },
         }
     }
@@ -145,19 +171,83 @@ unsafe impl<DataStore, A: Allocator> Storage for SmallStorage<DataStore, A> {
     ) -> Result<Self::Handle, AllocError> {
         match (self.inline.fits(old_layout), self.inline.fits(new_layout)) {
             (true, true) => self.inline.shrink(handle, old_layout, new_layout),
-            (true, false) => unreachable_unchecked(),
             (false, false) => {
-                let addr = self
-                    .inline
-                    .resolve_mut(handle, Self::OUTLINE_HANDLE_LAYOUT)
-                    .as_mut_ptr()
-                    .cast();
-                *addr = self.outline.shrink(*addr, old_layout, new_layout)?;
+                let addr = self.read_outline(handle);
+                let addr = self.outline.shrink(addr, old_layout, new_layout)?;
+                self.write_outline(handle, addr);
                 Ok(handle)
             },
             (false, true) => {
-                todo!();
+                let handle = self.move_inline(handle, old_layout, new_layout, new_layout.size());
+                Ok(handle)
             },
+            // The new layout can not fit when the old one does if it is more
+            // aligned, as shrink is allowed to change the alignment.
+            (true, false) => self.move_outline(handle, old_layout, new_layout, new_layout.size()),
+        }
+    }
+}
+
+impl<DataStore, A: Allocator> SmallStorage<DataStore, A> {
+    /// Move inline memory to a new outline allocation, copying `size` bytes.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must be valid for `old_layout`, which must fit inline.
+    /// - `size` must be at most the size of both layouts.
+    unsafe fn move_outline(
+        &mut self,
+        handle: SingleHandle,
+        old_layout: Layout,
+        new_layout: Layout,
+        size: usize,
+    ) -> Result<SingleHandle, AllocError> {
+        if !self.inline.fits(Self::OUTLINE_HANDLE_LAYOUT) {
+            return Err(AllocError);
         }
+
+        let addr = self.outline.allocate(new_layout)?;
+        let new_ptr = self.outline.resolve_mut(addr, new_layout);
+        let old_ptr = self.inline.resolve_mut(handle, old_layout);
+
+        copy_nonoverlapping(old_ptr.as_mut_ptr(), new_ptr.as_mut_ptr(), size);
+
+        self.inline.deallocate(handle, old_layout);
+        let addr_handle = self
+            .inline
+            .allocate(Self::OUTLINE_HANDLE_LAYOUT)
+            .unwrap_unchecked();
+        self.write_outline(addr_handle, addr);
+        Ok(addr_handle)
+    }
+
+    /// Move outline memory into the inline storage, copying `size` bytes, and
+    /// get the new handle.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must be valid for `old_layout`, which must not fit inline.
+    /// - `new_layout` must fit inline.
+    /// - `size` must be at most the size of both layouts.
+    unsafe fn move_inline(
+        &mut self,
+        handle: SingleHandle,
+        old_layout: Layout,
+        new_layout: Layout,
+        size: usize,
+    ) -> SingleHandle {
+        // Take the address out first, as the memory overwrites it.
+        let addr = self.read_outline(handle);
+        self.inline.deallocate(handle, Self::OUTLINE_HANDLE_LAYOUT);
+        let new_handle = self.inline.allocate(new_layout).unwrap_unchecked();
+
+        let old_ptr = self.outline.resolve_mut(addr, old_layout);
+        let new_ptr = self.inline.resolve_mut(new_handle, new_layout);
+        copy_nonoverlapping(old_ptr.as_mut_ptr(), new_ptr.as_mut_ptr(), size);
+
+        self.outline.deallocate(addr, old_layout);
+        new_handle
     }
 }
+
+unsafe impl<DataStore, A: Allocator> InfallibleEmptyStorage for SmallStorage<DataStore, A> {}
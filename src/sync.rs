@@ -0,0 +1,183 @@
+use {
+    crate::{ConstPoolStorage, Memory, MultipleStorage, SharedMutabilityStorage, Storage},
+    core::{
+        alloc::{AllocError, Layout},
+        cell::UnsafeCell,
+    },
+};
+
+/// A raw mutual exclusion lock, as used by [`SyncStorage`].
+///
+/// This is provided by the user, so that `SyncStorage` can stay `no_std`.
+///
+/// # Safety
+///
+/// - Only one thread may hold the lock at a time; `lock` must block until the
+///   lock is acquired.
+pub unsafe trait RawMutex {
+    /// Acquire the lock, blocking until it is available.
+    fn lock(&self);
+
+    /// Release the lock.
+    ///
+    /// # Safety
+    ///
+    /// - The lock must be held by the current thread.
+    unsafe fn unlock(&self);
+}
+
+/// A storage shared behind a lock.
+///
+/// `&SyncStorage` is itself a storage, so one (multiple) storage can be shared
+/// by many boxes that each only hold a shared reference to it. The inner
+/// storage must be a [`SharedMutabilityStorage`], so that resolved memory stays
+/// valid after the lock is released.
+pub struct SyncStorage<L: RawMutex, S> {
+    lock: L,
+    storage: UnsafeCell<S>,
+}
+
+unsafe impl<L: RawMutex + Sync, S: Send> Sync for SyncStorage<L, S> {}
+
+impl<L: RawMutex, S> SyncStorage<L, S> {
+    pub const fn new(lock: L, storage: S) -> Self {
+        Self {
+            lock,
+            storage: UnsafeCell::new(storage),
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage.into_inner()
+    }
+
+    /// Run a closure with exclusive access to the inner storage.
+    ///
+    /// This is only reached through `&self`, so the `&mut S` is only sound
+    /// because memory resolved from a [`SharedMutabilityStorage`] isn't
+    /// invalidated by it; it can't be public.
+    fn with_lock<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        self.locked(|storage| f(unsafe { &mut *storage }))
+    }
+
+    /// Run a closure with shared access to the inner storage, under the lock.
+    ///
+    /// Resolving doesn't need exclusive access, and creating a `&mut S` would
+    /// invalidate memory resolved earlier from an inline inner storage.
+    fn with_lock_shared<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        self.locked(|storage| f(unsafe { &*storage }))
+    }
+
+    fn locked<R>(&self, f: impl FnOnce(*mut S) -> R) -> R {
+        struct Unlock<'a, L: RawMutex>(&'a L);
+        impl<L: RawMutex> Drop for Unlock<'_, L> {
+            fn drop(&mut self) {
+                unsafe { self.0.unlock() }
+            }
+        }
+
+        self.lock.lock();
+        let _unlock = Unlock(&self.lock);
+        f(self.storage.get())
+    }
+}
+
+impl<L: RawMutex, T, const N: usize> SyncStorage<L, ConstPoolStorage<T, N>> {
+    /// Whether every block of the inner pool is allocated.
+    pub fn is_full(&self) -> bool {
+        self.with_lock_shared(|pool| pool.is_full())
+    }
+}
+
+unsafe impl<L: RawMutex, S: Storage> Storage for SyncStorage<L, S> {
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = S::NEEDS_LAYOUT_FOR_DEALLOC;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        self.storage.get_mut().allocate(layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.storage.get_mut().deallocate(handle, layout)
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.with_lock_shared(|storage| &*(storage.resolve(handle, layout) as *const Memory))
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.storage.get_mut().resolve_mut(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.storage.get_mut().grow(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.storage
+            .get_mut()
+            .shrink(handle, old_layout, new_layout)
+    }
+}
+
+unsafe impl<L: RawMutex, S: SharedMutabilityStorage> SharedMutabilityStorage for SyncStorage<L, S> {
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.with_lock_shared(|storage| &mut *(storage.resolve_raw(handle, layout) as *mut Memory))
+    }
+}
+
+unsafe impl<L: RawMutex, S> Storage for &'_ SyncStorage<L, S>
+where
+    S: MultipleStorage + SharedMutabilityStorage,
+{
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = S::NEEDS_LAYOUT_FOR_DEALLOC;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        self.with_lock(|storage| storage.allocate(layout))
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.with_lock(|storage| storage.deallocate(handle, layout))
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        (**self).resolve_raw(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        (**self).resolve_raw(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.with_lock(|storage| storage.grow(handle, old_layout, new_layout))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.with_lock(|storage| storage.shrink(handle, old_layout, new_layout))
+    }
+}
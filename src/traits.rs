@@ -2,6 +2,7 @@ use core::{
     alloc::{AllocError, Layout},
     hash::Hash,
     mem::MaybeUninit,
+    pin::Pin,
     ptr::copy_nonoverlapping,
 };
 
@@ -17,6 +18,21 @@ pub unsafe trait Storage {
     /// (Removing `Send + Sync` is enticing, as it allows using `ptr::NonNull`.)
     type Handle: Copy + Ord + Hash + Unpin + Send + Sync;
 
+    /// Whether allocating zero-sized memory handles is free.
+    ///
+    /// If this is false, e.g. because even an empty handle takes a block of a
+    /// pool, collections should defer allocating until they need memory,
+    /// rather than eagerly allocating an empty handle.
+    const EMPTY_LAYOUT_IS_FREE: bool = true;
+
+    /// Whether [`deallocate`](Self::deallocate) needs the layout the handle
+    /// was allocated with.
+    ///
+    /// If this is false, e.g. because the storage only has a single memory
+    /// handle, callers which don't otherwise keep track of the layout may
+    /// deallocate with an empty layout (`Layout::new::<()>()`) instead.
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = true;
+
     /// Allocate memory handle in this storage.
     ///
     /// The handled memory is not initialized. Any existing handles are
@@ -25,6 +41,44 @@ pub unsafe trait Storage {
     /// (Do we want an `allocate_zeroed`?)
     fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError>;
 
+    /// Allocate memory handle in this storage, also returning the layout the
+    /// storage actually reserved.
+    ///
+    /// The storage may round up the allocation, e.g. to an allocator's size
+    /// class, but the returned layout has the same alignment as `layout`. The
+    /// handle may then be used with any layout of that alignment and a size
+    /// between `layout.size()` and the returned size.
+    fn allocate_with_excess(
+        &mut self,
+        layout: Layout,
+    ) -> Result<(Self::Handle, Layout), AllocError> {
+        self.allocate(layout).map(|handle| (handle, layout))
+    }
+
+    /// Allocate a zero-sized memory handle with the given alignment.
+    ///
+    /// This is equivalent to allocating a zero-sized layout, and the handle is
+    /// used with that layout. Storages which need a resource (e.g. a pool
+    /// block) for a normal allocation can return a cheap dangling handle here,
+    /// so empty collections don't use up the storage. Fails if `align` is not
+    /// a power of two.
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        let layout = Layout::from_size_align(0, align).map_err(|_| AllocError)?;
+        self.allocate(layout)
+    }
+
+    /// The memory handle which is currently allocated, for storages with a
+    /// single memory handle which know whether it is allocated.
+    ///
+    /// Returns `None` if no handle is allocated, or if the storage doesn't
+    /// know, which is the default. The built-in single storages, such as
+    /// [`InlineStorage`](crate::InlineStorage), are only their memory, so they
+    /// don't know, and always return `None`; wrappers such as
+    /// [`InlineOrBorrowed`](crate::InlineOrBorrowed) forward it.
+    fn current_handle(&self) -> Option<Self::Handle> {
+        None
+    }
+
     /// Deallocate an object handle in this storage.
     ///
     /// The handled memory is not required to be valid in any way. The handle is
@@ -34,11 +88,16 @@ pub unsafe trait Storage {
     ///
     /// - The handle must have been created by this storage, and must not have
     ///   been invalidated.
-    /// - The layout must be the same as used to allocate the handle.
+    /// - The layout must be the same as used to allocate the handle, or empty
+    ///   if [`NEEDS_LAYOUT_FOR_DEALLOC`](Self::NEEDS_LAYOUT_FOR_DEALLOC) is
+    ///   false.
     unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout);
 
     /// Resolve a memory handle in this storage to a reference.
     ///
+    /// Zero-sized handles must resolve to a valid, suitably aligned empty slice
+    /// without panicking, like any other handle.
+    ///
     /// # Safety
     ///
     /// - The handle must have been created by this storage, and must not have
@@ -55,6 +114,23 @@ pub unsafe trait Storage {
     /// - The layout must be the same as used to allocate the handle.
     unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory;
 
+    /// Resolve memory handles in this storage to references.
+    ///
+    /// Unlike [`MultipleStorage::resolve_many_mut`], the same handle may be
+    /// resolved multiple times in a single call.
+    ///
+    /// # Safety
+    ///
+    /// - The handles must have been created by this storage, and must not have
+    ///   been invalidated.
+    /// - The layout must be the same as used to allocate the handle.
+    unsafe fn resolve_many<const N: usize>(
+        &self,
+        handles: [(Self::Handle, Layout); N],
+    ) -> [&Memory; N] {
+        handles.map(|(handle, layout)| self.resolve(handle, layout))
+    }
+
     /// Grow a memory handle to a larger size.
     ///
     /// If this function succeeds, then the old handle is invalidated and the
@@ -75,6 +151,9 @@ pub unsafe trait Storage {
     ///
     /// Note that `new_layout.align()` is not required to be the same as
     /// `old_layout.align()`
+    ///
+    /// If the layouts are equal, storages should return the same handle
+    /// without doing any work; the built-in storages all do.
     unsafe fn grow(
         &mut self,
         handle: Self::Handle,
@@ -99,12 +178,70 @@ pub unsafe trait Storage {
     ///
     /// Note that `new_layout.align()` is not required to be the same as
     /// `old_layout.align()`
+    ///
+    /// As with [`grow`](Self::grow), equal layouts should return the same
+    /// handle without doing any work.
     unsafe fn shrink(
         &mut self,
         handle: Self::Handle,
         old_layout: Layout,
         new_layout: Layout,
     ) -> Result<Self::Handle, AllocError>;
+
+    /// Resize a memory handle to a new layout, larger or smaller.
+    ///
+    /// This [`grow`](Self::grow)s or [`shrink`](Self::shrink)s depending on
+    /// the sizes of the layouts. If the layouts are equal, the storage isn't
+    /// touched and the same handle is returned. (Equal sizes with different
+    /// alignments grow, as that is allowed to change the alignment.)
+    ///
+    /// # Safety
+    ///
+    /// - The handle must have been created by this storage, and must not have
+    ///   been invalidated.
+    /// - `old_layout` must be the same as used to allocate the handle.
+    unsafe fn realloc(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        if new_layout == old_layout {
+            Ok(handle)
+        } else if new_layout.size() >= old_layout.size() {
+            self.grow(handle, old_layout, new_layout)
+        } else {
+            self.shrink(handle, old_layout, new_layout)
+        }
+    }
+}
+
+/// Types which can resolve memory handles to read-only memory.
+///
+/// This is the subset of [`Storage`] which doesn't need to allocate or mutate
+/// memory, e.g. to read an immutable region such as flash or a read-only
+/// memory map through [`RawRef`](crate::RawRef). Every storage is a read
+/// storage, resolving as with [`Storage::resolve`].
+pub unsafe trait ReadStorage {
+    /// The handle which is used to access the stored memory.
+    type Handle: Copy + Ord + Hash + Unpin + Send + Sync;
+
+    /// Resolve a memory handle in this storage to a reference.
+    ///
+    /// # Safety
+    ///
+    /// - The handle must have been created by this storage, and must not have
+    ///   been invalidated.
+    /// - The layout must be the same as used to create the handle.
+    unsafe fn resolve_read(&self, handle: Self::Handle, layout: Layout) -> &Memory;
+}
+
+unsafe impl<S: Storage> ReadStorage for S {
+    type Handle = S::Handle;
+
+    unsafe fn resolve_read(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.resolve(handle, layout)
+    }
 }
 
 /// A storage that allocates pinned memory handles.
@@ -116,6 +253,24 @@ pub unsafe trait Storage {
 /// [`deallocate`]: Storage::deallocate
 pub unsafe trait PinningStorage: Storage {}
 
+/// A storage that can always allocate zero-sized memory handles.
+///
+/// [`allocate`] and [`allocate_empty`] never fail when given a layout with a
+/// size of zero, whatever its alignment. This allows creating empty
+/// collections without having to handle allocation failure.
+///
+/// [`allocate`]: Storage::allocate
+/// [`allocate_empty`]: Storage::allocate_empty
+pub unsafe trait InfallibleEmptyStorage: Storage {}
+
+/// A storage whose clones all manage the same memory handles.
+///
+/// A handle allocated by one clone of the storage can be resolved and
+/// deallocated by any other clone, as is the case for allocators. This allows
+/// shared ownership types such as [`Rc`](crate::Rc) to hold a clone of the
+/// storage in every owner.
+pub unsafe trait CloneStorage: Storage + Clone {}
+
 /// A storage that can manage multiple memory handles.
 ///
 /// [`allocate`] no longer invalidates existing handles. You can `allocate`
@@ -127,6 +282,10 @@ pub unsafe trait PinningStorage: Storage {}
 /// well as creating a new handle and copying over the old contents. (This is
 /// provided by default impl.)
 ///
+/// Wrapper storages, such as [`CountingStorage`](crate::CountingStorage),
+/// can't forward this trait from the storage they wrap: the conditional impl
+/// would overlap with the blanket `Storage` impl for `MultipleStorage`.
+///
 /// [`allocate`]: Storage::allocate
 /// [`resolve`]: Storage::resolve
 /// [`resolve_mut`]: Storage::resolve_mut
@@ -147,6 +306,18 @@ pub unsafe trait MultipleStorage: Storage {
     ) -> [&mut Memory; N];
 }
 
+/// A storage that can deallocate all of its memory handles at once.
+///
+/// This is useful for arena and bump storages, where deallocating a single
+/// handle does nothing, so a collection can free everything in one call.
+pub unsafe trait ResettableStorage: Storage {
+    /// Deallocate all memory handles in this storage.
+    ///
+    /// All existing handles are invalidated, and all space is reclaimed. It is
+    /// a logic error to resolve any handle allocated before the reset.
+    fn reset(&mut self);
+}
+
 /// A storage that serves as a uniqueness barrier.
 ///
 /// Notably, this means that this storage can go `&Storage -> &mut Memory`, and
@@ -165,8 +336,86 @@ pub unsafe trait SharedMutabilityStorage: Storage {
     ///   been invalidated.
     /// - The layout must be the same as used to allocate the handle.
     unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory;
+
+    /// Grow a memory handle by creating a new handle and copying over the
+    /// old contents.
+    ///
+    /// This is the same as the default [`grow`] for multiple storages, made
+    /// available for implementing [`grow`] on storages which aren't
+    /// [`MultipleStorage`].
+    ///
+    /// # Safety
+    ///
+    /// - The requirements of [`grow`] must hold.
+    /// - Allocating a new handle must not invalidate the old handle.
+    ///
+    /// [`grow`]: Storage::grow
+    unsafe fn grow_by_copy(
+        &mut self,
+        old_handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "invalid arguments to Storage::grow",
+        );
+        if new_layout == old_layout {
+            return Ok(old_handle);
+        }
+
+        let new_handle = self.allocate(new_layout)?;
+        copy_nonoverlapping(
+            self.resolve_raw(old_handle, old_layout).as_ptr(),
+            self.resolve_raw(new_handle, new_layout).as_mut_ptr(),
+            old_layout.size(),
+        );
+
+        self.deallocate(old_handle, old_layout);
+        Ok(new_handle)
+    }
+
+    /// Shrink a memory handle by creating a new handle and copying over the
+    /// prefix of the old contents.
+    ///
+    /// This is the same as the default [`shrink`] for multiple storages, made
+    /// available for implementing [`shrink`] on storages which aren't
+    /// [`MultipleStorage`].
+    ///
+    /// # Safety
+    ///
+    /// - The requirements of [`shrink`] must hold.
+    /// - Allocating a new handle must not invalidate the old handle.
+    ///
+    /// [`shrink`]: Storage::shrink
+    unsafe fn shrink_by_copy(
+        &mut self,
+        old_handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "invalid arguments to Storage::shrink",
+        );
+        if new_layout == old_layout {
+            return Ok(old_handle);
+        }
+
+        let new_handle = self.allocate(new_layout)?;
+        copy_nonoverlapping(
+            self.resolve_raw(old_handle, old_layout).as_ptr(),
+            self.resolve_raw(new_handle, new_layout).as_mut_ptr(),
+            new_layout.size(),
+        );
+
+        self.deallocate(old_handle, old_layout);
+        Ok(new_handle)
+    }
 }
 
+// A second blanket impl for SharedMutabilityStorage would overlap with this
+// one, so such storages use SharedMutabilityStorage::grow_by_copy instead.
 default unsafe impl<S> Storage for S
 where
     S: MultipleStorage,
@@ -181,6 +430,9 @@ where
             new_layout.size() >= old_layout.size(),
             "invalid arguments to Storage::grow",
         );
+        if new_layout == old_layout {
+            return Ok(old_handle);
+        }
 
         let new_handle: Self::Handle = self.allocate(new_layout)?;
         let [new_ptr, old_ptr] =
@@ -206,6 +458,9 @@ where
             new_layout.size() <= old_layout.size(),
             "invalid arguments to Storage::shrink",
         );
+        if new_layout == old_layout {
+            return Ok(old_handle);
+        }
 
         let new_handle: Self::Handle = self.allocate(new_layout)?;
         let [new_ptr, old_ptr] =
@@ -221,3 +476,95 @@ where
         Ok(new_handle)
     }
 }
+
+/// A pinned storage is the storage itself.
+///
+/// Pinning the storage (e.g. because a value refers to its inline memory) does
+/// not pin the memory handles; that is what [`PinningStorage`] promises. A
+/// pinned storage is a `PinningStorage` exactly when the storage is.
+///
+/// This is only implemented for multiple storages, as an impl for any storage
+/// would overlap with the blanket Storage impl for MultipleStorage (`Pin` is
+/// not a fundamental type, so downstream crates could implement it).
+unsafe impl<S: MultipleStorage + Unpin> Storage for Pin<&mut S> {
+    type Handle = S::Handle;
+
+    const EMPTY_LAYOUT_IS_FREE: bool = S::EMPTY_LAYOUT_IS_FREE;
+    const NEEDS_LAYOUT_FOR_DEALLOC: bool = S::NEEDS_LAYOUT_FOR_DEALLOC;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        (**self).allocate(layout)
+    }
+
+    fn allocate_with_excess(
+        &mut self,
+        layout: Layout,
+    ) -> Result<(Self::Handle, Layout), AllocError> {
+        (**self).allocate_with_excess(layout)
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        (**self).allocate_empty(align)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        (**self).deallocate(handle, layout)
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        (**self).resolve(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        (**self).resolve_mut(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        (**self).grow(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        (**self).shrink(handle, old_layout, new_layout)
+    }
+}
+
+unsafe impl<S: MultipleStorage + Unpin> MultipleStorage for Pin<&mut S> {
+    unsafe fn resolve_many_mut<const N: usize>(
+        &mut self,
+        handles: [(Self::Handle, Layout); N],
+    ) -> [&mut Memory; N] {
+        (**self).resolve_many_mut(handles)
+    }
+}
+
+unsafe impl<S> SharedMutabilityStorage for Pin<&mut S>
+where
+    S: MultipleStorage + SharedMutabilityStorage + Unpin,
+{
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        (**self).resolve_raw(handle, layout)
+    }
+}
+
+unsafe impl<S: MultipleStorage + PinningStorage + Unpin> PinningStorage for Pin<&mut S> {}
+
+unsafe impl<S> InfallibleEmptyStorage for Pin<&mut S> where
+    S: MultipleStorage + InfallibleEmptyStorage + Unpin
+{
+}
+
+unsafe impl<S: MultipleStorage + ResettableStorage + Unpin> ResettableStorage for Pin<&mut S> {
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
@@ -0,0 +1,161 @@
+use {
+    crate::{polyfill::handle_alloc_error, MultipleStorage},
+    core::{alloc::Layout, cmp::Ordering, marker::PhantomData, mem},
+};
+
+/// A node of a [`TreeMap`], stored in the storage.
+struct Node<K, V, H> {
+    key: K,
+    value: V,
+    left: Option<H>,
+    right: Option<H>,
+}
+
+/// An ordered map as an (unbalanced) binary search tree.
+///
+/// Each entry is a node allocated in the storage, linked to its children by
+/// handle. The node type is internal: the storage only sees node layouts, so
+/// the user picks `S` without naming what it stores.
+pub struct TreeMap<K, V, S: MultipleStorage> {
+    root: Option<S::Handle>,
+    len: usize,
+    storage: S,
+    marker: PhantomData<Node<K, V, S::Handle>>,
+}
+
+const fn node_layout<K, V, H>() -> Layout {
+    Layout::new::<Node<K, V, H>>()
+}
+
+impl<K: Ord, V, S: MultipleStorage> TreeMap<K, V, S> {
+    pub fn new_in(storage: S) -> Self {
+        Self {
+            root: None,
+            len: 0,
+            storage,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// # Safety
+    ///
+    /// - The handle must be a node of this tree.
+    unsafe fn node(&self, handle: S::Handle) -> &Node<K, V, S::Handle> {
+        let memory = self
+            .storage
+            .resolve(handle, node_layout::<K, V, S::Handle>());
+        &*(memory.as_ptr() as *const Node<K, V, S::Handle>)
+    }
+
+    /// # Safety
+    ///
+    /// - The handle must be a node of this tree.
+    unsafe fn node_mut(&mut self, handle: S::Handle) -> &mut Node<K, V, S::Handle> {
+        let memory = self
+            .storage
+            .resolve_mut(handle, node_layout::<K, V, S::Handle>());
+        &mut *memory.as_mut_ptr().cast::<Node<K, V, S::Handle>>()
+    }
+
+    /// Find the node for a key.
+    fn find(&self, key: &K) -> Option<S::Handle> {
+        let mut next = self.root;
+        while let Some(handle) = next {
+            let node = unsafe { self.node(handle) };
+            next = match key.cmp(&node.key) {
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+                Ordering::Equal => return Some(handle),
+            };
+        }
+        None
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let handle = self.find(key)?;
+        Some(unsafe { &self.node(handle).value })
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let handle = self.find(key)?;
+        Some(unsafe { &mut self.node_mut(handle).value })
+    }
+
+    /// Insert a value, returning the previous value for the key.
+    #[track_caller]
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        // The parent of the new node, and which side it goes on.
+        let mut parent = None;
+        let mut next = self.root;
+        while let Some(handle) = next {
+            let node = unsafe { self.node_mut(handle) };
+            let ordering = key.cmp(&node.key);
+            next = match ordering {
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+                Ordering::Equal => return Some(mem::replace(&mut node.value, value)),
+            };
+            parent = Some((handle, ordering));
+        }
+
+        let layout = node_layout::<K, V, S::Handle>();
+        let handle = match self.storage.allocate(layout) {
+            Ok(handle) => handle,
+            Err(_) => handle_alloc_error(layout),
+        };
+        unsafe {
+            let memory = self.storage.resolve_mut(handle, layout);
+            memory
+                .as_mut_ptr()
+                .cast::<Node<K, V, S::Handle>>()
+                .write(Node {
+                    key,
+                    value,
+                    left: None,
+                    right: None,
+                });
+        }
+
+        // Link the node only after it's written, so the tree is always valid.
+        match parent {
+            None => self.root = Some(handle),
+            Some((parent, Ordering::Less)) => unsafe { self.node_mut(parent).left = Some(handle) },
+            Some((parent, _)) => unsafe { self.node_mut(parent).right = Some(handle) },
+        }
+        self.len += 1;
+        None
+    }
+}
+
+impl<K, V, S: MultipleStorage> Drop for TreeMap<K, V, S> {
+    fn drop(&mut self) {
+        // Free the nodes in order, rotating left children up to the top so
+        // that no stack is needed to remember the way back.
+        let layout = node_layout::<K, V, S::Handle>();
+        let mut next = self.root.take();
+        while let Some(handle) = next {
+            unsafe {
+                let memory = self.storage.resolve_mut(handle, layout);
+                let node = memory.as_mut_ptr().cast::<Node<K, V, S::Handle>>();
+                if let Some(left) = (*node).left {
+                    let [node, left_node] = self
+                        .storage
+                        .resolve_many_mut([(handle, layout), (left, layout)]);
+                    let node = &mut *node.as_mut_ptr().cast::<Node<K, V, S::Handle>>();
+                    let left_node = &mut *left_node.as_mut_ptr().cast::<Node<K, V, S::Handle>>();
+                    node.left = left_node.right;
+                    left_node.right = Some(handle);
+                    next = Some(left);
+                } else {
+                    let node = node.read();
+                    self.storage.deallocate(handle, layout);
+                    next = node.right;
+                }
+            }
+        }
+    }
+}
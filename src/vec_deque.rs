@@ -0,0 +1,152 @@
+use {
+    crate::{polyfill::handle_alloc_error, InfallibleEmptyStorage, RawVec, Storage},
+    core::{alloc::Layout, mem::MaybeUninit, ptr},
+};
+
+/// A double-ended queue as a ring buffer. A tiny subset of std's VecDeque.
+///
+/// The buffer is a [`RawVec`] whose length is always zero or a power of two,
+/// so indices wrap around with a mask. When it is full, it grows to double
+/// the length, and the elements which had wrapped around are moved so that
+/// they are contiguous again.
+pub struct VecDeque<T, S: Storage> {
+    raw: RawVec<T, S>,
+    /// The buffer index of the front element.
+    head: usize,
+    len: usize,
+}
+
+impl<T, S: Storage> VecDeque<T, S> {
+    pub fn new_in(storage: S) -> Self
+    where
+        S: InfallibleEmptyStorage,
+    {
+        Self {
+            raw: RawVec::new_in(storage),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The number of elements the deque can hold without growing.
+    pub fn capacity(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// The buffer index of the element at `index` from the front.
+    fn wrap(&self, index: usize) -> usize {
+        (self.head + index) & self.capacity().wrapping_sub(1)
+    }
+
+    /// Double the capacity, keeping the elements in order from `head`.
+    #[track_caller]
+    fn grow(&mut self) {
+        let old_cap = self.capacity();
+        let new_cap = if old_cap == 0 { 4 } else { old_cap * 2 };
+        if self.raw.grow_to(new_cap).is_err() {
+            handle_alloc_error(Layout::array::<T>(new_cap).unwrap_or(Layout::new::<T>()))
+        }
+
+        // The elements are `head..old_cap` followed by `0..wrapped`. If they
+        // wrapped, move the shorter part so they are contiguous (mod new_cap):
+        //
+        //   before:         [ 3 4 . 0 1 2 ]
+        //   move the tail:  [ . . . 0 1 2 3 4 . . . . ]
+        //   move the head:  [ 3 4 . . . . . . . 0 1 2 ]
+        if self.head + self.len > old_cap {
+            let head_len = old_cap - self.head;
+            let tail_len = self.len - head_len;
+            let buf = self.raw.as_mut().as_mut_ptr();
+            unsafe {
+                if tail_len < head_len {
+                    ptr::copy_nonoverlapping(buf, buf.add(old_cap), tail_len);
+                } else {
+                    let new_head = new_cap - head_len;
+                    ptr::copy_nonoverlapping(buf.add(self.head), buf.add(new_head), head_len);
+                    self.head = new_head;
+                }
+            }
+        }
+    }
+
+    #[track_caller]
+    pub fn push_back(&mut self, value: T) {
+        if self.len == self.capacity() {
+            self.grow();
+        }
+        let index = self.wrap(self.len);
+        self.raw.as_mut()[index].write(value);
+        self.len += 1;
+    }
+
+    #[track_caller]
+    pub fn push_front(&mut self, value: T) {
+        if self.len == self.capacity() {
+            self.grow();
+        }
+        self.head = self.wrap(self.capacity() - 1);
+        self.raw.as_mut()[self.head].write(value);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            let index = self.head;
+            self.head = self.wrap(1);
+            self.len -= 1;
+            Some(unsafe { self.raw.as_ref()[index].assume_init_read() })
+        }
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            let index = self.wrap(self.len);
+            Some(unsafe { self.raw.as_ref()[index].assume_init_read() })
+        }
+    }
+
+    /// Get the element at `index` from the front.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len {
+            Some(unsafe { self.raw.as_ref()[self.wrap(index)].assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    /// The elements, as the part from the front up to the end of the buffer
+    /// and the part which wrapped around to its start.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let buf = self.raw.as_ref();
+        let head_len = usize::min(self.len, buf.len() - self.head);
+        let (front, back) = (&buf[self.head..][..head_len], &buf[..self.len - head_len]);
+        unsafe {
+            (
+                &*(front as *const [MaybeUninit<T>] as *const [T]),
+                &*(back as *const [MaybeUninit<T>] as *const [T]),
+            )
+        }
+    }
+}
+
+unsafe impl<#[may_dangle] T, S: Storage> Drop for VecDeque<T, S> {
+    fn drop(&mut self) {
+        let (head, len) = (self.head, self.len);
+        let buf = self.raw.as_mut();
+        let head_len = usize::min(len, buf.len() - head);
+        let (back, front) = buf.split_at_mut(head);
+        unsafe {
+            ptr::drop_in_place(&mut front[..head_len] as *mut [MaybeUninit<T>] as *mut [T]);
+            ptr::drop_in_place(&mut back[..len - head_len] as *mut [MaybeUninit<T>] as *mut [T]);
+        }
+    }
+}
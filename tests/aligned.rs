@@ -0,0 +1,33 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::alloc::{Global, Layout},
+    storage_api::{AlignedStorage, AllocStorage, ByteInlineStorage, Storage},
+};
+
+#[test]
+fn raises_alignment() {
+    let mut storage = AlignedStorage::<_, 64>::new(AllocStorage::new(Global));
+    let mut layout = Layout::new::<u8>();
+    let mut handle = storage.allocate(layout).unwrap();
+    for size in [1, 100, 1000, 10] {
+        let new_layout = Layout::from_size_align(size, 1).unwrap();
+        handle = unsafe { storage.realloc(handle, layout, new_layout) }.unwrap();
+        layout = new_layout;
+        let memory = unsafe { storage.resolve(handle, layout) };
+        assert_eq!(memory.len(), size);
+        assert_eq!(memory.as_ptr() as usize % 64, 0);
+    }
+    unsafe { storage.deallocate(handle, layout) };
+}
+
+#[test]
+fn inner_storage_sees_alignment() {
+    // Byte inline storage can't hold anything aligned to more than 1.
+    let mut storage = AlignedStorage::<_, 2>::new(ByteInlineStorage::<8>::new());
+    assert!(storage.allocate(Layout::new::<u8>()).is_err());
+    let mut storage = AlignedStorage::<_, 1>::new(ByteInlineStorage::<8>::new());
+    assert!(storage.allocate(Layout::new::<u8>()).is_ok());
+}
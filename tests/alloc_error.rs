@@ -0,0 +1,54 @@
+#![cfg(debug_assertions)]
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Allocator, Layout},
+        panic::{self, Location},
+        ptr::NonNull,
+        sync::Mutex,
+    },
+    storage_api::{set_alloc_error_hook, AllocStorage, Box, Vec},
+};
+
+struct NullAlloc;
+
+unsafe impl Allocator for NullAlloc {
+    fn allocate(&self, _: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Err(AllocError)
+    }
+
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
+        unreachable!()
+    }
+}
+
+static FAILED: Mutex<Option<(Layout, &'static Location<'static>)>> = Mutex::new(None);
+
+/// Record the failed allocation, then unwind, as the alloc error handler
+/// would abort.
+fn record(layout: Layout, location: &'static Location<'static>) {
+    *FAILED.lock().unwrap() = Some((layout, location));
+    panic!("allocation failed");
+}
+
+#[test]
+fn reports_caller_location() {
+    set_alloc_error_hook(record);
+
+    let result = panic::catch_unwind(|| Box::new_in(0u32, AllocStorage::new(NullAlloc)));
+    let line = line!() - 1;
+    assert!(result.is_err());
+    let (layout, location) = FAILED.lock().unwrap().take().unwrap();
+    assert_eq!(layout, Layout::new::<u32>());
+    assert_eq!((location.file(), location.line()), (file!(), line));
+
+    let mut vec = Vec::new_in(AllocStorage::new(NullAlloc));
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| vec.push(0u64)));
+    let line = line!() - 1;
+    assert!(result.is_err());
+    let (_, location) = FAILED.lock().unwrap().take().unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+}
@@ -0,0 +1,58 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::Global,
+        prelude::rust_2021::*,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    },
+    storage_api::{AllocStorage, Arc},
+};
+
+#[test]
+fn shared_across_threads() {
+    let arc = Arc::new_in(42u32, AllocStorage::new(Global));
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let arc = arc.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    let clone = arc.clone();
+                    assert_eq!(*clone, 42);
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(Arc::strong_count(&arc), 1);
+}
+
+#[test]
+fn drops_once() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountDrop;
+    impl Drop for CountDrop {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let arc = Arc::new_in(CountDrop, AllocStorage::new(Global));
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let arc = arc.clone();
+            thread::spawn(move || drop(arc))
+        })
+        .collect();
+    drop(arc);
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
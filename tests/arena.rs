@@ -0,0 +1,88 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{alloc::Layout, mem::MaybeUninit, pin::Pin},
+    storage_api::{
+        with_stack_storage, BorrowedArena, Box, BumpHandle, LinkedList, MultipleStorage,
+        ResettableStorage, Storage,
+    },
+};
+
+struct Node {
+    value: u32,
+    next: Option<BumpHandle>,
+}
+
+#[test]
+fn graph_in_scratch() {
+    let layout = Layout::new::<Node>();
+    // Miri's symbolic alignment check only accepts memory which is aligned by
+    // its type, not just by its address.
+    let mut scratch = [MaybeUninit::<u64>::uninit(); 32];
+    let scratch = unsafe {
+        &mut *(&mut scratch[..] as *mut [MaybeUninit<u64>] as *mut [MaybeUninit<u8>; 256])
+    };
+    let mut arena = BorrowedArena::new(scratch);
+
+    let mut next = None;
+    let handles = [(); 3].map(|()| arena.allocate(layout).unwrap());
+    for (value, &handle) in handles.iter().enumerate().rev() {
+        unsafe {
+            let memory = arena.resolve_mut(handle, layout);
+            assert_eq!(memory.as_ptr() as usize % layout.align(), 0);
+            memory.as_mut_ptr().cast::<Node>().write(Node {
+                value: value as u32,
+                next,
+            });
+        }
+        next = Some(handle);
+    }
+
+    let [a, b, c] = unsafe { arena.resolve_many_mut(handles.map(|handle| (handle, layout))) };
+    let [a, b, c] = [a, b, c].map(|memory| unsafe { &mut *memory.as_mut_ptr().cast::<Node>() });
+    assert_eq!((a.value, b.value, c.value), (0, 1, 2));
+    assert_eq!(
+        (a.next, b.next, c.next),
+        (Some(handles[1]), Some(handles[2]), None)
+    );
+    a.value += b.value + c.value;
+    assert_eq!(a.value, 3);
+
+    arena.reset();
+    assert_eq!(arena.used(), 0);
+    arena.allocate(Layout::new::<[u8; 256]>()).unwrap();
+    assert_eq!(arena.remaining(), 0);
+}
+
+#[test]
+fn aligns_addresses() {
+    let mut scratch = [MaybeUninit::<u64>::uninit(); 8];
+    let scratch = unsafe {
+        &mut *(&mut scratch[..] as *mut [MaybeUninit<u64>] as *mut [MaybeUninit<u8>; 64])
+    };
+    let base = scratch.as_ptr() as usize;
+    // Start the arena off of the alignment of the scratch memory.
+    let mut arena = BorrowedArena::new(&mut scratch[1..]);
+    let handle = arena.allocate(Layout::new::<u64>()).unwrap();
+    let memory = unsafe { arena.resolve(handle, Layout::new::<u64>()) };
+    assert_eq!(memory.as_ptr() as usize, base + 8);
+    assert!(arena.allocate(Layout::new::<[u64; 7]>()).is_err());
+}
+
+#[test]
+fn stack_storage() {
+    let sum = with_stack_storage::<256, _>(|mut arena| {
+        let boxed = Box::new_in([1u32, 2, 3], Pin::new(&mut arena));
+        let first = boxed[0];
+        drop(boxed);
+
+        let mut list = LinkedList::new_in(arena);
+        for i in 0..4 {
+            list.push_front(first + i);
+        }
+        list.iter().sum::<u32>()
+    });
+    assert_eq!(sum, 10);
+}
@@ -0,0 +1,340 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Global, Layout},
+        any::Any,
+        borrow::Borrow,
+        fmt::Debug,
+        mem::MaybeUninit,
+        prelude::rust_2021::*,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+    storage_api::{
+        alloc_box, inline_box, AllocStorage, Box, BudgetStorage, ByteInlineStorage,
+        CountingStorage, EitherStorage, FlexBox, InlineStorage, Memory, Storage,
+    },
+    unsize::{CoerceUnsize, Coercion},
+};
+
+/// A storage which can only hold zero-sized objects, and panics on resolve.
+struct NullStorage;
+
+unsafe impl Storage for NullStorage {
+    type Handle = ();
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if layout.size() == 0 {
+            Ok(())
+        } else {
+            Err(AllocError)
+        }
+    }
+
+    unsafe fn deallocate(&mut self, _: Self::Handle, _: Layout) {}
+
+    unsafe fn resolve(&self, _: Self::Handle, _: Layout) -> &Memory {
+        panic!("resolved a NullStorage handle")
+    }
+
+    unsafe fn resolve_mut(&mut self, _: Self::Handle, _: Layout) -> &mut Memory {
+        panic!("resolved a NullStorage handle")
+    }
+
+    unsafe fn grow(&mut self, _: (), _: Layout, _: Layout) -> Result<(), AllocError> {
+        Err(AllocError)
+    }
+
+    unsafe fn shrink(&mut self, _: (), _: Layout, _: Layout) -> Result<(), AllocError> {
+        Err(AllocError)
+    }
+}
+
+#[test]
+fn zst_does_not_resolve() {
+    let boxed = Box::new_in((), NullStorage);
+    drop(boxed);
+}
+
+#[test]
+fn zst_drops_once() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountDrop;
+    impl Drop for CountDrop {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let boxed = Box::new_in(CountDrop, AllocStorage::new(Global));
+    assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+    drop(boxed);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn replace() {
+    let mut boxed = Box::new_in(String::from("old"), AllocStorage::new(Global));
+    let old = boxed.replace(String::from("new"));
+    assert_eq!(old, "old");
+    assert_eq!(*boxed, "new");
+}
+
+#[test]
+fn helpers() {
+    let mut boxed = inline_box([1u8; 24]);
+    boxed[0] = 0;
+    assert_eq!(boxed[..2], [0, 1]);
+
+    let boxed = alloc_box(String::from("alloc"));
+    assert_eq!(*boxed, "alloc");
+}
+
+#[test]
+fn split_drop_and_deallocate() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountDrop;
+    impl Drop for CountDrop {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let boxed = Box::new_in(CountDrop, AllocStorage::new(Global));
+    let mut raw = Box::forget_contents(boxed);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+    unsafe { raw.drop_contents() };
+    assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+    drop(raw);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn as_ref_str() {
+    fn shout<S: AsRef<str>>(s: S) -> String {
+        s.as_ref().to_uppercase()
+    }
+
+    let bytes = Box::new_in(*b"hello", AllocStorage::new(Global));
+    let (handle, (), storage) = Box::into_raw_parts(bytes);
+    let boxed: Box<str, _> = unsafe { Box::from_raw_parts(handle, 5, storage) };
+    assert_eq!(Borrow::<str>::borrow(&boxed), "hello");
+    assert_eq!(shout(boxed), "HELLO");
+}
+
+#[test]
+fn from_str_in() {
+    let boxed = Box::from_str_in("hello", InlineStorage::<[u8; 16]>::new())
+        .unwrap_or_else(|_| panic!("\"hello\" should fit in 16 bytes"));
+    assert!(&*boxed == "hello");
+
+    let empty = Box::from_str_in("", AllocStorage::new(Global))
+        .ok()
+        .unwrap();
+    assert!(empty.is_empty());
+
+    let long = "a string longer than sixteen bytes";
+    assert!(Box::from_str_in(long, InlineStorage::<[u8; 16]>::new()).is_err());
+}
+
+#[test]
+fn map_same_layout() {
+    #[derive(Debug, PartialEq)]
+    struct Meters(u32);
+
+    let boxed = Box::new_in(5, CountingStorage::new(AllocStorage::new(Global)));
+    let boxed = Box::map(boxed, Meters);
+    assert_eq!(*boxed, Meters(5));
+    let (handle, (), mut storage) = Box::into_raw_parts(boxed);
+    assert_eq!(storage.allocations(), 1);
+    assert_eq!(storage.deallocations(), 0);
+    unsafe { storage.deallocate(handle, Layout::new::<Meters>()) };
+}
+
+#[test]
+fn map_different_layout() {
+    let boxed = Box::new_in(5u8, CountingStorage::new(AllocStorage::new(Global)));
+    let boxed = Box::map(boxed, |x| [u64::from(x); 4]);
+    assert_eq!(*boxed, [5; 4]);
+    let (handle, (), mut storage) = Box::into_raw_parts(boxed);
+    assert_eq!(storage.allocations(), 2);
+    assert_eq!(storage.deallocations(), 1);
+    assert_eq!(storage.live_bytes(), 32);
+    unsafe { storage.deallocate(handle, Layout::new::<[u64; 4]>()) };
+}
+
+#[test]
+fn byte_inline_storage() {
+    let boxed = Box::new_in([7u8; 40], ByteInlineStorage::<64>::new());
+    assert_eq!(*boxed, [7; 40]);
+
+    let storage = ByteInlineStorage::from_bytes([MaybeUninit::new(0); 2]);
+    assert!(!storage.fits(Layout::new::<[u8; 3]>()));
+    assert!(!storage.fits(Layout::new::<u16>()));
+    let boxed = Box::new_in([1u8, 2], storage);
+    let (_, (), storage) = Box::into_raw_parts(boxed);
+    let bytes = storage.into_bytes();
+    assert_eq!(
+        unsafe { [bytes[0].assume_init(), bytes[1].assume_init()] },
+        [1, 2]
+    );
+}
+
+#[test]
+fn try_pin_in() {
+    let storage = BudgetStorage::new(AllocStorage::new(Global), 4);
+    let pinned = Box::try_pin_in(7u32, storage).ok().unwrap();
+    assert_eq!(*pinned, 7);
+
+    let storage = BudgetStorage::new(AllocStorage::new(Global), 4);
+    match Box::try_pin_in(String::from("too big"), storage) {
+        Ok(_) => panic!("a String shouldn't fit in a 4 byte budget"),
+        Err((value, storage)) => {
+            assert_eq!(value, "too big");
+            assert_eq!(storage.live_bytes(), 0);
+        },
+    }
+}
+
+#[test]
+fn downcast() {
+    let boxed: Box<dyn Any, _> = alloc_box(String::from("known")).unsize(Coercion::to_any());
+    let boxed = boxed.downcast::<u32>().err().unwrap();
+    let mut string = unsafe { boxed.downcast_unchecked::<String>() };
+    string.push_str(" type");
+    assert_eq!(*string, "known type");
+}
+
+#[test]
+fn new_fn_in() {
+    let captured = 40;
+    let add = Box::new_fn_in(move || captured + 2, InlineStorage::<i32>::new());
+    assert_eq!(add(), 42);
+    assert_eq!((*add)(), 42);
+}
+
+#[test]
+fn new_unsized() {
+    let boxed = Box::<dyn Debug, _>::new_unsized(7u32, AllocStorage::new(Global));
+    assert_eq!(format!("{:?}", &*boxed), "7");
+}
+
+#[test]
+fn outlives_borrow() {
+    // Dropping a borrow doesn't use it, so the box may outlive the value.
+    let mut boxed = Box::new_in("", AllocStorage::new(Global));
+    let value = String::from("borrowed");
+    *boxed = &value;
+    assert_eq!(*boxed, "borrowed");
+}
+
+#[test]
+fn clone_slice() {
+    let strings = [String::from("deep"), String::from("copy")];
+    let boxed = Box::<[String], _>::new_unsized(strings, AllocStorage::new(Global));
+    let mut cloned = boxed.clone();
+    assert_eq!(*cloned, *boxed);
+    assert_ne!(cloned.as_ptr(), boxed.as_ptr());
+    for (a, b) in cloned.iter().zip(boxed.iter()) {
+        assert_ne!(a.as_ptr(), b.as_ptr());
+    }
+
+    cloned[0].push_str("er");
+    assert_eq!(*boxed, ["deep", "copy"]);
+    assert_eq!(*cloned, ["deeper", "copy"]);
+
+    let empty = Box::<[String], _>::new_unsized([], AllocStorage::new(Global));
+    let empty_clone = empty.clone();
+    // The clone owns its own storage, so it outlives the original.
+    drop(empty);
+    assert_eq!(empty_clone.len(), 0);
+}
+
+#[test]
+fn clone_slice_panic() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct PanicOnThirdClone(usize);
+    impl Clone for PanicOnThirdClone {
+        fn clone(&self) -> Self {
+            assert_ne!(self.0, 2, "third clone");
+            Self(self.0)
+        }
+    }
+    impl Drop for PanicOnThirdClone {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let values = [0, 1, 2, 3].map(PanicOnThirdClone);
+    let boxed = Box::<[_], _>::new_unsized(values, AllocStorage::new(Global));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| boxed.clone()));
+    assert!(result.is_err());
+    assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+    drop(boxed);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 6);
+}
+
+static CONST_BOXED: Box<u32, InlineStorage<u32>> = Box::const_new_in(5, InlineStorage::new());
+
+#[test]
+fn const_new_in() {
+    assert_eq!(*CONST_BOXED, 5);
+    const SLOT: Box<[u8; 3], InlineStorage<[u8; 3]>> =
+        Box::const_new_in(*b"abc", InlineStorage::new());
+    let mut boxed = SLOT;
+    boxed[0] = b'x';
+    assert_eq!(*boxed, *b"xbc");
+    assert_eq!(*SLOT, *b"abc");
+}
+
+#[test]
+fn flex_box() {
+    // NullStorage can't allocate a u32, so the small value must be inline.
+    let mut small = FlexBox::<u32, [u32; 4], _>::new_in(5, NullStorage);
+    assert!(FlexBox::is_inline(&small));
+    *small += 1;
+    assert_eq!(*small, 6);
+
+    let large = FlexBox::<[u32; 8], [u32; 4], _>::new_in(
+        [7; 8],
+        CountingStorage::new(AllocStorage::new(Global)),
+    );
+    assert!(!FlexBox::is_inline(&large));
+    assert_eq!(*large, [7; 8]);
+    let (handle, (), mut storage) = Box::into_raw_parts(FlexBox::into_box(large));
+    match &storage {
+        EitherStorage::Right(counting) => assert_eq!(counting.allocations(), 1),
+        EitherStorage::Left(_) => panic!("large value stored inline"),
+    }
+    unsafe { storage.deallocate(handle, Layout::new::<[u32; 8]>()) };
+
+    let global = FlexBox::<[u64; 4], [u64; 2], _>::new([1; 4]);
+    assert!(!FlexBox::is_inline(&global));
+}
+
+#[test]
+fn replace_storage() {
+    let inline = Box::new_in(*b"data", InlineStorage::<[u8; 4]>::new());
+    let heap = inline
+        .replace_storage(AllocStorage::new(Global))
+        .ok()
+        .unwrap();
+    assert_eq!(*heap, *b"data");
+
+    let slice = Box::<[u16], _>::new_unsized([1, 2, 3], AllocStorage::new(Global));
+    let (slice, _) = slice
+        .replace_storage(InlineStorage::<[u16; 2]>::new())
+        .err()
+        .unwrap();
+    let slice = slice
+        .replace_storage(InlineStorage::<[u16; 3]>::new())
+        .ok()
+        .unwrap();
+    assert_eq!(*slice, [1, 2, 3]);
+}
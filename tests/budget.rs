@@ -0,0 +1,37 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::alloc::{Global, Layout},
+    storage_api::{AllocStorage, BudgetStorage, RawVec, Storage},
+};
+
+#[test]
+fn exact_threshold() {
+    let mut storage = BudgetStorage::new(AllocStorage::new(Global), 100);
+    let hundred = Layout::array::<u8>(100).unwrap();
+    let one = Layout::new::<u8>();
+
+    let handle = storage.allocate(hundred).unwrap();
+    assert_eq!(storage.remaining(), 0);
+    assert!(storage.allocate(one).is_err());
+    assert!(unsafe { storage.grow(handle, hundred, Layout::array::<u8>(101).unwrap()) }.is_err());
+    assert_eq!(storage.live_bytes(), 100);
+
+    let fifty = Layout::array::<u8>(50).unwrap();
+    let handle = unsafe { storage.shrink(handle, hundred, fifty) }.unwrap();
+    assert_eq!(storage.remaining(), 50);
+    unsafe { storage.deallocate(handle, fifty) };
+    assert_eq!(storage.live_bytes(), 0);
+    let handle = storage.allocate(hundred).unwrap();
+    unsafe { storage.deallocate(handle, hundred) };
+}
+
+#[test]
+fn vec_degrades_gracefully() {
+    let mut vec = RawVec::<u8, _>::new_in(BudgetStorage::new(AllocStorage::new(Global), 100));
+    assert!(vec.grow_to(100).is_ok());
+    assert!(vec.grow_to(101).is_err());
+    assert_eq!(vec.len(), 100);
+}
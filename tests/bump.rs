@@ -0,0 +1,72 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{alloc::Layout, pin::Pin},
+    storage_api::{Box, BumpStorage, ResettableStorage, Storage},
+};
+
+#[test]
+fn reset_reclaims_capacity() {
+    let layout = Layout::new::<u64>();
+    let mut storage = BumpStorage::<[u64; 10]>::new();
+    let handles = [(); 10].map(|()| storage.allocate(layout).unwrap());
+    for (i, &handle) in handles.iter().enumerate() {
+        unsafe {
+            storage
+                .resolve_mut(handle, layout)
+                .as_mut_ptr()
+                .cast::<u64>()
+                .write(i as u64);
+        }
+    }
+    for (i, &handle) in handles.iter().enumerate() {
+        let value = unsafe {
+            storage
+                .resolve(handle, layout)
+                .as_ptr()
+                .cast::<u64>()
+                .read()
+        };
+        assert_eq!(value, i as u64);
+    }
+    assert_eq!(storage.remaining(), 0);
+    assert!(storage.allocate(layout).is_err());
+    let start = unsafe { storage.resolve(handles[0], layout) }.as_ptr();
+
+    storage.reset();
+    assert_eq!(storage.used(), 0);
+    let layout = Layout::new::<[u64; 10]>();
+    let handle = storage.allocate(layout).unwrap();
+    assert_eq!(unsafe { storage.resolve(handle, layout) }.as_ptr(), start);
+    assert_eq!(storage.remaining(), 0);
+}
+
+#[test]
+fn over_aligned() {
+    let mut storage = BumpStorage::<[u16; 8]>::new();
+    let byte = storage.allocate(Layout::new::<u8>()).unwrap();
+    let byte = unsafe { storage.resolve(byte, Layout::new::<u8>()) }.as_ptr();
+    let short = storage.allocate(Layout::new::<u16>()).unwrap();
+    let short = unsafe { storage.resolve(short, Layout::new::<u16>()) }.as_ptr();
+    assert_eq!(short as usize, byte as usize + 2);
+    assert!(storage.allocate(Layout::new::<u32>()).is_err());
+}
+
+#[test]
+fn pinned() {
+    let mut storage = BumpStorage::<[u64; 4]>::new();
+    let mut pinned = Pin::new(&mut storage);
+    pinned.allocate(Layout::new::<u64>()).unwrap();
+    assert_eq!(pinned.used(), 8);
+
+    let boxed = Box::new_in([1u64, 2], pinned);
+    assert_eq!(*boxed, [1, 2]);
+    drop(boxed);
+    assert_eq!(storage.used(), 24);
+
+    let mut pinned = Pin::new(&mut storage);
+    pinned.reset();
+    assert_eq!(storage.remaining(), 32);
+}
@@ -0,0 +1,134 @@
+#![cfg(feature = "checked")]
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{Global, Layout},
+        mem::MaybeUninit,
+    },
+    storage_api::{
+        AllocStorage, BorrowedArena, BorrowedStorage, BumpStorage, ConstPoolStorage, InlineStorage,
+        MultipleStorage, ResettableStorage, Storage,
+    },
+};
+
+#[test]
+#[should_panic = "InlineStorage resolved an invalidated handle"]
+fn inline_stale_handle() {
+    let layout = Layout::new::<u32>();
+    let mut storage = InlineStorage::<u32>::new();
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        storage.deallocate(handle, layout);
+        storage.resolve(handle, layout);
+    }
+}
+
+#[test]
+#[should_panic = "InlineStorage resolved an invalidated handle"]
+fn inline_reallocated_stale_handle() {
+    let layout = Layout::new::<u32>();
+    let mut storage = InlineStorage::<u32>::new();
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        storage.deallocate(handle, layout);
+        storage.allocate(layout).unwrap();
+        storage.resolve(handle, layout);
+    }
+}
+
+#[test]
+#[should_panic = "BorrowedStorage resolved an invalidated handle"]
+fn borrowed_stale_handle() {
+    let layout = Layout::new::<u32>();
+    let mut memory = MaybeUninit::<u32>::uninit();
+    let mut storage = BorrowedStorage::new(&mut memory);
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        storage.deallocate(handle, layout);
+        storage.resolve_mut(handle, layout);
+    }
+}
+
+#[test]
+#[should_panic = "BumpStorage resolved an invalidated handle"]
+fn bump_stale_handle() {
+    let layout = Layout::new::<u32>();
+    let mut storage = BumpStorage::<[u32; 4]>::new();
+    let handle = storage.allocate(layout).unwrap();
+    storage.reset();
+    unsafe { storage.resolve(handle, layout) };
+}
+
+#[test]
+#[should_panic = "BorrowedArena resolved an invalidated handle"]
+fn arena_stale_handle() {
+    let layout = Layout::new::<u32>();
+    let mut memory = [MaybeUninit::uninit(); 16];
+    let mut storage = BorrowedArena::new(&mut memory);
+    let handle = storage.allocate(layout).unwrap();
+    storage.reset();
+    unsafe { storage.resolve_mut(handle, layout) };
+}
+
+#[test]
+#[should_panic = "BumpStorage resolved an invalidated handle"]
+fn bump_reallocated_stale_handle() {
+    let layout = Layout::new::<u32>();
+    let mut storage = BumpStorage::<[u32; 4]>::new();
+    let handle = storage.allocate(layout).unwrap();
+    storage.reset();
+    storage.allocate(layout).unwrap();
+    unsafe { storage.resolve(handle, layout) };
+}
+
+#[test]
+#[should_panic = "ConstPoolStorage resolved an invalidated handle"]
+fn pool_reallocated_stale_handle() {
+    let layout = Layout::new::<u32>();
+    let mut storage = ConstPoolStorage::<u32, 1>::new();
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        storage.deallocate(handle, layout);
+        storage.allocate(layout).unwrap();
+        storage.resolve(handle, layout);
+    }
+}
+
+#[test]
+#[should_panic = "AllocStorage resolved an invalidated handle"]
+fn alloc_stale_handle() {
+    let layout = Layout::new::<u32>();
+    let mut storage = AllocStorage::new(Global);
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        storage.deallocate(handle, layout);
+        // The allocator may well reuse the address.
+        let _reused = storage.allocate(layout).unwrap();
+        storage.resolve(handle, layout);
+    }
+}
+
+#[test]
+fn live_handles_resolve() {
+    let layout = Layout::new::<u32>();
+    let mut storage = BumpStorage::<[u32; 4]>::new();
+    storage.allocate(layout).unwrap();
+    storage.reset();
+    let handle = storage.allocate(layout).unwrap();
+    let other = storage.allocate(layout).unwrap();
+    unsafe {
+        storage.resolve(handle, layout);
+        storage.resolve_many_mut([(handle, layout), (other, layout)]);
+    }
+
+    let mut storage = AllocStorage::new(Global);
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        let handle = storage.grow(handle, layout, Layout::new::<u64>()).unwrap();
+        storage.resolve(handle, Layout::new::<u64>());
+        storage.deallocate(handle, Layout::new::<u64>());
+    }
+}
@@ -0,0 +1,71 @@
+#![feature(allocator_api, slice_ptr_len)]
+
+//! Functions to compare the code generated for resolving boxes against std's
+//! `Box`. None of them should have any overhead: for `AllocStorage`, getting
+//! the pointer of a `RawBox` compiles to the same code as for std's `Box` (at
+//! most a single load), with no branches.
+//!
+//! To check, look at the assembly of these functions with e.g.
+//!
+//! ```text
+//! cargo rustc --release --test codegen -- --emit asm -C "llvm-args=-x86-asm-syntax=intel"
+//! ```
+//!
+//! and find `codegen::raw_box_as_ptr` etc. in the `.s` file under
+//! `target/release/deps`. (With the `checked` feature, resolving also checks
+//! the handle, so there is overhead by design.)
+//!
+//! This is a manual check: the test below only checks that the functions give
+//! the right pointers, and still passes if their code regresses.
+
+extern crate std;
+
+use {
+    std::{alloc::Global, hint::black_box},
+    storage_api::{AllocStorage, Box, RawBox},
+};
+
+type Storage = AllocStorage<Global>;
+
+#[inline(never)]
+pub fn std_box_as_ptr(boxed: &std::boxed::Box<u64>) -> *const u64 {
+    &**boxed
+}
+
+#[inline(never)]
+pub fn raw_box_as_ptr(boxed: &RawBox<u64, Storage>) -> *const u64 {
+    boxed.as_ptr()
+}
+
+#[inline(never)]
+pub fn raw_box_as_mut_ptr(boxed: &mut RawBox<u64, Storage>) -> *mut u64 {
+    boxed.as_mut_ptr()
+}
+
+#[inline(never)]
+pub fn slice_box_as_ptr(boxed: &RawBox<[u64], Storage>) -> *const [u64] {
+    boxed.as_ptr()
+}
+
+#[inline(never)]
+pub fn box_deref(boxed: &Box<u64, Storage>) -> u64 {
+    **boxed
+}
+
+#[test]
+fn same_pointers() {
+    let std_box = std::boxed::Box::new(1u64);
+    assert_eq!(black_box(std_box_as_ptr)(&std_box), &*std_box as *const u64);
+
+    let boxed = Box::new_in(2u64, Storage::new(Global));
+    assert_eq!(black_box(box_deref)(&boxed), 2);
+    let mut raw = Box::forget_contents(boxed);
+    let ptr = black_box(raw_box_as_ptr)(&raw);
+    assert_eq!(black_box(raw_box_as_mut_ptr)(&mut raw), ptr as *mut u64);
+    unsafe { raw.drop_contents() };
+
+    let slice = RawBox::<[u64], _>::new_checked(3, Storage::new(Global))
+        .ok()
+        .unwrap();
+    assert_eq!(black_box(slice_box_as_ptr)(&slice).len(), 3);
+}
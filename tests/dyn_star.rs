@@ -6,7 +6,7 @@ use std::mem::ManuallyDrop;
 extern crate std;
 
 use {
-    std::{alloc::Global, fmt::Debug},
+    std::{alloc::Global, any::Any, fmt::Debug},
     storage_api::{AllocStorage, Box, DynStorage, InlineStorage},
     unsize::*,
 };
@@ -41,3 +41,19 @@ fn stolen_allocation() {
     debug_print(unsafe { Box::take(&mut string) });
     // Note: no leak here, as the box takes and drops the ManuallyDrop contents.
 }
+
+#[test]
+fn inline_round_trip() {
+    let number: Box<u16, _> = Box::new_in(42, INLINE);
+    let number: Box<dyn Any, _> = number.unsize(Coercion::to_any());
+    let number: Box<dyn Any, DynStorage<'_>> = Box::inline(number);
+    let number: Box<dyn Any, InlineStorage<usize>> = Box::from_dyn(number).unwrap();
+    assert_eq!(number.downcast_ref::<u16>(), Some(&42));
+}
+
+#[test]
+fn borrowed_is_not_inline() {
+    let mut string = ManuallyDrop::new(String::from("Hello, world!"));
+    let string: Box<dyn Debug, DynStorage<'_>> = unsafe { Box::take(&mut string) };
+    assert!(Box::<dyn Debug, InlineStorage<usize>>::from_dyn(string).is_none());
+}
@@ -0,0 +1,32 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::alloc::Global,
+    storage_api::{AllocStorage, Box, EitherStorage, InlineStorage, RawVec},
+};
+
+type InlineOrAlloc = EitherStorage<InlineStorage<[u32; 4]>, AllocStorage<Global>>;
+
+#[test]
+fn both_variants() {
+    let storages: [InlineOrAlloc; 2] = [
+        EitherStorage::Left(InlineStorage::new()),
+        EitherStorage::Right(AllocStorage::new(Global)),
+    ];
+    for storage in storages {
+        let boxed = Box::new_in([1u32, 2, 3, 4], storage);
+        assert_eq!(*boxed, [1, 2, 3, 4]);
+    }
+
+    let mut vec = RawVec::<u32, InlineOrAlloc>::new_in(EitherStorage::Left(InlineStorage::new()));
+    vec.grow_to(4).unwrap();
+    assert!(vec.grow_to(5).is_err());
+
+    let mut vec =
+        RawVec::<u32, InlineOrAlloc>::new_in(EitherStorage::Right(AllocStorage::new(Global)));
+    vec.grow_to(4).unwrap();
+    vec.grow_to(64).unwrap();
+    assert_eq!(vec.len(), 64);
+}
@@ -0,0 +1,85 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{Global, Layout},
+        mem::MaybeUninit,
+    },
+    storage_api::{
+        AllocStorage, BorrowedArena, BorrowedStorage, BumpStorage, CountingStorage, EitherStorage,
+        InlineStorage, ScratchStorage, SmallStorage, Storage,
+    },
+};
+
+/// Resolve zero-sized handles of various alignments, checking that each
+/// resolves to empty, aligned memory.
+///
+/// Storages may reject over-aligned layouts, but must accept a byte-aligned
+/// empty layout.
+fn resolve_empty<S: Storage>(mut storage: S) {
+    for align in [1, 2, 8, 64, 4096] {
+        let layout = Layout::from_size_align(0, align).unwrap();
+        let handle = match storage.allocate(layout) {
+            Ok(handle) => handle,
+            Err(_) if align > 1 => continue,
+            Err(_) => panic!("failed to allocate an empty layout"),
+        };
+        unsafe {
+            let memory = storage.resolve(handle, layout);
+            assert_eq!(memory.len(), 0);
+            assert_eq!(memory.as_ptr() as usize % align, 0);
+
+            let memory = storage.resolve_mut(handle, layout);
+            assert_eq!(memory.len(), 0);
+            assert_eq!(memory.as_mut_ptr() as usize % align, 0);
+
+            storage.deallocate(handle, layout);
+        }
+    }
+}
+
+#[test]
+fn alloc() {
+    resolve_empty(AllocStorage::new(Global));
+    resolve_empty(&AllocStorage::new(Global));
+}
+
+#[test]
+fn inline() {
+    resolve_empty(InlineStorage::<usize>::new());
+    resolve_empty(InlineStorage::<()>::new());
+}
+
+#[test]
+fn borrowed() {
+    let mut store = MaybeUninit::<[usize; 2]>::uninit();
+    resolve_empty(BorrowedStorage::new(&mut store));
+}
+
+#[test]
+fn small() {
+    resolve_empty(SmallStorage::<usize, Global>::new(Global));
+    resolve_empty(SmallStorage::<(), Global>::new(Global));
+}
+
+#[test]
+fn bump() {
+    resolve_empty(BumpStorage::<[usize; 4]>::new());
+}
+
+#[test]
+fn arena() {
+    let mut memory = [MaybeUninit::uninit(); 32];
+    resolve_empty(BorrowedArena::new(&mut memory));
+}
+
+#[test]
+fn wrappers() {
+    resolve_empty(CountingStorage::new(InlineStorage::<usize>::new()));
+    resolve_empty(ScratchStorage::new(AllocStorage::new(Global)));
+    resolve_empty(EitherStorage::<_, AllocStorage<Global>>::Left(
+        InlineStorage::<usize>::new(),
+    ));
+}
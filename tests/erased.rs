@@ -0,0 +1,44 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{Global, Layout},
+        prelude::rust_2021::*,
+    },
+    storage_api::{AllocStorage, DynStorageTrait, Erased, InlineStorage, RawBox},
+};
+
+#[test]
+fn heterogeneous_storages() {
+    let mut alloc = Erased(AllocStorage::new(Global));
+    let mut inline = Erased(InlineStorage::<[usize; 4]>::new());
+    let storages: [&mut dyn DynStorageTrait; 2] = [&mut alloc, &mut inline];
+
+    let layout = Layout::new::<u64>();
+    for storage in storages {
+        let handle = storage.allocate(layout).unwrap();
+        unsafe {
+            let memory = storage
+                .resolve_mut(handle, layout)
+                .as_mut_ptr()
+                .cast::<u64>();
+            memory.write(0xDEAD_BEEF);
+            let memory = storage.resolve(handle, layout).as_ptr().cast::<u64>();
+            assert_eq!(memory.read(), 0xDEAD_BEEF);
+            storage.deallocate(handle, layout);
+        }
+    }
+}
+
+#[test]
+fn boxed_through_trait_object() {
+    let mut alloc = Erased(AllocStorage::new(Global));
+    let storage: &mut dyn DynStorageTrait = &mut alloc;
+    unsafe {
+        let mut boxed = RawBox::<u32, _>::new((), storage).unwrap_or_else(|_| panic!());
+        boxed.as_mut_ptr().write(42);
+        assert_eq!(boxed.as_ptr().read(), 42);
+    }
+}
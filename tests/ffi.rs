@@ -0,0 +1,31 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{alloc::Global, mem::MaybeUninit},
+    storage_api::{AllocStorage, CStorage, FromStorage, RawBox},
+};
+
+/// Stands in for a function exported by a plugin, given a storage by its host.
+extern "C" fn plugin_box_value(storage: CStorage<'_>) -> u64 {
+    let mut boxed = RawBox::<MaybeUninit<u64>, _>::new_uninit(storage).unwrap_or_else(|_| panic!());
+    *boxed.write(0xDEAD_BEEF)
+}
+
+#[test]
+fn box_through_vtable() {
+    let mut host = FromStorage(AllocStorage::new(Global));
+    assert_eq!(plugin_box_value(host.as_c_storage()), 0xDEAD_BEEF);
+}
+
+#[test]
+fn grow_through_vtable() {
+    let mut host = FromStorage(AllocStorage::new(Global));
+    let storage = host.as_c_storage();
+    let mut boxed = unsafe { RawBox::<[u8], _>::new(4, storage) }.unwrap_or_else(|_| panic!());
+    unsafe { boxed.as_mut_ptr().cast::<[u8; 4]>().write(*b"abcd") };
+    boxed.grow_to(16).unwrap();
+    assert_eq!(boxed.metadata(), 16);
+    assert_eq!(unsafe { boxed.as_ptr().cast::<[u8; 4]>().read() }, *b"abcd");
+}
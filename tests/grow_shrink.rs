@@ -0,0 +1,360 @@
+#![feature(allocator_api)]
+
+//! Property tests running random sequences of storage operations against
+//! every storage, checking that the contents of each live handle survive all
+//! the resizes, and (for allocator-backed storages) that every allocation is
+//! freed exactly once.
+
+extern crate std;
+
+use {
+    proptest::prelude::*,
+    std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        cell::{Cell, RefCell},
+        collections::HashMap,
+        mem::MaybeUninit,
+        prelude::rust_2021::*,
+        ptr::NonNull,
+    },
+    storage_api::{
+        Align64, AlignedStorage, AllocStorage, BorrowedArena, BorrowedStorage, BudgetStorage,
+        BumpStorage, ConstPoolStorage, CountingStorage, EitherStorage, FromStorage,
+        HistogramStorage, InlineOrBorrowed, InlineStorage, ScratchStorage, SmallStorage, Storage,
+    },
+};
+
+/// An allocator which checks that every allocation is freed exactly once,
+/// with the layout it was allocated with.
+#[derive(Default)]
+struct Tracked(RefCell<HashMap<usize, Layout>>);
+
+unsafe impl Allocator for Tracked {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = Global.allocate(layout)?;
+        let previous = self
+            .0
+            .borrow_mut()
+            .insert(ptr.as_ptr() as *mut u8 as usize, layout);
+        assert!(previous.is_none(), "allocated a live pointer");
+        Ok(ptr)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let allocated = self.0.borrow_mut().remove(&(ptr.as_ptr() as usize));
+        assert_eq!(allocated, Some(layout), "freed a pointer that isn't live");
+        Global.deallocate(ptr, layout)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            assert!(self.0.get_mut().is_empty(), "leaked an allocation");
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Allocate {
+        size: usize,
+        align: usize,
+    },
+    Grow {
+        slot: usize,
+        by: usize,
+        align: usize,
+    },
+    Shrink {
+        slot: usize,
+        by: usize,
+        align: usize,
+    },
+    Deallocate {
+        slot: usize,
+    },
+}
+
+fn ops() -> impl Strategy<Value = Vec<Op>> {
+    let align = (0..7u32).prop_map(|shift| 1 << shift);
+    let op = prop_oneof![
+        (0..256usize, align.clone()).prop_map(|(size, align)| Op::Allocate { size, align }),
+        (any::<usize>(), 0..256usize, align.clone()).prop_map(|(slot, by, align)| Op::Grow {
+            slot,
+            by,
+            align
+        }),
+        (any::<usize>(), 0..256usize, align).prop_map(|(slot, by, align)| Op::Shrink {
+            slot,
+            by,
+            align
+        }),
+        any::<usize>().prop_map(|slot| Op::Deallocate { slot }),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+struct Live<H> {
+    handle: H,
+    layout: Layout,
+    seed: u8,
+}
+
+fn pattern(seed: u8, i: usize) -> u8 {
+    seed.wrapping_add(i as u8)
+}
+
+unsafe fn fill<S: Storage>(storage: &mut S, live: &Live<S::Handle>, from: usize) {
+    let memory = storage.resolve_mut(live.handle, live.layout);
+    assert_eq!(memory.len(), live.layout.size());
+    assert_eq!(memory.as_ptr() as usize % live.layout.align(), 0);
+    for (i, byte) in memory.iter_mut().enumerate().skip(from) {
+        byte.write(pattern(live.seed, i));
+    }
+}
+
+unsafe fn check<S: Storage>(storage: &S, live: &Live<S::Handle>) {
+    let memory = storage.resolve(live.handle, live.layout);
+    assert_eq!(memory.len(), live.layout.size());
+    assert_eq!(memory.as_ptr() as usize % live.layout.align(), 0);
+    for (i, byte) in memory.iter().enumerate() {
+        assert_eq!(
+            byte.assume_init(),
+            pattern(live.seed, i),
+            "byte {i} changed"
+        );
+    }
+}
+
+/// Run the operations against a storage, keeping at most `max_live` handles
+/// alive at once (1 for single storages). Failing operations are allowed, but
+/// must leave the handle as it was.
+fn run<S: Storage>(mut storage: S, max_live: usize, ops: &[Op]) {
+    let mut live: Vec<Live<S::Handle>> = Vec::new();
+    let mut next_seed = 0u8;
+
+    for op in ops {
+        unsafe {
+            match *op {
+                Op::Allocate { size, align } => {
+                    if live.len() == max_live {
+                        continue;
+                    }
+                    let layout = Layout::from_size_align(size, align).unwrap();
+                    if let Ok(handle) = storage.allocate(layout) {
+                        next_seed = next_seed.wrapping_add(37);
+                        let new = Live {
+                            handle,
+                            layout,
+                            seed: next_seed,
+                        };
+                        fill(&mut storage, &new, 0);
+                        live.push(new);
+                    }
+                },
+                Op::Grow { slot, by, align } if !live.is_empty() => {
+                    let index = slot % live.len();
+                    let slot = &mut live[index];
+                    let new_layout =
+                        Layout::from_size_align(slot.layout.size() + by, align).unwrap();
+                    if let Ok(handle) = storage.grow(slot.handle, slot.layout, new_layout) {
+                        let old_size = slot.layout.size();
+                        slot.handle = handle;
+                        slot.layout = new_layout;
+                        fill(&mut storage, slot, old_size);
+                    }
+                },
+                Op::Shrink { slot, by, align } if !live.is_empty() => {
+                    let index = slot % live.len();
+                    let slot = &mut live[index];
+                    let new_size = slot.layout.size().saturating_sub(by);
+                    let new_layout = Layout::from_size_align(new_size, align).unwrap();
+                    if let Ok(handle) = storage.shrink(slot.handle, slot.layout, new_layout) {
+                        slot.handle = handle;
+                        slot.layout = new_layout;
+                    }
+                },
+                Op::Deallocate { slot } if !live.is_empty() => {
+                    let slot = live.swap_remove(slot % live.len());
+                    storage.deallocate(slot.handle, slot.layout);
+                },
+                _ => {},
+            }
+            for slot in &live {
+                check(&storage, slot);
+            }
+        }
+    }
+
+    for slot in live {
+        unsafe { storage.deallocate(slot.handle, slot.layout) };
+    }
+}
+
+proptest! {
+    // Miri can't persist failures to a file under isolation, and runs far too
+    // slowly for the full number of cases.
+    #![proptest_config(ProptestConfig {
+        failure_persistence: None,
+        cases: if cfg!(miri) { 4 } else { 256 },
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn alloc(ops in ops()) {
+        let tracked = Tracked::default();
+        run(AllocStorage::new(&tracked), usize::MAX, &ops);
+        run(&AllocStorage::new(&tracked), usize::MAX, &ops);
+    }
+
+    #[test]
+    fn inline(ops in ops()) {
+        run(InlineStorage::<Align64<128>>::new(), 1, &ops);
+    }
+
+    #[test]
+    fn borrowed(ops in ops()) {
+        let mut store = MaybeUninit::<Align64<128>>::uninit();
+        run(BorrowedStorage::new(&mut store), 1, &ops);
+    }
+
+    #[test]
+    fn small(ops in ops()) {
+        let tracked = Tracked::default();
+        run(SmallStorage::<[usize; 4], _>::new(&tracked), 1, &ops);
+    }
+
+    #[test]
+    fn inline_or_borrowed(ops in ops()) {
+        let mut scratch = MaybeUninit::<Align64<128>>::uninit();
+        run(InlineOrBorrowed::<[usize; 4], _>::new(&mut scratch), 1, &ops);
+    }
+
+    #[test]
+    fn bump(ops in ops()) {
+        run(BumpStorage::<Align64<4096>>::new(), usize::MAX, &ops);
+    }
+
+    #[test]
+    fn arena(ops in ops()) {
+        let mut memory = MaybeUninit::<Align64<4096>>::uninit();
+        let memory = unsafe { &mut *(memory.as_mut_ptr() as *mut [MaybeUninit<u8>; 4096]) };
+        run(BorrowedArena::new(memory), usize::MAX, &ops);
+    }
+
+    #[test]
+    fn pool(ops in ops()) {
+        run(ConstPoolStorage::<Align64<256>, 8>::new(), usize::MAX, &ops);
+    }
+
+    #[test]
+    fn wrappers(ops in ops()) {
+        let tracked = Tracked::default();
+        run(CountingStorage::new(AllocStorage::new(&tracked)), 1, &ops);
+        run(ScratchStorage::new(AllocStorage::new(&tracked)), 1, &ops);
+        run(BudgetStorage::new(AllocStorage::new(&tracked), 512), 1, &ops);
+        run(
+            EitherStorage::<_, InlineStorage<usize>>::Left(AllocStorage::new(&tracked)),
+            1,
+            &ops,
+        );
+        run(FromStorage(AllocStorage::new(&tracked)).as_c_storage(), 1, &ops);
+        run(AlignedStorage::<_, 16>::new(AllocStorage::new(&tracked)), 1, &ops);
+        run(HistogramStorage::new(AllocStorage::new(&tracked)), 1, &ops);
+    }
+}
+
+/// Shrink a handle from `from` to `to` bytes, checking that the prefix
+/// survives.
+fn shrink_keeps_prefix<S: Storage>(mut storage: S, from: usize, to: usize) {
+    let layout = Layout::from_size_align(from, 8).unwrap();
+    let mut live = Live {
+        handle: storage.allocate(layout).unwrap(),
+        layout,
+        seed: 7,
+    };
+    unsafe {
+        fill(&mut storage, &live, 0);
+        let new_layout = Layout::from_size_align(to, 8).unwrap();
+        live.handle = storage
+            .shrink(live.handle, live.layout, new_layout)
+            .unwrap();
+        live.layout = new_layout;
+        check(&storage, &live);
+        storage.deallocate(live.handle, live.layout);
+    }
+}
+
+#[test]
+fn shrink_matrix() {
+    let tracked = Tracked::default();
+    for (from, to) in [(128, 128), (128, 64), (128, 1), (128, 0), (8, 0)] {
+        shrink_keeps_prefix(AllocStorage::new(&tracked), from, to);
+        shrink_keeps_prefix(InlineStorage::<Align64<128>>::new(), from, to);
+        let mut store = MaybeUninit::<Align64<128>>::uninit();
+        shrink_keeps_prefix(BorrowedStorage::new(&mut store), from, to);
+        // Outline to outline, outline to inline, and inline to inline.
+        shrink_keeps_prefix(SmallStorage::<[usize; 4], _>::new(&tracked), from, to);
+        let mut scratch = MaybeUninit::<Align64<128>>::uninit();
+        shrink_keeps_prefix(
+            InlineOrBorrowed::<[usize; 4], _>::new(&mut scratch),
+            from,
+            to,
+        );
+        shrink_keeps_prefix(BumpStorage::<Align64<256>>::new(), from, to);
+        let mut memory = MaybeUninit::<Align64<256>>::uninit();
+        let memory = unsafe { &mut *(memory.as_mut_ptr() as *mut [MaybeUninit<u8>; 256]) };
+        shrink_keeps_prefix(BorrowedArena::new(memory), from, to);
+        shrink_keeps_prefix(ConstPoolStorage::<Align64<128>, 2>::new(), from, to);
+        shrink_keeps_prefix(ScratchStorage::new(AllocStorage::new(&tracked)), from, to);
+        shrink_keeps_prefix(
+            AlignedStorage::<_, 64>::new(AllocStorage::new(&tracked)),
+            from,
+            to,
+        );
+    }
+}
+
+/// An allocator which tracks the usable bytes of its live allocations, as
+/// reported by the allocator it wraps.
+#[derive(Default)]
+struct Usable(Cell<usize>);
+
+unsafe impl Allocator for Usable {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = Global.allocate(layout)?;
+        self.0.set(self.0.get() + ptr.len());
+        Ok(ptr)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.0.set(self.0.get() - layout.size());
+        Global.deallocate(ptr, layout)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = Global.shrink(ptr, old_layout, new_layout)?;
+        self.0.set(self.0.get() - old_layout.size() + ptr.len());
+        Ok(ptr)
+    }
+}
+
+#[test]
+fn alloc_shrink_reclaims() {
+    let usable = Usable::default();
+    let mut storage = AllocStorage::new(&usable);
+    let old_layout = Layout::new::<[u8; 4096]>();
+    let new_layout = Layout::new::<[u8; 16]>();
+    let handle = storage.allocate(old_layout).unwrap();
+    assert_eq!(usable.0.get(), 4096);
+    let handle = unsafe { storage.shrink(handle, old_layout, new_layout) }.unwrap();
+    assert_eq!(usable.0.get(), 16);
+    unsafe { storage.deallocate(handle, new_layout) };
+    assert_eq!(usable.0.get(), 0);
+}
@@ -0,0 +1,81 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::Global,
+        collections::hash_map::RandomState,
+        hash::{BuildHasherDefault, Hasher},
+        prelude::rust_2021::*,
+        rc::Rc,
+    },
+    storage_api::{AllocStorage, HashMap, InfallibleEmptyStorage, SmallStorage, Storage},
+};
+
+fn insert_many<S: Storage + InfallibleEmptyStorage>(storage: S) {
+    let mut map = HashMap::<_, _, _, RandomState>::new_in(storage);
+    for key in 0..1000 {
+        assert_eq!(map.insert(key, key * 10), None);
+    }
+    assert_eq!(map.len(), 1000);
+    for key in 0..1000 {
+        assert_eq!(map.get(&key), Some(&(key * 10)));
+    }
+    assert_eq!(map.get(&1000), None);
+
+    assert_eq!(map.insert(3, 33), Some(30));
+    *map.get_mut(&4).unwrap() += 4;
+    assert_eq!(map.get(&3), Some(&33));
+    assert_eq!(map.get(&4), Some(&44));
+    for key in (0..1000).step_by(2) {
+        assert!(map.remove(&key).is_some());
+    }
+    assert_eq!(map.len(), 500);
+    for key in 0..1000 {
+        assert_eq!(map.get(&key).is_some(), key % 2 == 1);
+    }
+}
+
+#[test]
+fn alloc() {
+    insert_many(AllocStorage::new(Global));
+}
+
+#[test]
+fn small() {
+    insert_many(SmallStorage::<[usize; 16], _>::new(Global));
+}
+
+/// A hasher which hashes everything to the same value, so every key probes
+/// from the same bucket.
+#[derive(Default)]
+struct Colliding;
+
+impl Hasher for Colliding {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+#[test]
+fn remove_colliding() {
+    let value = Rc::new(());
+    let mut map =
+        HashMap::<_, _, _, BuildHasherDefault<Colliding>>::new_in(AllocStorage::new(Global));
+    for key in 0..20 {
+        map.insert(key, value.clone());
+    }
+    for key in [0, 7, 19, 8] {
+        assert!(map.remove(&key).is_some());
+        assert!(map.remove(&key).is_none());
+    }
+    for key in 0..20 {
+        assert_eq!(map.get(&key).is_some(), ![0, 7, 8, 19].contains(&key));
+    }
+    assert_eq!(Rc::strong_count(&value), 17);
+    drop(map);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
@@ -0,0 +1,39 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::alloc::{Global, Layout},
+    storage_api::{AllocStorage, HistogramStorage, Storage},
+};
+
+#[test]
+fn buckets_by_power_of_two() {
+    let mut storage = HistogramStorage::new(AllocStorage::new(Global));
+    let mut handles = std::vec::Vec::new();
+    for size in [0, 1, 2, 3, 4, 5, 8, 9, 1000, 1024, 1025] {
+        let layout = Layout::from_size_align(size, 1).unwrap();
+        handles.push((storage.allocate(layout).unwrap(), layout));
+    }
+
+    let (handle, layout) = handles.pop().unwrap();
+    let grown = Layout::from_size_align(4096, 1).unwrap();
+    let handle = unsafe { storage.grow(handle, layout, grown) }.unwrap();
+    handles.push((handle, grown));
+
+    let mut expected = [0; 64];
+    expected[0] = 2; // 0, 1
+    expected[1] = 1; // 2
+    expected[2] = 2; // 3, 4
+    expected[3] = 2; // 5, 8
+    expected[4] = 1; // 9
+    expected[10] = 2; // 1000, 1024
+    expected[11] = 1; // 1025
+    expected[12] = 1; // 4096
+    assert_eq!(storage.histogram(), expected);
+
+    for (handle, layout) in handles {
+        unsafe { storage.deallocate(handle, layout) };
+    }
+    assert_eq!(storage.histogram(), expected);
+}
@@ -0,0 +1,47 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{alloc::Global, prelude::rust_2021::*, rc::Rc},
+    storage_api::{Align8, AllocStorage, BumpStorage, LinkedList},
+};
+
+#[test]
+fn push_pop() {
+    let mut list = LinkedList::new_in(AllocStorage::new(Global));
+    assert_eq!(list.pop_front(), None);
+    for i in 0..5 {
+        list.push_front(i);
+    }
+    assert_eq!(list.len(), 5);
+    assert_eq!(list.front(), Some(&4));
+    assert_eq!(list.pop_front(), Some(4));
+    assert_eq!(list.pop_front(), Some(3));
+    assert_eq!(list.len(), 3);
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), [2, 1, 0]);
+}
+
+#[test]
+fn in_bump_arena() {
+    let mut list = LinkedList::new_in(BumpStorage::<Align8<256>>::new());
+    for s in ["a", "b", "c"] {
+        list.push_front(String::from(s));
+    }
+    let joined: String = list.iter().map(String::as_str).collect();
+    assert_eq!(joined, "cba");
+    assert_eq!(list.pop_front().as_deref(), Some("c"));
+    assert_eq!((&list).into_iter().count(), 2);
+}
+
+#[test]
+fn drops_values() {
+    let value = Rc::new(());
+    let mut list = LinkedList::new_in(AllocStorage::new(Global));
+    for _ in 0..4 {
+        list.push_front(value.clone());
+    }
+    assert_eq!(Rc::strong_count(&value), 5);
+    drop(list);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
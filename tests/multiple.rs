@@ -0,0 +1,210 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        mem::MaybeUninit,
+        ptr::NonNull,
+    },
+    storage_api::{
+        AllocHandle, AllocStorage, BumpStorage, CountingStorage, MultipleStorage, Storage,
+    },
+};
+
+#[test]
+fn resolve_many_repeated() {
+    let layout = Layout::new::<u32>();
+    let mut storage = AllocStorage::new(Global);
+    let one = storage.allocate(layout).unwrap();
+    let two = storage.allocate(layout).unwrap();
+    unsafe {
+        storage
+            .resolve_mut(one, layout)
+            .as_mut_ptr()
+            .cast::<u32>()
+            .write(1);
+        storage
+            .resolve_mut(two, layout)
+            .as_mut_ptr()
+            .cast::<u32>()
+            .write(2);
+
+        let [a, b, c] = storage.resolve_many([(one, layout), (one, layout), (two, layout)]);
+        assert_eq!(a.as_ptr().cast::<u32>().read(), 1);
+        assert_eq!(b.as_ptr().cast::<u32>().read(), 1);
+        assert_eq!(c.as_ptr().cast::<u32>().read(), 2);
+
+        storage.deallocate(one, layout);
+        storage.deallocate(two, layout);
+    }
+}
+
+#[test]
+fn resolve_is_aligned() {
+    let mut storage = AllocStorage::new(Global);
+    for shift in 0..=12 {
+        let align = 1 << shift;
+        for size in [0, 8, 3 * align] {
+            let layout = Layout::from_size_align(size, align).unwrap();
+            let handle = storage.allocate(layout).unwrap();
+            unsafe {
+                assert_eq!(storage.resolve(handle, layout).as_ptr() as usize % align, 0);
+                let grown = Layout::from_size_align(size + 64, align).unwrap();
+                let handle = storage.grow(handle, layout, grown).unwrap();
+                assert_eq!(storage.resolve(handle, grown).as_ptr() as usize % align, 0);
+                storage.deallocate(handle, grown);
+            }
+        }
+    }
+}
+
+#[test]
+fn realign_keeps_contents() {
+    let mut storage = AllocStorage::new(Global);
+    let layout = Layout::from_size_align(8, 1).unwrap();
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        let memory = storage.resolve_mut(handle, layout).as_mut_ptr();
+        memory.cast::<[u8; 8]>().write(*b"contents");
+
+        let grown = Layout::from_size_align(16, 4096).unwrap();
+        let handle = storage.grow(handle, layout, grown).unwrap();
+        let memory = storage.resolve(handle, grown).as_ptr();
+        assert_eq!(memory as usize % 4096, 0);
+        assert_eq!(memory.cast::<[u8; 8]>().read(), *b"contents");
+
+        let shrunk = Layout::from_size_align(8, 8192).unwrap();
+        let handle = storage.shrink(handle, grown, shrunk).unwrap();
+        let memory = storage.resolve(handle, shrunk).as_ptr();
+        assert_eq!(memory as usize % 8192, 0);
+        assert_eq!(memory.cast::<[u8; 8]>().read(), *b"contents");
+
+        storage.deallocate(handle, shrunk);
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic = "the same handle was passed twice"]
+fn resolve_many_mut_duplicate() {
+    // Bump storage, so that nothing leaks when this panics.
+    let layout = Layout::new::<u32>();
+    let mut storage = BumpStorage::<[u32; 2]>::new();
+    let one = storage.allocate(layout).unwrap();
+    let two = storage.allocate(layout).unwrap();
+    unsafe { storage.resolve_many_mut([(one, layout), (two, layout), (one, layout)]) };
+}
+
+#[test]
+fn resolve_many_mut_empty_duplicates() {
+    let empty = Layout::new::<()>();
+    let mut storage = AllocStorage::new(Global);
+    let handle = storage.allocate(empty).unwrap();
+    let [a, b] = unsafe { storage.resolve_many_mut([(handle, empty), (handle, empty)]) };
+    assert_eq!((a.len(), b.len()), (0, 0));
+}
+
+#[test]
+fn alloc_handle_from_ptr() {
+    let layout = Layout::new::<u32>();
+    let ptr = Global.allocate(layout).unwrap().cast::<()>();
+    let handle = AllocHandle::from_ptr(ptr);
+    assert_eq!(handle.as_ptr(), ptr);
+
+    let mut storage = AllocStorage::new(Global);
+    unsafe {
+        let memory = storage.resolve_mut(handle, layout);
+        assert_eq!(memory.as_mut_ptr().cast::<()>(), ptr.as_ptr());
+        memory.as_mut_ptr().cast::<u32>().write(7);
+        assert_eq!(handle.as_ptr().cast::<u32>().as_ptr().read(), 7);
+        storage.deallocate(handle, layout);
+    }
+}
+
+#[test]
+fn allocate_with_excess() {
+    let mut storage = AllocStorage::new(Global);
+    for size in 0..100 {
+        let layout = Layout::from_size_align(size, 8).unwrap();
+        let (handle, excess) = storage.allocate_with_excess(layout).unwrap();
+        assert!(excess.size() >= layout.size());
+        assert_eq!(excess.align(), layout.align());
+        unsafe {
+            // The whole excess is usable, and may be freed with either layout.
+            let memory = storage.resolve_mut(handle, excess);
+            assert_eq!(memory.len(), excess.size());
+            memory.fill(MaybeUninit::new(size as u8));
+            storage.deallocate(handle, excess);
+        }
+    }
+}
+
+#[test]
+fn realloc_either_direction() {
+    let mut storage = CountingStorage::new(AllocStorage::new(Global));
+    let mut layout = Layout::new::<[u8; 8]>();
+    let mut handle = storage.allocate(layout).unwrap();
+    unsafe { storage.resolve_mut(handle, layout) }.fill(MaybeUninit::new(7));
+
+    // Grow, keep the same layout without touching the storage, then shrink.
+    for (size, reallocations) in [(16, 1), (16, 1), (4, 2)] {
+        let new_layout = Layout::from_size_align(size, 1).unwrap();
+        handle = unsafe { storage.realloc(handle, layout, new_layout) }.unwrap();
+        layout = new_layout;
+        assert_eq!(storage.reallocations(), reallocations);
+        let memory = unsafe { storage.resolve(handle, layout) };
+        assert!(memory[..4].iter().all(|b| unsafe { b.assume_init() } == 7));
+    }
+    unsafe { storage.deallocate(handle, layout) };
+}
+
+#[test]
+fn equal_layout_grow_is_free() {
+    /// An allocator which must not be used to reallocate.
+    struct NoRealloc;
+
+    unsafe impl Allocator for NoRealloc {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+
+        unsafe fn grow(
+            &self,
+            _ptr: NonNull<u8>,
+            _old_layout: Layout,
+            _new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            panic!("reallocated to the same layout")
+        }
+
+        unsafe fn shrink(
+            &self,
+            _ptr: NonNull<u8>,
+            _old_layout: Layout,
+            _new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            panic!("reallocated to the same layout")
+        }
+    }
+
+    fn check<S: Storage>(mut storage: S) {
+        let layout = Layout::new::<[u32; 4]>();
+        let handle = storage.allocate(layout).unwrap();
+        unsafe {
+            assert!(storage.grow(handle, layout, layout).ok() == Some(handle));
+            assert!(storage.shrink(handle, layout, layout).ok() == Some(handle));
+            storage.deallocate(handle, layout);
+        }
+    }
+
+    check(AllocStorage::new(NoRealloc));
+    check(CountingStorage::new(AllocStorage::new(NoRealloc)));
+    // Multiple storages would otherwise allocate a new handle and copy.
+    check(BumpStorage::<[u32; 4]>::new());
+}
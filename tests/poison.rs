@@ -0,0 +1,74 @@
+#![cfg(any(debug_assertions, feature = "checked"))]
+#![feature(allocator_api)]
+// `SingleHandle` is `()` without the `checked` feature.
+#![allow(clippy::let_unit_value)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        cell::RefCell,
+        prelude::rust_2021::*,
+        ptr::NonNull,
+        slice,
+    },
+    storage_api::{AllocStorage, InlineStorage, Storage},
+};
+
+const POISON: u8 = 0xDE;
+
+/// An allocator which copies the bytes of memory just before freeing it.
+#[derive(Default)]
+struct Snapshot {
+    freed: RefCell<Vec<u8>>,
+}
+
+unsafe impl Allocator for Snapshot {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let bytes = slice::from_raw_parts(ptr.as_ptr(), layout.size());
+        self.freed.borrow_mut().extend_from_slice(bytes);
+        Global.deallocate(ptr, layout)
+    }
+}
+
+#[test]
+fn alloc_poisons_on_deallocate() {
+    let alloc = Snapshot::default();
+    let mut storage = AllocStorage::new(&alloc);
+    let layout = Layout::new::<[u32; 4]>();
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        let memory = storage.resolve_mut(handle, layout);
+        memory.as_mut_ptr().cast::<[u32; 4]>().write([1, 2, 3, 4]);
+        storage.deallocate(handle, layout);
+    }
+    assert_eq!(*alloc.freed.borrow(), [POISON; 16]);
+}
+
+#[test]
+fn inline_poisons_on_deallocate() {
+    let mut storage = InlineStorage::<u64>::new();
+    let layout = Layout::new::<u64>();
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        let memory = storage.resolve_mut(handle, layout);
+        memory.as_mut_ptr().cast::<u64>().write(u64::MAX);
+        storage.deallocate(handle, layout);
+    }
+
+    // Reallocating doesn't touch the memory, so it still holds the poison.
+    let handle = storage.allocate(layout).unwrap();
+    let value = unsafe {
+        storage
+            .resolve(handle, layout)
+            .as_ptr()
+            .cast::<u64>()
+            .read()
+    };
+    assert_eq!(value, u64::from_ne_bytes([POISON; 8]));
+}
@@ -0,0 +1,88 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::Layout,
+        hint,
+        mem::MaybeUninit,
+        prelude::rust_2021::*,
+        sync::atomic::{AtomicBool, Ordering},
+    },
+    storage_api::{ConstPoolStorage, RawBox, RawMutex, Storage, SyncStorage},
+};
+
+struct SpinLock(AtomicBool);
+
+unsafe impl RawMutex for SpinLock {
+    fn lock(&self) {
+        while self
+            .0
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+static POOL: SyncStorage<SpinLock, ConstPoolStorage<u64, 4>> =
+    SyncStorage::new(SpinLock(AtomicBool::new(false)), ConstPoolStorage::new());
+
+#[test]
+fn static_pool() {
+    let mut boxes: Vec<_> = (0..4)
+        .map(|i| {
+            let mut boxed =
+                RawBox::<MaybeUninit<u64>, _>::new_uninit(&POOL).unwrap_or_else(|_| panic!());
+            boxed.write(i);
+            unsafe { boxed.assume_init() }
+        })
+        .collect();
+    assert!(POOL.is_full());
+    assert!(RawBox::<MaybeUninit<u64>, _>::new_uninit(&POOL).is_err());
+
+    // Freeing a block makes it available again.
+    drop(boxes.remove(1));
+    let mut boxed = RawBox::<MaybeUninit<u64>, _>::new_uninit(&POOL).unwrap_or_else(|_| panic!());
+    boxed.write(9);
+    boxes.push(unsafe { boxed.assume_init() });
+
+    let values: Vec<u64> = boxes
+        .iter()
+        .map(|boxed| unsafe { boxed.as_ref().assume_init() })
+        .collect();
+    assert_eq!(values, [0, 2, 3, 9]);
+}
+
+#[test]
+fn block_layouts() {
+    let mut pool = ConstPoolStorage::<u32, 2>::new();
+    assert!(pool.allocate(Layout::new::<u64>()).is_err());
+    assert!(pool.allocate(Layout::new::<[u16; 2]>()).is_ok());
+
+    // Empty layouts don't take a block.
+    for _ in 0..4 {
+        pool.allocate(Layout::new::<()>()).unwrap();
+    }
+    let handle = pool.allocate(Layout::new::<u8>()).unwrap();
+    assert!(pool.is_full());
+
+    unsafe {
+        let handle = pool
+            .grow(handle, Layout::new::<u8>(), Layout::new::<u32>())
+            .unwrap();
+        let handle = pool
+            .shrink(handle, Layout::new::<u32>(), Layout::new::<()>())
+            .unwrap();
+        assert!(!pool.is_full());
+        pool.grow(handle, Layout::new::<()>(), Layout::new::<u32>())
+            .unwrap();
+    }
+    assert!(pool.is_full());
+}
@@ -0,0 +1,246 @@
+#![feature(allocator_api, ptr_metadata)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        cell::Cell,
+        fmt::Debug,
+        mem::{self, MaybeUninit},
+        prelude::rust_2021::*,
+        ptr::{self, NonNull, Pointee},
+    },
+    storage_api::{AllocStorage, Box, InlineStorage, RawBox},
+};
+
+#[test]
+fn read_write_through_pointers() {
+    unsafe {
+        let mut boxed =
+            RawBox::<[u32; 4], _>::new((), AllocStorage::new(Global)).unwrap_or_else(|_| panic!());
+        boxed.as_mut_ptr().write([1, 2, 3, 4]);
+        (*boxed.as_mut_ptr())[2] = 30;
+        assert_eq!(boxed.as_ptr().read(), [1, 2, 30, 4]);
+
+        let mut boxed = RawBox::<[u16], _>::new(3, InlineStorage::<[u16; 4]>::new())
+            .unwrap_or_else(|_| panic!());
+        for i in 0..3 {
+            boxed.as_mut_ptr().cast::<u16>().add(i).write(i as u16);
+        }
+        assert_eq!(&*boxed.as_ptr(), &[0, 1, 2]);
+    }
+}
+
+#[test]
+fn uninit_round_trip() {
+    let mut boxed = RawBox::<MaybeUninit<[u8; 32]>, _>::new_uninit(AllocStorage::new(Global))
+        .unwrap_or_else(|_| panic!());
+    boxed.write([7; 32])[31] = 31;
+    let mut boxed = unsafe { boxed.assume_init() };
+    unsafe {
+        let value = boxed.as_mut().assume_init_mut();
+        assert_eq!(value[..31], [7; 31]);
+        assert_eq!(value[31], 31);
+    }
+
+    let boxed = boxed.into_uninit();
+    assert_eq!(unsafe { boxed.as_ptr().read().assume_init() }[0], 7);
+}
+
+/// An allocator which records the requested layout, then fails.
+#[derive(Default)]
+struct LayoutRecorder(Cell<Option<Layout>>);
+
+unsafe impl Allocator for LayoutRecorder {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.set(Some(layout));
+        Err(AllocError)
+    }
+
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
+        unreachable!()
+    }
+}
+
+fn requested_layout<T: ?Sized>(metadata: <T as Pointee>::Metadata) -> Option<Layout> {
+    let recorder = LayoutRecorder::default();
+    let boxed = unsafe { RawBox::<T, _>::new(metadata, AllocStorage::new(&recorder)) };
+    assert!(boxed.is_err());
+    recorder.0.get()
+}
+
+#[test]
+fn slice_layouts() {
+    assert_eq!(requested_layout::<[u8]>(5), Some(Layout::new::<[u8; 5]>()));
+    assert_eq!(
+        requested_layout::<[u64]>(3),
+        Some(Layout::new::<[u64; 3]>())
+    );
+    assert_eq!(requested_layout::<str>(7), Some(Layout::new::<[u8; 7]>()));
+
+    // Zero-sized layouts don't reach the allocator.
+    let boxed = unsafe { RawBox::<[()], _>::new(usize::MAX, AllocStorage::new(Global)) };
+    assert!(boxed.is_ok());
+
+    // Overflowing layouts are rejected before reaching the allocator.
+    assert_eq!(requested_layout::<[u64]>(usize::MAX / 4), None);
+    assert_eq!(requested_layout::<[u8]>(isize::MAX as usize + 1), None);
+}
+
+#[test]
+fn checked_metadata() {
+    let storage = &AllocStorage::new(Global);
+    assert!(RawBox::<[u64], _>::new_checked(3, storage).is_ok());
+    assert!(RawBox::<[u64], _>::new_checked(usize::MAX / 4, storage).is_err());
+    assert!(RawBox::<str, _>::new_checked(isize::MAX as usize + 1, storage).is_err());
+
+    let vtable = ptr::metadata(&0u32 as &dyn Debug);
+    let boxed = RawBox::<dyn Debug, _>::new_checked(vtable, storage).unwrap_or_else(|_| panic!());
+    assert_eq!(
+        Layout::for_value(unsafe { &*boxed.as_ptr() }),
+        Layout::new::<u32>()
+    );
+
+    // Composite layouts can't be checked.
+    struct Tail {
+        _head: u8,
+        _tail: [u16],
+    }
+    assert!(RawBox::<Tail, _>::new_checked(1, storage).is_err());
+}
+
+#[test]
+fn byte_buffer() {
+    let layout = Layout::from_size_align(128, 64).unwrap();
+    let mut bytes =
+        RawBox::new_bytes(layout, AllocStorage::new(Global)).unwrap_or_else(|_| panic!());
+    assert_eq!(bytes.metadata(), 128);
+    assert_eq!(bytes.as_ptr().cast::<u8>() as usize % 64, 0);
+    for (i, byte) in unsafe { &mut *bytes.as_mut_ptr() }.iter_mut().enumerate() {
+        byte.write(i as u8 ^ 0xA5);
+    }
+    let bytes = unsafe { &*bytes.as_ptr() };
+    assert!((0..128).all(|i| unsafe { bytes[i].assume_init() } == i as u8 ^ 0xA5));
+
+    // The box keeps the alignment when growing.
+    let mut bytes =
+        RawBox::new_bytes(layout, AllocStorage::new(Global)).unwrap_or_else(|_| panic!());
+    bytes.grow_to(192).unwrap();
+    assert_eq!(bytes.as_ptr().cast::<u8>() as usize % 64, 0);
+    let debug = format!("{bytes:?}");
+    assert!(debug.contains("size: 192, align: 64"), "{debug}");
+}
+
+#[test]
+fn swap_inline_boxes() {
+    fn inline_box(value: [u32; 4]) -> RawBox<[u32; 4], InlineStorage<[u32; 4]>> {
+        let mut boxed = RawBox::<MaybeUninit<[u32; 4]>, _>::new_uninit(InlineStorage::new())
+            .unwrap_or_else(|_| panic!());
+        boxed.write(value);
+        unsafe { boxed.assume_init() }
+    }
+
+    let mut a = inline_box([1, 2, 3, 4]);
+    let mut b = inline_box([5, 6, 7, 8]);
+    mem::swap(&mut a, &mut b);
+    unsafe {
+        assert_eq!(a.as_ptr().read(), [5, 6, 7, 8]);
+        assert_eq!(b.as_ptr().read(), [1, 2, 3, 4]);
+        a.as_mut_ptr().write([0; 4]);
+        assert_eq!(b.as_ptr().read(), [1, 2, 3, 4]);
+    }
+
+    let mut a = Box::new_in(String::from("a"), InlineStorage::<String>::new());
+    let mut b = Box::new_in(String::from("b"), InlineStorage::<String>::new());
+    mem::swap(&mut a, &mut b);
+    assert_eq!((&**a, &**b), ("b", "a"));
+}
+
+#[test]
+fn grow_slice() {
+    let mut boxed = unsafe { RawBox::<[u8], _>::new(4, AllocStorage::new(Global)) }
+        .unwrap_or_else(|_| panic!());
+    unsafe { boxed.as_mut_ptr().cast::<[u8; 4]>().write(*b"abcd") };
+    boxed.grow_to(16).unwrap();
+    assert_eq!(boxed.metadata(), 16);
+    assert_eq!(unsafe { boxed.as_ptr().cast::<[u8; 4]>().read() }, *b"abcd");
+
+    boxed.grow_to(8).unwrap();
+    assert_eq!(boxed.metadata(), 16);
+    assert!(boxed.grow_to(usize::MAX).is_err());
+    assert_eq!(boxed.metadata(), 16);
+
+    let mut inline = unsafe { RawBox::<[u8], _>::new(4, InlineStorage::<[u8; 8]>::new()) }
+        .unwrap_or_else(|_| panic!());
+    assert!(inline.grow_to(8).is_ok());
+    assert!(inline.grow_to(16).is_err());
+    assert_eq!(inline.metadata(), 8);
+}
+
+#[test]
+fn debug_layout() {
+    let boxed = unsafe { RawBox::<[u16], _>::new(3, InlineStorage::<[u16; 4]>::new()) }
+        .unwrap_or_else(|_| panic!());
+    let debug = format!("{boxed:?}");
+    assert!(debug.contains("metadata: 3"), "{debug}");
+    assert!(debug.contains("size: 6"), "{debug}");
+}
+
+/// Counts allocations and frees, and how many times it's dropped, so that
+/// moving a storage out and back in can be checked to not drop it.
+struct Counter<'a> {
+    allocations: &'a Cell<usize>,
+    frees: &'a Cell<usize>,
+    drops: &'a Cell<usize>,
+}
+
+unsafe impl Allocator for Counter<'_> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocations.set(self.allocations.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.frees.set(self.frees.get() + 1);
+        Global.deallocate(ptr, layout)
+    }
+}
+
+impl Drop for Counter<'_> {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+    }
+}
+
+#[test]
+fn raw_parts_round_trip() {
+    let (allocations, frees, drops) = (Cell::new(0), Cell::new(0), Cell::new(0));
+    let storage = AllocStorage::new(Counter {
+        allocations: &allocations,
+        frees: &frees,
+        drops: &drops,
+    });
+
+    let mut boxed =
+        RawBox::<MaybeUninit<String>, _>::new_uninit(storage).unwrap_or_else(|_| panic!());
+    boxed.write(String::from("round trip"));
+    let boxed = unsafe { boxed.assume_init() };
+
+    let (handle, (), storage) = boxed.into_raw_parts();
+    assert_eq!(drops.get(), 0);
+    let boxed = unsafe { RawBox::<String, _>::from_raw_parts(handle, (), storage) };
+    assert_eq!(unsafe { &*boxed.as_ptr() }, "round trip");
+
+    // Reassemble the parts as a `Box`, which owns the string, and round trip
+    // through its parts too.
+    let (handle, (), storage) = boxed.into_raw_parts();
+    let boxed: Box<String, _> = unsafe { Box::from_raw_parts(handle, (), storage) };
+    let (handle, (), storage) = Box::into_raw_parts(boxed);
+    let boxed: Box<String, _> = unsafe { Box::from_raw_parts(handle, (), storage) };
+    assert_eq!(*boxed, "round trip");
+    assert_eq!((allocations.get(), frees.get(), drops.get()), (1, 0, 0));
+
+    drop(boxed);
+    assert_eq!((allocations.get(), frees.get(), drops.get()), (1, 1, 1));
+}
@@ -0,0 +1,438 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        cell::Cell,
+        mem::{size_of, MaybeUninit},
+        ptr::NonNull,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+    storage_api::{
+        AllocStorage, BudgetStorage, CountingStorage, GrowthPolicy, HistogramStorage,
+        InlineStorage, Memory, RawVec, ScratchStorage, SmallStorage, Storage,
+    },
+};
+
+struct NullAlloc;
+
+unsafe impl Allocator for NullAlloc {
+    fn allocate(&self, _: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Err(AllocError)
+    }
+
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
+        unreachable!()
+    }
+}
+
+#[test]
+fn new_in_is_infallible() {
+    let vec = RawVec::<u64, _>::new_in(AllocStorage::new(NullAlloc));
+    assert_eq!(vec.len(), 0);
+
+    // The inline storage is less aligned than the element type.
+    let vec = RawVec::<u64, _>::new_in(InlineStorage::<[u8; 16]>::new());
+    assert_eq!(vec.len(), 0);
+    assert_eq!(vec.as_ref().as_ptr() as usize % 8, 0);
+
+    let mut vec = RawVec::<u8, _>::new_in(SmallStorage::<usize, _>::new(Global));
+    vec.grow_to(64).unwrap();
+    assert_eq!(vec.len(), 64);
+}
+
+#[test]
+fn drop_elements_once() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountDrop;
+    impl Drop for CountDrop {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let mut vec = RawVec::<CountDrop, _>::new_in(AllocStorage::new(Global));
+    vec.grow_to(4).unwrap();
+    for slot in &mut vec.as_mut()[..3] {
+        slot.write(CountDrop);
+    }
+    unsafe { vec.drop_elements(3) };
+    assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+    drop(vec);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+}
+
+struct GoldenRatio;
+
+impl GrowthPolicy for GoldenRatio {
+    fn next_capacity(current: usize, required: usize) -> usize {
+        Ord::max(current + current / 2, required)
+    }
+}
+
+struct FixedChunk;
+
+impl GrowthPolicy for FixedChunk {
+    fn next_capacity(_current: usize, required: usize) -> usize {
+        match required % 16 {
+            0 => required,
+            rem => required + 16 - rem,
+        }
+    }
+}
+
+#[test]
+fn growth_policies() {
+    let mut vec = RawVec::<u8, _>::new_in(AllocStorage::new(Global));
+    vec.grow_amortized(1).unwrap();
+    assert_eq!(vec.len(), 1);
+    vec.grow_amortized(2).unwrap();
+    assert_eq!(vec.len(), 2);
+    vec.grow_amortized(3).unwrap();
+    assert_eq!(vec.len(), 4);
+    vec.grow_amortized(4).unwrap();
+    assert_eq!(vec.len(), 4);
+
+    let mut vec = RawVec::<u8, _>::new_in(AllocStorage::new(Global));
+    vec.grow_to(10).unwrap();
+    vec.grow_amortized_with::<GoldenRatio>(11).unwrap();
+    assert_eq!(vec.len(), 15);
+    vec.grow_amortized_with::<GoldenRatio>(16).unwrap();
+    assert_eq!(vec.len(), 22);
+    vec.grow_amortized_with::<GoldenRatio>(40).unwrap();
+    assert_eq!(vec.len(), 40);
+
+    let mut vec = RawVec::<u8, _>::new_in(AllocStorage::new(Global));
+    vec.grow_amortized_with::<FixedChunk>(1).unwrap();
+    assert_eq!(vec.len(), 16);
+    vec.grow_amortized_with::<FixedChunk>(17).unwrap();
+    assert_eq!(vec.len(), 32);
+    vec.grow_amortized_with::<FixedChunk>(20).unwrap();
+    assert_eq!(vec.len(), 32);
+}
+
+#[test]
+fn cache_line_aligned() {
+    let mut vec =
+        RawVec::<u8, _>::with_align(64, AllocStorage::new(Global)).unwrap_or_else(|_| panic!());
+    assert_eq!(vec.as_ref().as_ptr() as usize % 64, 0);
+    vec.grow_to(100).unwrap();
+    assert_eq!(vec.as_ref().as_ptr() as usize % 64, 0);
+    vec.grow_to(1000).unwrap();
+    assert_eq!(vec.as_ref().as_ptr() as usize % 64, 0);
+}
+
+#[test]
+fn grow_to_overflow() {
+    let mut vec = RawVec::<u32, _>::new_in(AllocStorage::new(NullAlloc));
+    let too_long = usize::MAX / size_of::<u32>() + 1;
+    assert_eq!(vec.grow_to(too_long), Err(AllocError));
+    assert_eq!(vec.grow_to(isize::MAX as usize / 2), Err(AllocError));
+    assert_eq!(vec.len(), 0);
+}
+
+/// A storage of two fixed blocks, which hands out dangling empty handles.
+struct Pool {
+    blocks: [MaybeUninit<[u64; 4]>; 2],
+    used: [bool; 2],
+}
+
+impl Pool {
+    const EMPTY: usize = usize::MAX;
+    const BLOCK: Layout = Layout::new::<[u64; 4]>();
+
+    fn new() -> Self {
+        Pool {
+            blocks: [MaybeUninit::uninit(); 2],
+            used: [false; 2],
+        }
+    }
+}
+
+unsafe impl Storage for Pool {
+    type Handle = usize;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if layout.size() > Self::BLOCK.size() || layout.align() > Self::BLOCK.align() {
+            return Err(AllocError);
+        }
+        let index = self.used.iter().position(|&used| !used).ok_or(AllocError)?;
+        self.used[index] = true;
+        Ok(index)
+    }
+
+    fn allocate_empty(&mut self, align: usize) -> Result<Self::Handle, AllocError> {
+        assert!(align <= Self::BLOCK.align());
+        Ok(Self::EMPTY)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, _: Layout) {
+        if handle != Self::EMPTY {
+            self.used[handle] = false;
+        }
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        if handle == Self::EMPTY {
+            // Empty, but still aligned as the block would be.
+            return std::slice::from_raw_parts(self.blocks.as_ptr().cast::<MaybeUninit<u8>>(), 0);
+        }
+        std::slice::from_raw_parts(
+            self.blocks[handle].as_ptr().cast::<MaybeUninit<u8>>(),
+            layout.size(),
+        )
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        if handle == Self::EMPTY {
+            return std::slice::from_raw_parts_mut(
+                self.blocks.as_mut_ptr().cast::<MaybeUninit<u8>>(),
+                0,
+            );
+        }
+        std::slice::from_raw_parts_mut(
+            self.blocks[handle].as_mut_ptr().cast::<MaybeUninit<u8>>(),
+            layout.size(),
+        )
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        _: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        if handle == Self::EMPTY {
+            self.allocate(new_layout)
+        } else if new_layout.size() <= Self::BLOCK.size() {
+            Ok(handle)
+        } else {
+            Err(AllocError)
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        _: Layout,
+        _: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        Ok(handle)
+    }
+}
+
+#[test]
+fn new_in_full_pool() {
+    let mut pool = Pool::new();
+    pool.allocate(Pool::BLOCK).unwrap();
+    pool.allocate(Pool::BLOCK).unwrap();
+    assert!(pool.allocate(Layout::new::<()>()).is_err());
+
+    let mut vec = RawVec::<u64, _>::new(pool).unwrap_or_else(|_| panic!());
+    assert_eq!(vec.len(), 0);
+    assert_eq!(vec.as_ref().len(), 0);
+    assert_eq!(vec.grow_to(1), Err(AllocError));
+}
+
+#[test]
+fn new_in_full_pool_through_wrappers() {
+    let full_pool = || {
+        let mut pool = Pool::new();
+        pool.allocate(Pool::BLOCK).unwrap();
+        pool.allocate(Pool::BLOCK).unwrap();
+        pool
+    };
+    assert!(RawVec::<u64, _>::new(CountingStorage::new(full_pool())).is_ok());
+    assert!(RawVec::<u64, _>::new(ScratchStorage::new(full_pool())).is_ok());
+    assert!(RawVec::<u64, _>::new(BudgetStorage::new(full_pool(), 0)).is_ok());
+    assert!(RawVec::<u64, _>::new(HistogramStorage::new(full_pool())).is_ok());
+}
+
+/// An allocator which rounds allocations up to a multiple of 64 bytes.
+struct RoundUp;
+
+impl RoundUp {
+    fn round(layout: Layout) -> Layout {
+        Layout::from_size_align((layout.size() + 63) & !63, layout.align()).unwrap()
+    }
+}
+
+unsafe impl Allocator for RoundUp {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Global.allocate(Self::round(layout))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        Global.deallocate(ptr, Self::round(layout))
+    }
+}
+
+#[test]
+fn with_capacity_uses_excess() {
+    let vec = RawVec::<u32, _>::with_capacity(10, AllocStorage::new(RoundUp))
+        .unwrap_or_else(|_| panic!());
+    assert_eq!(vec.len(), 16);
+
+    let mut vec = RawVec::<u32, _>::with_capacity(10, InlineStorage::<[u32; 12]>::new())
+        .unwrap_or_else(|_| panic!());
+    assert_eq!(vec.len(), 10);
+    vec.grow_to(12).unwrap();
+
+    let vec = RawVec::<(), _>::with_capacity(10, AllocStorage::new(NullAlloc))
+        .unwrap_or_else(|_| panic!());
+    assert_eq!(vec.len(), 10);
+    assert!(RawVec::<u32, _>::with_capacity(usize::MAX, AllocStorage::new(Global)).is_err());
+}
+
+#[test]
+fn non_null_slice() {
+    let mut vec = RawVec::<u16, _>::new_in(InlineStorage::<[u16; 8]>::new());
+    vec.grow_to(5).unwrap();
+    for (i, slot) in vec.as_mut().iter_mut().enumerate() {
+        slot.write(i as u16);
+    }
+    let ptr = vec.as_non_null();
+    assert_eq!(ptr.len(), 5);
+    assert_eq!(unsafe { ptr.as_ref() }, [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn compact_evens() {
+    let mut vec = RawVec::<u16, _>::new_in(InlineStorage::<[u16; 8]>::new());
+    vec.grow_to(5).unwrap();
+    for (i, slot) in vec.as_mut().iter_mut().enumerate() {
+        slot.write(i as u16);
+    }
+    assert_eq!(vec.compact(|i| i % 2 == 0), 3);
+    assert_eq!(vec.len(), 5);
+    let (kept, removed) = unsafe { vec.split_at_spare_mut(3) };
+    assert_eq!(kept, [0, 2, 4]);
+    let mut removed: std::vec::Vec<u16> =
+        removed.iter().map(|x| unsafe { x.assume_init() }).collect();
+    removed.sort();
+    assert_eq!(removed, [1, 3]);
+}
+
+#[test]
+fn dedup_runs() {
+    let values = [1u16, 1, 2, 3, 3, 3, 4];
+    let mut vec = RawVec::<u16, _>::new_in(InlineStorage::<[u16; 8]>::new());
+    vec.grow_to(values.len()).unwrap();
+    for (slot, &value) in vec.as_mut().iter_mut().zip(&values) {
+        slot.write(value);
+    }
+    assert_eq!(unsafe { vec.dedup(|a, b| a == b) }, 4);
+    let (kept, removed) = unsafe { vec.split_at_spare_mut(4) };
+    assert_eq!(kept, [1, 2, 3, 4]);
+    let mut removed: std::vec::Vec<u16> =
+        removed.iter().map(|x| unsafe { x.assume_init() }).collect();
+    removed.sort();
+    assert_eq!(removed, [1, 3, 3]);
+}
+
+/// A storage of one block, which even empty allocations take.
+struct OneBlock<'a> {
+    block: MaybeUninit<[u64; 4]>,
+    taken: &'a Cell<bool>,
+}
+
+unsafe impl Storage for OneBlock<'_> {
+    type Handle = ();
+
+    const EMPTY_LAYOUT_IS_FREE: bool = false;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if self.taken.get() || layout.size() > size_of::<[u64; 4]>() || layout.align() > 8 {
+            return Err(AllocError);
+        }
+        self.taken.set(true);
+        Ok(())
+    }
+
+    unsafe fn deallocate(&mut self, (): Self::Handle, _: Layout) {
+        self.taken.set(false);
+    }
+
+    unsafe fn resolve(&self, (): Self::Handle, layout: Layout) -> &Memory {
+        std::slice::from_raw_parts(self.block.as_ptr().cast::<MaybeUninit<u8>>(), layout.size())
+    }
+
+    unsafe fn resolve_mut(&mut self, (): Self::Handle, layout: Layout) -> &mut Memory {
+        std::slice::from_raw_parts_mut(
+            self.block.as_mut_ptr().cast::<MaybeUninit<u8>>(),
+            layout.size(),
+        )
+    }
+
+    unsafe fn grow(&mut self, (): Self::Handle, _: Layout, new: Layout) -> Result<(), AllocError> {
+        if new.size() > size_of::<[u64; 4]>() {
+            return Err(AllocError);
+        }
+        Ok(())
+    }
+
+    unsafe fn shrink(&mut self, (): Self::Handle, _: Layout, _: Layout) -> Result<(), AllocError> {
+        Ok(())
+    }
+}
+
+#[test]
+fn deferred_empty_allocation() {
+    let taken = Cell::new(false);
+    let storage = OneBlock {
+        block: MaybeUninit::uninit(),
+        taken: &taken,
+    };
+    let mut vec = RawVec::<u64, _>::new(storage).unwrap_or_else(|_| panic!());
+    assert!(!taken.get());
+    assert_eq!(vec.as_ref().len(), 0);
+    assert_eq!(vec.as_mut().as_ptr() as usize % 8, 0);
+
+    vec.grow_to(1).unwrap();
+    assert!(taken.get());
+    vec.as_mut()[0].write(1);
+    vec.grow_to(4).unwrap();
+    assert_eq!(unsafe { vec.as_ref()[0].assume_init() }, 1);
+
+    vec.shrink_to(0).unwrap();
+    assert!(!taken.get());
+    vec.grow_to(2).unwrap();
+    assert!(taken.get());
+    drop(vec);
+    assert!(!taken.get());
+}
+
+#[test]
+fn shift_insert_remove() {
+    let mut vec = RawVec::<u16, _>::new_in(InlineStorage::<[u16; 8]>::new());
+    vec.grow_to(5).unwrap();
+    for (i, slot) in vec.as_mut()[..4].iter_mut().enumerate() {
+        slot.write(i as u16);
+    }
+
+    // Insert at 1, with one spare element at the end.
+    unsafe { vec.shift_right(1, 1) };
+    vec.as_mut()[1].write(9);
+    assert_eq!(unsafe { vec.split_at_spare_mut(5) }.0, [0, 9, 1, 2, 3]);
+
+    // Remove the two elements at 2.
+    unsafe { vec.shift_left(2, 2) };
+    assert_eq!(unsafe { vec.split_at_spare_mut(3) }.0, [0, 9, 3]);
+
+    // Shifting nothing, or by nothing, is fine.
+    unsafe { vec.shift_right(5, 0) };
+    unsafe { vec.shift_left(0, 0) };
+    assert_eq!(unsafe { vec.split_at_spare_mut(3) }.0, [0, 9, 3]);
+}
+
+#[test]
+fn debug_layout() {
+    let mut vec = RawVec::<u32, _>::new_in(AllocStorage::new(Global));
+    vec.grow_to(4).unwrap();
+    let debug = format!("{vec:?}");
+    assert!(debug.contains("len: 4"), "{debug}");
+    assert!(debug.contains("size: 16"), "{debug}");
+}
@@ -0,0 +1,95 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        cell::Cell,
+        prelude::rust_2021::*,
+        ptr::NonNull,
+    },
+    storage_api::{AllocStorage, Rc},
+};
+
+/// An allocator which counts its live allocations.
+#[derive(Default)]
+struct LiveAlloc(Cell<usize>);
+
+unsafe impl Allocator for LiveAlloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.set(self.0.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.0.set(self.0.get() - 1);
+        Global.deallocate(ptr, layout)
+    }
+}
+
+struct SetOnDrop<'a>(&'a Cell<bool>);
+
+impl Drop for SetOnDrop<'_> {
+    fn drop(&mut self) {
+        assert!(!self.0.replace(true), "dropped twice");
+    }
+}
+
+#[test]
+fn shared_value() {
+    let rc = Rc::new_in(String::from("shared"), AllocStorage::new(Global));
+    let other = rc.clone();
+    assert_eq!(*other, "shared");
+    assert_eq!(Rc::strong_count(&rc), 2);
+    drop(other);
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
+
+#[test]
+fn upgrade_after_drop() {
+    let alloc = LiveAlloc::default();
+    let dropped = Cell::new(false);
+
+    let rc = Rc::new_in(SetOnDrop(&dropped), AllocStorage::new(&alloc));
+    let weak = Rc::downgrade(&rc);
+    assert_eq!(Rc::weak_count(&rc), 1);
+    assert!(weak.upgrade().is_some());
+    assert_eq!(weak.strong_count(), 1);
+
+    drop(rc);
+    assert!(dropped.get());
+    assert!(weak.upgrade().is_none());
+    assert_eq!(weak.strong_count(), 0);
+
+    // The control block is only deallocated with the last weak reference.
+    let other = weak.clone();
+    drop(weak);
+    assert_eq!(alloc.0.get(), 1);
+    drop(other);
+    assert_eq!(alloc.0.get(), 0);
+}
+
+#[test]
+fn deallocated_with_last_strong() {
+    let alloc = LiveAlloc::default();
+    let rc = Rc::new_in(1u32, AllocStorage::new(&alloc));
+    let weak = Rc::downgrade(&rc);
+    drop(weak);
+    assert_eq!(alloc.0.get(), 1);
+    drop(rc);
+    assert_eq!(alloc.0.get(), 0);
+}
+
+#[test]
+fn deref_outlives_new_pointers() {
+    let rc = Rc::new_in(7u32, AllocStorage::new(Global));
+    let value = &*rc;
+    let other = rc.clone();
+    let weak = Rc::downgrade(&rc);
+    let upgraded = weak.upgrade().unwrap();
+    assert_eq!(*value, 7);
+    assert_eq!(*other, 7);
+    assert_eq!(*upgraded, 7);
+    assert_eq!(*value, 7);
+}
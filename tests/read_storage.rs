@@ -0,0 +1,71 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{Global, Layout},
+        mem::{align_of, size_of},
+    },
+    storage_api::{AllocStorage, RawRef, ReadStorage, RoBorrowedStorage, Storage},
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    magic: u32,
+    version: u16,
+    flags: u16,
+}
+
+#[repr(C, align(4))]
+struct Image([u8; 16]);
+
+#[test]
+fn read_struct() {
+    let mut image = Image([0; 16]);
+    image.0[4..8].copy_from_slice(&0xC0FFEEu32.to_ne_bytes());
+    image.0[8..10].copy_from_slice(&3u16.to_ne_bytes());
+    image.0[10..12].copy_from_slice(&1u16.to_ne_bytes());
+    let storage = RoBorrowedStorage::from_bytes(&image.0);
+
+    let header = RawRef::<Header, _>::at(storage, 4, ()).unwrap();
+    assert_eq!(
+        unsafe { header.as_ref().assume_init() },
+        Header {
+            magic: 0xC0FFEE,
+            version: 3,
+            flags: 1,
+        }
+    );
+
+    let bytes = RawRef::<[u8], _>::at(storage, 8, 8).unwrap();
+    assert_eq!(unsafe { &*bytes.as_ptr() }, &image.0[8..]);
+
+    // Misaligned, and out of bounds.
+    assert!(align_of::<Header>() > 1);
+    assert!(RawRef::<Header, _>::at(storage, 2, ()).is_none());
+    assert!(RawRef::<Header, _>::at(storage, 16 - size_of::<Header>() + 4, ()).is_none());
+    assert!(RawRef::<[u8], _>::at(storage, 8, 9).is_none());
+}
+
+#[test]
+fn resolve_with_both_traits_in_scope() {
+    let layout = Layout::new::<u32>();
+    let mut storage = AllocStorage::new(Global);
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        storage
+            .resolve_mut(handle, layout)
+            .as_mut_ptr()
+            .cast::<u32>()
+            .write(7);
+        let memory = storage.resolve(handle, layout);
+        assert_eq!(memory.as_ptr().cast::<u32>().read(), 7);
+        assert_eq!(
+            storage.resolve_read(handle, layout).as_ptr(),
+            memory.as_ptr()
+        );
+        storage.deallocate(handle, layout);
+    }
+}
@@ -0,0 +1,53 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::alloc::{Global, Layout},
+    storage_api::{AllocStorage, CountingStorage, InlineStorage, ScratchStorage, Storage},
+};
+
+#[test]
+fn reuses_allocation() {
+    let layout = Layout::new::<[u64; 8]>();
+    let mut storage = ScratchStorage::new(CountingStorage::new(AllocStorage::new(Global)));
+    for i in 0..16 {
+        let handle = storage.allocate(layout).unwrap();
+        unsafe {
+            storage
+                .resolve_mut(handle, layout)
+                .as_mut_ptr()
+                .cast::<u64>()
+                .write(i);
+            storage.deallocate(handle, layout);
+        }
+    }
+    assert_eq!(storage.inner().allocations(), 1);
+    assert_eq!(storage.inner().deallocations(), 0);
+}
+
+#[test]
+fn other_layouts_bypass_cache() {
+    let small = Layout::new::<u32>();
+    let large = Layout::new::<[u32; 16]>();
+    let mut storage = ScratchStorage::new(CountingStorage::new(AllocStorage::new(Global)));
+    unsafe {
+        let handle = storage.allocate(small).unwrap();
+        storage.deallocate(handle, small);
+        let handle = storage.allocate(large).unwrap();
+        storage.deallocate(handle, large);
+    }
+    assert_eq!(storage.inner().allocations(), 2);
+    assert_eq!(storage.inner().deallocations(), 1);
+    assert_eq!(storage.inner().live_bytes(), large.size());
+}
+
+#[test]
+fn needs_layout_for_dealloc() {
+    // Both keep the layout, even if the inner storage ignores it.
+    let needs = [
+        ScratchStorage::<InlineStorage<u32>>::NEEDS_LAYOUT_FOR_DEALLOC,
+        CountingStorage::<InlineStorage<u32>>::NEEDS_LAYOUT_FOR_DEALLOC,
+    ];
+    assert_eq!(needs, [true, true]);
+}
@@ -0,0 +1,210 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Global, Layout},
+        cell::UnsafeCell,
+        mem::MaybeUninit,
+        ptr,
+    },
+    storage_api::{
+        AllocStorage, Box, BumpStorage, CheckedSharedStorage, Memory, MultipleStorage,
+        SharedMutabilityStorage, Storage,
+    },
+};
+
+/// A storage of two slots, which moves memory to the other slot to grow.
+struct TwoSlots {
+    slots: UnsafeCell<[MaybeUninit<[u64; 4]>; 2]>,
+    used: [bool; 2],
+}
+
+impl TwoSlots {
+    const SLOT: Layout = Layout::new::<[u64; 4]>();
+
+    fn new() -> Self {
+        TwoSlots {
+            slots: UnsafeCell::new([MaybeUninit::uninit(); 2]),
+            used: [false; 2],
+        }
+    }
+
+    fn fits(layout: Layout) -> bool {
+        layout.size() <= Self::SLOT.size() && layout.align() <= Self::SLOT.align()
+    }
+}
+
+unsafe impl Storage for TwoSlots {
+    type Handle = usize;
+
+    fn allocate(&mut self, layout: Layout) -> Result<Self::Handle, AllocError> {
+        if !Self::fits(layout) {
+            return Err(AllocError);
+        }
+        let index = self.used.iter().position(|&used| !used).ok_or(AllocError)?;
+        self.used[index] = true;
+        Ok(index)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, _: Layout) {
+        self.used[handle] = false;
+    }
+
+    unsafe fn resolve(&self, handle: Self::Handle, layout: Layout) -> &Memory {
+        self.resolve_raw(handle, layout)
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        self.resolve_raw(handle, layout)
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.grow_by_copy(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<Self::Handle, AllocError> {
+        self.shrink_by_copy(handle, old_layout, new_layout)
+    }
+}
+
+unsafe impl SharedMutabilityStorage for TwoSlots {
+    unsafe fn resolve_raw(&self, handle: Self::Handle, layout: Layout) -> &mut Memory {
+        let slot = self.slots.get().cast::<[u64; 4]>().add(handle);
+        &mut *ptr::slice_from_raw_parts_mut(slot.cast::<MaybeUninit<u8>>(), layout.size())
+    }
+}
+
+#[test]
+fn grow_and_shrink_by_copy() {
+    let small = Layout::new::<[u64; 2]>();
+    let mut storage = TwoSlots::new();
+    unsafe {
+        let handle = storage.allocate(small).unwrap();
+        storage
+            .resolve_mut(handle, small)
+            .as_mut_ptr()
+            .cast::<[u64; 2]>()
+            .write([1, 2]);
+
+        let grown = storage.grow(handle, small, TwoSlots::SLOT).unwrap();
+        assert_ne!(grown, handle);
+        let memory = storage.resolve(grown, TwoSlots::SLOT);
+        assert_eq!(memory.as_ptr().cast::<[u64; 2]>().read(), [1, 2]);
+
+        let shrunk = storage.shrink(grown, TwoSlots::SLOT, small).unwrap();
+        assert_eq!(shrunk, handle);
+        let memory = storage.resolve(shrunk, small);
+        assert_eq!(memory.as_ptr().cast::<[u64; 2]>().read(), [1, 2]);
+
+        // Both slots are needed to move the memory.
+        let _other = storage.allocate(small).unwrap();
+        assert!(storage.grow(shrunk, small, TwoSlots::SLOT).is_err());
+    }
+}
+
+#[test]
+fn boxes_sharing_storage() {
+    let storage = AllocStorage::new(Global);
+    let mut a = Box::new_in(1u32, &storage);
+    let mut b = Box::new_in(2u32, &storage);
+    *a += 10;
+    *b += 20;
+    assert_eq!((*a, *b), (11, 22));
+
+    let mut shared = &storage;
+    let layout = Layout::new::<u64>();
+    let handle = shared.allocate(layout).unwrap();
+    unsafe {
+        let [x] = shared.resolve_many_mut([(handle, layout)]);
+        x.as_mut_ptr().cast::<u64>().write(3);
+        assert_eq!(
+            storage
+                .resolve_raw(handle, layout)
+                .as_ptr()
+                .cast::<u64>()
+                .read(),
+            3
+        );
+        shared.deallocate(handle, layout);
+    }
+}
+
+#[test]
+fn checked_sequential_resolves() {
+    let layout = Layout::new::<u64>();
+    let mut storage = CheckedSharedStorage::new(AllocStorage::new(Global));
+    let a = storage.allocate(layout).unwrap();
+    let b = storage.allocate(layout).unwrap();
+    unsafe {
+        storage
+            .resolve_checked(a, layout)
+            .as_mut_ptr()
+            .cast::<u64>()
+            .write(1);
+        storage
+            .resolve_checked(b, layout)
+            .as_mut_ptr()
+            .cast::<u64>()
+            .write(2);
+        let a_value = storage.resolve(a, layout).as_ptr().cast::<u64>().read();
+        let b_value = storage.resolve_raw(b, layout).as_ptr().cast::<u64>().read();
+        assert_eq!((a_value, b_value), (1, 2));
+        storage.deallocate(a, layout);
+        storage.deallocate(b, layout);
+    }
+}
+
+#[test]
+fn checked_overlapping_raw_resolves() {
+    // Raw resolves aren't checked, so this doesn't panic (and doesn't alias).
+    let layout = Layout::new::<u64>();
+    let mut storage = CheckedSharedStorage::new(AllocStorage::new(Global));
+    let a = storage.allocate(layout).unwrap();
+    let b = storage.allocate(layout).unwrap();
+    unsafe {
+        let a_memory = storage.resolve_raw(a, layout);
+        let b_memory = storage.resolve_raw(b, layout);
+        a_memory.as_mut_ptr().cast::<u64>().write(1);
+        b_memory.as_mut_ptr().cast::<u64>().write(2);
+        let a_value = a_memory.as_ptr().cast::<u64>().read();
+        assert_eq!(a_value, 1);
+        storage.deallocate(a, layout);
+        storage.deallocate(b, layout);
+    }
+}
+
+#[test]
+#[should_panic = "mutably resolved twice at once"]
+fn checked_overlapping_resolves() {
+    let layout = Layout::new::<u64>();
+    let mut storage = CheckedSharedStorage::new(BumpStorage::<[u64; 1]>::new());
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        let _first = storage.resolve_checked(handle, layout);
+        let _second = storage.resolve_checked(handle, layout);
+    }
+}
+
+#[test]
+#[should_panic = "resolved while mutably resolved"]
+fn checked_resolve_while_resolved() {
+    let layout = Layout::new::<u64>();
+    let mut storage = CheckedSharedStorage::new(BumpStorage::<[u64; 1]>::new());
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        let _guard = storage.resolve_checked(handle, layout);
+        storage.resolve(handle, layout);
+    }
+}
@@ -0,0 +1,286 @@
+#![feature(allocator_api)]
+// `SingleHandle` is `()` without the `checked` feature.
+#![allow(clippy::let_unit_value)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        mem::{align_of, size_of, MaybeUninit},
+        ptr::NonNull,
+    },
+    storage_api::{
+        Align1, Align4096, Align64, Alignment, AllocStorage, BorrowedStorage, InlineLayoutStorage,
+        InlineOrBorrowed, InlineStorage, LayoutDataStore, SingleHandle, SmallStorage, Storage,
+        SupportedAlignment,
+    },
+};
+
+#[test]
+fn reallocate_after_deallocate() {
+    let layout = Layout::new::<u32>();
+    let mut storage = InlineStorage::<u32>::new();
+    let handle = storage.allocate(layout).unwrap();
+    unsafe { storage.deallocate(handle, layout) };
+    storage.allocate(layout).unwrap();
+
+    let mut memory = MaybeUninit::<u32>::uninit();
+    let mut storage = BorrowedStorage::new(&mut memory);
+    let handle = storage.allocate(layout).unwrap();
+    unsafe { storage.deallocate(handle, layout) };
+    storage.allocate(layout).unwrap();
+}
+
+#[test]
+fn needs_layout_for_dealloc() {
+    let needs = [
+        InlineStorage::<u32>::NEEDS_LAYOUT_FOR_DEALLOC,
+        BorrowedStorage::<u32>::NEEDS_LAYOUT_FOR_DEALLOC,
+        AllocStorage::<Global>::NEEDS_LAYOUT_FOR_DEALLOC,
+        SmallStorage::<u32, Global>::NEEDS_LAYOUT_FOR_DEALLOC,
+    ];
+    assert_eq!(needs, [false, false, true, true]);
+
+    let mut storage = InlineStorage::<u32>::new();
+    let handle = storage.allocate(Layout::new::<u32>()).unwrap();
+    unsafe { storage.deallocate(handle, Layout::new::<()>()) };
+    storage.allocate(Layout::new::<u32>()).unwrap();
+}
+
+#[test]
+fn current_handle() {
+    // The built-in single storages don't track whether they are allocated.
+    let layout = Layout::new::<u32>();
+    let mut storage = InlineStorage::<u32>::new();
+    assert_eq!(storage.current_handle(), None);
+    let handle = storage.allocate(layout).unwrap();
+    assert_eq!(storage.current_handle(), None);
+    unsafe { storage.deallocate(handle, layout) };
+
+    assert_eq!(AllocStorage::new(Global).current_handle(), None);
+}
+
+#[test]
+#[cfg(feature = "checked")]
+#[should_panic = "already allocated"]
+fn inline_double_allocate() {
+    let layout = Layout::new::<u32>();
+    let mut storage = InlineStorage::<u32>::new();
+    storage.allocate(layout).unwrap();
+    let _ = storage.allocate(layout);
+}
+
+#[test]
+#[cfg(feature = "checked")]
+#[should_panic = "already allocated"]
+fn borrowed_double_allocate() {
+    let layout = Layout::new::<u32>();
+    let mut memory = MaybeUninit::<u32>::uninit();
+    let mut storage = BorrowedStorage::new(&mut memory);
+    storage.allocate(layout).unwrap();
+    let _ = storage.allocate(layout);
+}
+
+#[test]
+fn borrowed_too_big_or_too_aligned() {
+    let mut memory = MaybeUninit::<[u32; 2]>::uninit();
+    let mut storage = BorrowedStorage::new(&mut memory);
+    let available = storage.available_layout();
+    assert_eq!(available, Layout::new::<[u32; 2]>());
+
+    let too_big = Layout::new::<[u32; 3]>();
+    assert!(too_big.size() > available.size());
+    assert!(!storage.fits(too_big));
+    assert!(storage.allocate(too_big).is_err());
+
+    let too_aligned = Layout::new::<u64>();
+    assert!(too_aligned.size() <= available.size());
+    assert!(too_aligned.align() > available.align());
+    assert!(!storage.fits(too_aligned));
+    assert!(storage.allocate(too_aligned).is_err());
+
+    // Failing to allocate leaves the storage unallocated.
+    storage.allocate(available).unwrap();
+}
+
+#[test]
+fn small_spills_outline() {
+    let mut storage = SmallStorage::<[usize; 2], _>::new(Global);
+    assert_eq!(storage.inline_capacity(), 2 * size_of::<usize>());
+
+    let mut layout = Layout::array::<usize>(1).unwrap();
+    let mut handle = storage.allocate(layout).unwrap();
+    assert!(storage.is_inline(layout));
+    for len in 2..=4 {
+        let new_layout = Layout::array::<usize>(len).unwrap();
+        handle = unsafe { storage.grow(handle, layout, new_layout) }.unwrap();
+        layout = new_layout;
+        assert_eq!(storage.is_inline(layout), len <= 2);
+    }
+    unsafe { storage.deallocate(handle, layout) };
+}
+
+#[test]
+fn inline_or_borrowed_spills_to_scratch() {
+    let mut scratch = MaybeUninit::<[u64; 4]>::uninit();
+    let scratch_ptr = scratch.as_ptr().cast::<MaybeUninit<u8>>();
+    let mut storage = InlineOrBorrowed::<u32, _>::new(&mut scratch);
+
+    let small = Layout::new::<u32>();
+    let handle = storage.allocate(small).unwrap();
+    assert!(storage.is_inline(small));
+    let memory = unsafe { storage.resolve(handle, small) };
+    assert_ne!(memory.as_ptr(), scratch_ptr);
+    unsafe { storage.deallocate(handle, small) };
+
+    let large = Layout::new::<[u64; 4]>();
+    let handle = storage.allocate(large).unwrap();
+    assert!(!storage.is_inline(large));
+    let memory = unsafe { storage.resolve(handle, large) };
+    assert_eq!(memory.as_ptr(), scratch_ptr);
+    unsafe { storage.deallocate(handle, large) };
+
+    assert!(storage.allocate(Layout::new::<[u64; 5]>()).is_err());
+}
+
+#[test]
+fn small_undersized_never_spills() {
+    /// An allocator which must not be used.
+    struct Unused;
+
+    unsafe impl Allocator for Unused {
+        fn allocate(&self, _layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            panic!("allocated with no room for the outline handle")
+        }
+
+        unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
+            unreachable!()
+        }
+    }
+
+    let mut storage = SmallStorage::<u8, _>::new(Unused);
+    let handle = storage.allocate(Layout::new::<u8>()).unwrap();
+    unsafe { storage.deallocate(handle, Layout::new::<u8>()) };
+    assert!(storage.allocate(Layout::new::<u64>()).is_err());
+
+    // Byte arrays have room, but aren't aligned for the handle.
+    let mut storage = SmallStorage::<[u8; 32], _>::new(Unused);
+    assert!(storage.allocate(Layout::new::<[u8; 64]>()).is_err());
+}
+
+#[test]
+fn const_constructors() {
+    const SMALL: SmallStorage<usize, Global> = SmallStorage::new(Global);
+    static INLINE: InlineStorage<usize> = InlineStorage::new();
+
+    let mut storage = SMALL;
+    let handle = storage.allocate(Layout::new::<usize>()).unwrap();
+    assert!(storage.is_inline(Layout::new::<usize>()));
+    unsafe { storage.deallocate(handle, Layout::new::<usize>()) };
+    assert!(INLINE.fits(Layout::new::<usize>()));
+}
+
+#[test]
+fn inline_capacity_const() {
+    const CAPACITY: usize = SmallStorage::<[u8; 32], Global>::INLINE_CAPACITY;
+    assert_eq!(CAPACITY, 32);
+    assert_eq!(
+        SmallStorage::<[u8; 32], Global>::new(Global).inline_capacity(),
+        CAPACITY
+    );
+}
+
+#[test]
+fn small_move_arms() {
+    type Small = SmallStorage<[usize; 2], Global>;
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    fn fill(storage: &mut Small, handle: SingleHandle, layout: Layout) {
+        let memory = unsafe { storage.resolve_mut(handle, layout) };
+        for (i, byte) in memory.iter_mut().enumerate() {
+            byte.write(i as u8);
+        }
+    }
+
+    fn check(storage: &Small, handle: SingleHandle, layout: Layout, len: usize) {
+        let memory = unsafe { storage.resolve(handle, layout) };
+        for (i, byte) in memory[..len].iter().enumerate() {
+            assert_eq!(unsafe { byte.assume_init() }, i as u8);
+        }
+    }
+
+    // Growing and shrinking within, into, and out of the inline storage, and
+    // into and out of it by changing the alignment.
+    let cases = [
+        (layout(8, 8), layout(16, 8)),
+        (layout(16, 8), layout(32, 8)),
+        (layout(32, 8), layout(64, 8)),
+        (layout(8, 16), layout(16, 8)),
+    ];
+    for (small, large) in cases {
+        let mut storage = Small::new(Global);
+        let handle = storage.allocate(small).unwrap();
+        fill(&mut storage, handle, small);
+        let handle = unsafe { storage.grow(handle, small, large) }.unwrap();
+        check(&storage, handle, large, small.size());
+        unsafe { storage.deallocate(handle, large) };
+
+        let mut storage = Small::new(Global);
+        let handle = storage.allocate(large).unwrap();
+        fill(&mut storage, handle, large);
+        let handle = unsafe { storage.shrink(handle, large, small) }.unwrap();
+        check(&storage, handle, small, small.size());
+        unsafe { storage.deallocate(handle, small) };
+    }
+}
+
+#[test]
+fn align_data_stores() {
+    assert_eq!(size_of::<Align64<128>>(), 128);
+    assert_eq!(align_of::<Align64<128>>(), 64);
+    assert_eq!(size_of::<Align4096<1>>(), 4096);
+    assert_eq!(size_of::<Align1<3>>(), 3);
+
+    let mut storage = InlineStorage::<Align64<128>>::new();
+    let layout = Layout::from_size_align(128, 64).unwrap();
+    assert!(storage.fits(layout));
+    assert!(!storage.fits(Layout::from_size_align(128, 128).unwrap()));
+    let handle = storage.allocate(layout).unwrap();
+    unsafe {
+        assert_eq!(storage.resolve(handle, layout).as_ptr() as usize % 64, 0);
+        storage.deallocate(handle, layout);
+    }
+}
+
+#[test]
+fn inline_layout_storage() {
+    fn check<const SIZE: usize, const ALIGN: usize>()
+    where
+        Alignment<ALIGN>: SupportedAlignment,
+    {
+        let storage = InlineLayoutStorage::<SIZE, ALIGN>::new();
+        // The size is rounded up to a multiple of the alignment.
+        let rounded_size = size_of::<LayoutDataStore<SIZE, ALIGN>>();
+        assert_eq!(rounded_size & (ALIGN - 1), 0);
+        assert!(SIZE <= rounded_size && rounded_size < SIZE + ALIGN);
+        assert!(storage.fits(Layout::from_size_align(SIZE, ALIGN).unwrap()));
+        assert!(storage.fits(Layout::from_size_align(rounded_size, 1).unwrap()));
+        assert!(!storage.fits(Layout::from_size_align(SIZE.max(1), ALIGN * 2).unwrap()));
+        assert!(!storage.fits(Layout::from_size_align(rounded_size + 1, 1).unwrap()));
+    }
+
+    check::<0, 1>();
+    check::<3, 1>();
+    check::<8, 8>();
+    check::<24, 8>();
+    check::<100, 64>();
+    check::<4096, 4096>();
+
+    let storage = InlineLayoutStorage::<3, 8>::new();
+    assert!(storage.fits(Layout::new::<u64>()));
+    assert!(!storage.fits(Layout::new::<[u64; 2]>()));
+}
@@ -0,0 +1,89 @@
+#![feature(allocator_api)]
+// `SingleHandle` is `()` without the `checked` feature.
+#![allow(clippy::let_unit_value)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::{Global, Layout},
+        hint,
+        prelude::rust_2021::*,
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+    },
+    storage_api::{AllocStorage, InlineStorage, RawBox, RawMutex, Storage, SyncStorage},
+};
+
+struct SpinLock(AtomicBool);
+
+unsafe impl RawMutex for SpinLock {
+    fn lock(&self) {
+        while self
+            .0
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+type SharedArena = SyncStorage<SpinLock, AllocStorage<Global>>;
+
+#[test]
+fn shared_references() {
+    let arena: SharedArena =
+        SyncStorage::new(SpinLock(AtomicBool::new(false)), AllocStorage::new(Global));
+    let (a, b) = (&arena, &arena);
+    unsafe {
+        let mut one = RawBox::<u32, _>::new((), a).unwrap_or_else(|_| panic!());
+        let mut two = RawBox::<u64, _>::new((), b).unwrap_or_else(|_| panic!());
+        one.as_mut_ptr().write(1);
+        two.as_mut_ptr().write(2);
+        assert_eq!(one.as_ptr().read(), 1);
+        assert_eq!(two.as_ptr().read(), 2);
+    }
+}
+
+#[test]
+fn shared_between_threads() {
+    let arena: SharedArena =
+        SyncStorage::new(SpinLock(AtomicBool::new(false)), AllocStorage::new(Global));
+    thread::scope(|scope| {
+        for i in 0..4 {
+            let arena = &arena;
+            scope.spawn(move || unsafe {
+                let mut boxed = RawBox::<usize, _>::new((), arena).unwrap_or_else(|_| panic!());
+                boxed.as_mut_ptr().write(i);
+                assert_eq!(boxed.as_ptr().read(), i);
+            });
+        }
+    });
+}
+
+#[test]
+fn resolve_twice_inline() {
+    let layout = Layout::new::<u64>();
+    let mut storage = SyncStorage::new(
+        SpinLock(AtomicBool::new(false)),
+        InlineStorage::<u64>::new(),
+    );
+    unsafe {
+        let handle = storage.allocate(layout).unwrap();
+        storage
+            .resolve_mut(handle, layout)
+            .as_mut_ptr()
+            .cast::<u64>()
+            .write(5);
+        let first = storage.resolve(handle, layout);
+        let second = storage.resolve(handle, layout);
+        assert_eq!(first.as_ptr().cast::<u64>().read(), 5);
+        assert_eq!(second.as_ptr().cast::<u64>().read(), 5);
+        storage.deallocate(handle, layout);
+    }
+}
@@ -0,0 +1,55 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{alloc::Global, mem::MaybeUninit, prelude::rust_2021::*, rc::Rc},
+    storage_api::{AllocStorage, BorrowedArena, MultipleStorage, TreeMap},
+};
+
+fn insert_and_get<S: MultipleStorage>(storage: S) {
+    let mut map = TreeMap::new_in(storage);
+    for key in [5, 2, 8, 1, 9, 3, 7, 4, 6, 0] {
+        assert_eq!(map.insert(key, key * 10), None);
+    }
+    assert_eq!(map.len(), 10);
+    for key in 0..10 {
+        assert_eq!(map.get(&key), Some(&(key * 10)));
+    }
+    assert_eq!(map.get(&10), None);
+
+    assert_eq!(map.insert(3, 33), Some(30));
+    *map.get_mut(&4).unwrap() += 4;
+    assert_eq!(map.get(&3), Some(&33));
+    assert_eq!(map.get(&4), Some(&44));
+    assert_eq!(map.len(), 10);
+}
+
+#[test]
+fn alloc() {
+    insert_and_get(AllocStorage::new(Global));
+}
+
+#[test]
+fn arena() {
+    // Aligned by type, not just by address, for Miri's symbolic alignment
+    // check.
+    let mut memory = [MaybeUninit::<u64>::uninit(); 128];
+    let memory = unsafe {
+        &mut *(&mut memory[..] as *mut [MaybeUninit<u64>] as *mut [MaybeUninit<u8>; 1024])
+    };
+    insert_and_get(BorrowedArena::new(memory));
+}
+
+#[test]
+fn drops_values() {
+    let value = Rc::new(());
+    let mut map = TreeMap::new_in(AllocStorage::new(Global));
+    for key in [3, 1, 4, 0, 2, 6, 5] {
+        map.insert(key, value.clone());
+    }
+    map.insert(4, value.clone());
+    assert_eq!(Rc::strong_count(&value), 8);
+    drop(map);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
@@ -1,14 +1,16 @@
 #![feature(allocator_api)]
+// `SingleHandle` is `()` without the `checked` feature.
+#![allow(clippy::let_unit_value)]
 
 extern crate std;
 
 use {
     std::{
         alloc::{AllocError, Allocator, Global},
-        mem::size_of,
+        mem::{align_of, size_of, transmute},
         prelude::rust_2021::*,
     },
-    storage_api::{RawBox, SmallStorage},
+    storage_api::{AllocStorage, RawBox, SmallStorage},
 };
 
 trait Trait {}
@@ -28,6 +30,10 @@ unsafe impl Allocator for NullAlloc {
 type SmallRawBox<T, A> = RawBox<T, SmallStorage<usize, A>>;
 
 #[test]
+#[cfg_attr(
+    feature = "checked",
+    ignore = "checked builds track inline allocation state"
+)]
 fn sizes() {
     assert_eq!(
         size_of::<SmallRawBox<dyn Trait, Global>>(),
@@ -35,6 +41,46 @@ fn sizes() {
     );
 }
 
+#[test]
+#[cfg_attr(
+    feature = "checked",
+    ignore = "checked builds track inline allocation state"
+)]
+fn slice_sizes() {
+    assert_eq!(
+        size_of::<SmallRawBox<[u32], Global>>(),
+        size_of::<Box<[u32]>>()
+    );
+    assert_eq!(
+        align_of::<SmallRawBox<[u32], Global>>(),
+        align_of::<Box<[u32]>>()
+    );
+    assert_eq!(
+        size_of::<SmallRawBox<[u64], Global>>(),
+        2 * size_of::<usize>()
+    );
+}
+
+#[test]
+#[cfg_attr(
+    feature = "checked",
+    ignore = "checked builds carry a generation in the handles"
+)]
+fn alloc_sizes() {
+    assert_eq!(
+        size_of::<RawBox<u32, AllocStorage<Global>>>(),
+        size_of::<Box<u32>>()
+    );
+    assert_eq!(
+        align_of::<RawBox<u32, AllocStorage<Global>>>(),
+        align_of::<Box<u32>>()
+    );
+    assert_eq!(
+        size_of::<RawBox<[u8; 64], AllocStorage<Global>>>(),
+        size_of::<usize>()
+    );
+}
+
 #[test]
 fn does_not_alloc() {
     let storage = SmallStorage::new(NullAlloc);
@@ -43,3 +89,77 @@ fn does_not_alloc() {
         *boxed.as_mut_ptr() = 0usize;
     }
 }
+
+#[test]
+fn alloc_storage_is_transparent() {
+    assert_eq!(size_of::<AllocStorage<Global>>(), 0);
+    assert_eq!(size_of::<AllocStorage<&Global>>(), size_of::<&Global>());
+    assert_eq!(align_of::<AllocStorage<&Global>>(), align_of::<&Global>());
+
+    let _: AllocStorage<Global> = unsafe { transmute::<Global, AllocStorage<Global>>(Global) };
+    let _: AllocStorage<&Global> = unsafe { transmute::<&Global, AllocStorage<&Global>>(&Global) };
+}
+
+// Checked builds track inline allocation state, so the storages aren't
+// transparent there.
+#[test]
+#[cfg(not(feature = "checked"))]
+fn inline_box_repr() {
+    use {
+        std::mem::{size_of_val, transmute_copy, MaybeUninit},
+        storage_api::InlineStorage,
+    };
+
+    /// How C sees a `RawBox<[u8], InlineStorage<[u8; 8]>>`.
+    #[repr(C)]
+    struct CInlineBox {
+        len: usize,
+        bytes: [MaybeUninit<u8>; 8],
+    }
+
+    let mut boxed = RawBox::<[u8], _>::new_checked(3, InlineStorage::<[u8; 8]>::new())
+        .unwrap_or_else(|_| panic!());
+    unsafe { (*boxed.as_mut_ptr()).copy_from_slice(&[1, 2, 3]) };
+
+    assert_eq!(size_of_val(&boxed), size_of::<CInlineBox>());
+    let c = unsafe { transmute_copy::<_, CInlineBox>(&boxed) };
+    assert_eq!(c.len, 3);
+    assert_eq!(
+        unsafe { [c.bytes[0], c.bytes[1], c.bytes[2]].map(|byte| byte.assume_init()) },
+        [1, 2, 3]
+    );
+}
+
+#[test]
+#[cfg(not(feature = "checked"))]
+fn storage_reprs() {
+    use {
+        std::{
+            alloc::Layout,
+            mem::{transmute_copy, MaybeUninit},
+        },
+        storage_api::{BorrowedStorage, InlineStorage, Storage},
+    };
+
+    assert_eq!(size_of::<InlineStorage<[u16; 3]>>(), size_of::<[u16; 3]>());
+    assert_eq!(
+        align_of::<InlineStorage<[u16; 3]>>(),
+        align_of::<[u16; 3]>()
+    );
+    assert_eq!(size_of::<BorrowedStorage<'_, u64>>(), size_of::<&mut u64>());
+    assert_eq!(
+        size_of::<SmallStorage<[usize; 2], Global>>(),
+        size_of::<[usize; 2]>()
+    );
+
+    // Spilled memory is handled by a pointer at the start of the inline bytes.
+    let mut storage = SmallStorage::<[usize; 2], _>::new(Global);
+    let layout = Layout::new::<[u64; 4]>();
+    let handle = storage.allocate(layout).unwrap();
+    let ptr = unsafe { storage.resolve(handle, layout) }.as_ptr();
+    assert_eq!(
+        unsafe { transmute_copy::<_, *const MaybeUninit<u8>>(&storage) },
+        ptr
+    );
+    unsafe { storage.deallocate(handle, layout) };
+}
@@ -0,0 +1,209 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{
+        alloc::Global,
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+        mem,
+        rc::Rc,
+    },
+    storage_api::{AllocStorage, ArrayVec, InlineStorage, RawVec, Vec},
+};
+
+fn vec_of(values: &[u32]) -> Vec<u32, AllocStorage<Global>> {
+    let mut vec = Vec::new_in(AllocStorage::new(Global));
+    for &value in values {
+        vec.push(value);
+    }
+    vec
+}
+
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn push_pop() {
+    let mut vec = vec_of(&[1, 2, 3]);
+    assert_eq!(*vec, [1, 2, 3]);
+    assert!(vec.capacity() >= 3);
+    assert_eq!(vec.pop(), Some(3));
+    vec[0] = 10;
+    assert_eq!(*vec, [10, 2]);
+    assert_eq!(vec.pop(), Some(2));
+    assert_eq!(vec.pop(), Some(10));
+    assert_eq!(vec.pop(), None);
+}
+
+#[test]
+fn comparisons() {
+    assert!(vec_of(&[1, 2]) == vec_of(&[1, 2]));
+    assert!(vec_of(&[1, 2]) != vec_of(&[1, 2, 3]));
+    assert!(vec_of(&[1, 2]) < vec_of(&[1, 3]));
+    assert!(vec_of(&[1, 2]) < vec_of(&[1, 2, 0]));
+    assert_eq!(hash_of(vec_of(&[1, 2])), hash_of(vec_of(&[1, 2])));
+    assert_eq!(hash_of(vec_of(&[1, 2])), hash_of([1u32, 2].as_slice()));
+
+    // Vecs in different storages still compare equal.
+    let mut inline = Vec::new_in(InlineStorage::<[u32; 4]>::new());
+    inline.push(1);
+    inline.push(2);
+    assert!(vec_of(&[1, 2]) == inline);
+}
+
+#[test]
+fn fill_spare() {
+    let mut vec = vec_of(&[0, 1]);
+    vec.reserve(8);
+    assert!(vec.capacity() >= 10);
+
+    let (init, spare) = vec.split_at_spare_mut();
+    assert_eq!(init, [0, 1]);
+    let (first, second) = spare[..8].split_at_mut(4);
+    for (part, offset) in [(first, 2), (second, 6)] {
+        for (i, slot) in part.iter_mut().enumerate() {
+            slot.write(offset + i as u32);
+        }
+    }
+    unsafe { vec.set_len(10) };
+    assert_eq!(*vec, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    let mut raw = RawVec::<u32, _>::new_in(AllocStorage::new(Global));
+    raw.grow_to(3).unwrap();
+    raw.as_mut()[0].write(7);
+    let (init, spare) = unsafe { raw.split_at_spare_mut(1) };
+    assert_eq!(init, [7]);
+    assert_eq!(spare.len(), 2);
+}
+
+#[test]
+fn std_vec_round_trip() {
+    let mut std_vec = std::vec::Vec::with_capacity(10);
+    std_vec.extend([1u32, 2, 3]);
+    let capacity = std_vec.capacity();
+
+    let mut vec = Vec::from(std_vec);
+    assert_eq!(vec.len(), 3);
+    assert_eq!(vec.capacity(), capacity);
+    assert_eq!(*vec, [1, 2, 3]);
+    vec.push(4);
+
+    let std_vec = vec.into_std_vec();
+    assert_eq!(std_vec, [1, 2, 3, 4]);
+    assert_eq!(std_vec.capacity(), capacity);
+
+    let empty = Vec::<u32, _>::new_in(AllocStorage::new(Global)).into_std_vec();
+    assert_eq!(empty.capacity(), 0);
+    let zsts = Vec::from(std::vec![(); 5]);
+    assert_eq!(zsts.len(), 5);
+    assert_eq!(zsts.into_std_vec().len(), 5);
+}
+
+#[test]
+fn raw_parts_round_trip() {
+    let mut vec = vec_of(&[1, 2, 3]);
+    vec.reserve(7);
+    let capacity = vec.capacity();
+    assert!(capacity >= 10);
+
+    let (handle, len, cap, storage) = vec.into_raw_parts();
+    assert!(handle.is_some());
+    assert_eq!((len, cap), (3, capacity));
+    let mut vec = unsafe { Vec::<u32, _>::from_raw_parts(handle, len, cap, storage) };
+    assert_eq!(*vec, [1, 2, 3]);
+    assert_eq!(vec.capacity(), capacity);
+    vec.push(4);
+    assert_eq!(*vec, [1, 2, 3, 4]);
+
+    let raw =
+        RawVec::<u16, _>::with_align(64, AllocStorage::new(Global)).unwrap_or_else(|_| panic!());
+    let (handle, len, align, storage) = raw.into_raw_parts();
+    assert_eq!((len, align), (0, 64));
+    let mut raw = unsafe { RawVec::<u16, _>::from_raw_parts(handle, len, align, storage) };
+    raw.grow_to(4).unwrap();
+    assert_eq!(raw.as_ref().as_ptr() as usize % 64, 0);
+}
+
+fn full_array_vec() -> ArrayVec<u32, 3> {
+    let mut vec = ArrayVec::new();
+    assert_eq!(vec.capacity(), 3);
+    for i in 0..3 {
+        vec.push(i);
+    }
+    vec
+}
+
+#[test]
+fn array_vec_full_capacity() {
+    let vec = full_array_vec();
+    assert_eq!(*vec, [0, 1, 2]);
+    assert_eq!(vec.capacity(), 3);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic = "memory allocation of 16 bytes failed"]
+fn array_vec_overflow() {
+    // Unwind instead of aborting in the alloc error handler.
+    storage_api::set_alloc_error_hook(|layout, _| {
+        panic!("memory allocation of {} bytes failed", layout.size())
+    });
+    full_array_vec().push(3);
+}
+
+#[test]
+fn try_push_overflow() {
+    let mut vec = ArrayVec::<u32, 3>::new();
+    for i in 0..3 {
+        assert_eq!(vec.try_push(i), Ok(()));
+    }
+    assert_eq!(vec.try_push(3), Err(3));
+    assert_eq!(*vec, [0, 1, 2]);
+    assert_eq!(vec.pop(), Some(2));
+    assert_eq!(vec.try_push(4), Ok(()));
+    assert_eq!(*vec, [0, 1, 4]);
+
+    // Doubling would skip from 4 to 8, past what fits inline.
+    let mut inline = Vec::new_in(InlineStorage::<[u32; 5]>::new());
+    for i in 0..5 {
+        assert_eq!(inline.try_push(i), Ok(()));
+    }
+    assert_eq!(inline.try_push(5), Err(5));
+    assert_eq!(inline.capacity(), 5);
+}
+
+#[test]
+fn array_vec_drops() {
+    let rc = Rc::new(());
+    let mut vec = ArrayVec::<Rc<()>, 4>::new();
+    while vec.try_push(rc.clone()).is_ok() {}
+    assert_eq!(Rc::strong_count(&rc), 5);
+    drop(vec.pop());
+    assert_eq!(Rc::strong_count(&rc), 4);
+    drop(vec);
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
+
+#[test]
+fn outlives_borrow() {
+    // Dropping a borrow doesn't use it, so the vec may outlive the value.
+    let mut vec;
+    let value = 7;
+    vec = Vec::new_in(AllocStorage::new(Global));
+    vec.push(&value);
+    assert_eq!(*vec, [&7]);
+}
+
+#[test]
+fn take() {
+    let mut vec = vec_of(&[1, 2, 3]);
+    let taken = mem::take(&mut vec);
+    assert_eq!(vec.len(), 0);
+    assert_eq!(vec.capacity(), 0);
+    assert_eq!(*taken, [1, 2, 3]);
+}
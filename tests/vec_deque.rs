@@ -0,0 +1,88 @@
+#![feature(allocator_api)]
+
+extern crate std;
+
+use {
+    std::{alloc::Global, prelude::rust_2021::*, rc::Rc},
+    storage_api::{AllocStorage, Storage, VecDeque},
+};
+
+fn contents<T: Clone, S: Storage>(deque: &VecDeque<T, S>) -> Vec<T> {
+    let (front, back) = deque.as_slices();
+    [front, back].concat()
+}
+
+#[test]
+fn both_ends() {
+    let mut deque = VecDeque::new_in(AllocStorage::new(Global));
+    assert_eq!(deque.pop_front(), None::<i32>);
+    assert_eq!(deque.pop_back(), None);
+
+    deque.push_back(1);
+    deque.push_back(2);
+    deque.push_front(0);
+    deque.push_front(-1);
+    assert_eq!(deque.len(), 4);
+    assert_eq!(contents(&deque), [-1, 0, 1, 2]);
+    assert_eq!(deque.get(1), Some(&0));
+    assert_eq!(deque.get(4), None);
+
+    assert_eq!(deque.pop_front(), Some(-1));
+    assert_eq!(deque.pop_back(), Some(2));
+    assert_eq!(deque.pop_back(), Some(1));
+    assert_eq!(deque.pop_back(), Some(0));
+    assert_eq!(deque.pop_back(), None);
+}
+
+#[test]
+fn grow_moves_wrapped_tail() {
+    // [ 4 1 2 3 ] wraps after the head at index 1, with the tail part the
+    // shorter one when it grows.
+    let mut deque = VecDeque::new_in(AllocStorage::new(Global));
+    for i in 0..4 {
+        deque.push_back(i);
+    }
+    deque.pop_front();
+    deque.push_back(4);
+    assert_eq!(deque.capacity(), 4);
+    assert_eq!(deque.as_slices(), (&[1, 2, 3][..], &[4][..]));
+    deque.push_back(5);
+    assert_eq!(deque.capacity(), 8);
+    assert_eq!(deque.as_slices(), (&[1, 2, 3, 4, 5][..], &[][..]));
+}
+
+#[test]
+fn grow_moves_wrapped_head() {
+    // Pushing to the front wraps the head to the end of the buffer, with
+    // most elements in the tail part.
+    let mut deque = VecDeque::new_in(AllocStorage::new(Global));
+    for i in 1..4 {
+        deque.push_back(i);
+    }
+    deque.push_front(0);
+    assert_eq!(deque.as_slices(), (&[0][..], &[1, 2, 3][..]));
+    deque.push_front(-1);
+    assert_eq!(deque.capacity(), 8);
+    assert_eq!(contents(&deque), [-1, 0, 1, 2, 3]);
+    for i in 4..20 {
+        deque.push_back(i);
+        deque.push_front(-i);
+    }
+    assert_eq!(deque.capacity(), 64);
+    let expected: Vec<i32> = (-19..20).filter(|&i| !(-3..=-2).contains(&i)).collect();
+    assert_eq!(contents(&deque), expected);
+}
+
+#[test]
+fn drops_wrapped_elements() {
+    let value = Rc::new(());
+    let mut deque = VecDeque::new_in(AllocStorage::new(Global));
+    for _ in 0..3 {
+        deque.push_back(value.clone());
+        deque.push_front(value.clone());
+    }
+    drop(deque.pop_back());
+    assert_eq!(Rc::strong_count(&value), 6);
+    drop(deque);
+    assert_eq!(Rc::strong_count(&value), 1);
+}